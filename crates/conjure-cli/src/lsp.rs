@@ -0,0 +1,528 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use conjure::{
+    ast::{self, Span},
+    builtins::Builtin,
+    check::{self, Analysis},
+    diagnostic::{self, Severity},
+    resolve::Definition,
+    syntax,
+    typeck::NamedType,
+};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use serde_json::Value;
+
+/// Serves the language server protocol over standard input and output until the client shuts the
+/// server down.
+pub fn run() -> anyhow::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Serves the language server protocol over a connection, from initialization to shutdown.
+fn serve(connection: &Connection) -> anyhow::Result<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => (),
+        }
+    }
+    Ok(())
+}
+
+/// The most recent analysis of a document that parsed, with the text it was made from.
+struct Snapshot {
+    text: String,
+    conjuration: ast::Conjuration,
+    analysis: Analysis,
+}
+
+struct Document {
+    snapshot: Option<Snapshot>,
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server<'_> {
+    fn notification(&mut self, notification: Notification) -> anyhow::Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.update(document.uri, change.text, Some(document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(uri, Vec::new(), None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Url, text: String, version: Option<i32>) -> anyhow::Result<()> {
+        let (diagnostics, snapshot) = match syntax::parse(&text) {
+            Ok(conjuration) => {
+                let analysis = check::check(&conjuration);
+                let diagnostics = analysis
+                    .diagnostics
+                    .iter()
+                    .map(|d| to_lsp_diagnostic(&text, d))
+                    .collect();
+                let snapshot = Snapshot {
+                    text,
+                    conjuration,
+                    analysis,
+                };
+                (diagnostics, Some(snapshot))
+            }
+            Err(diagnostic) => (vec![to_lsp_diagnostic(&text, &diagnostic)], None),
+        };
+        let document = self
+            .documents
+            .entry(uri.clone())
+            .or_insert(Document { snapshot: None });
+        if snapshot.is_some() {
+            document.snapshot = snapshot;
+        }
+        self.publish(uri, diagnostics, version)
+    }
+
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<Diagnostic>,
+        version: Option<i32>,
+    ) -> anyhow::Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))
+            .context("Failed to publish diagnostics")
+    }
+
+    fn respond(&self, id: RequestId, result: Value) -> anyhow::Result<()> {
+        let response = Response::new_ok(id, result);
+        self.connection
+            .sender
+            .send(Message::Response(response))
+            .context("Failed to send response")
+    }
+
+    fn snapshot(&self, uri: &Url) -> Option<&Snapshot> {
+        self.documents.get(uri)?.snapshot.as_ref()
+    }
+
+    fn request(&mut self, request: Request) -> anyhow::Result<()> {
+        use lsp_types::request::Request as _;
+
+        let id = request.id.clone();
+        match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let params: HoverParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let hover = self
+                    .snapshot(&position.text_document.uri)
+                    .and_then(|s| hover(s, position.position));
+                self.respond(id, serde_json::to_value(hover)?)
+            }
+            GotoDefinition::METHOD => {
+                let params: GotoDefinitionParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let definition = self
+                    .snapshot(&uri)
+                    .and_then(|s| definition(s, &uri, position.position));
+                self.respond(id, serde_json::to_value(definition)?)
+            }
+            Completion::METHOD => {
+                let params: CompletionParams = serde_json::from_value(request.params)?;
+                let position = params.text_document_position;
+                let snapshot = self.snapshot(&position.text_document.uri);
+                let completion = completion(snapshot, position.position);
+                self.respond(id, serde_json::to_value(completion)?)
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params: DocumentSymbolParams = serde_json::from_value(request.params)?;
+                let symbols = self
+                    .snapshot(&params.text_document.uri)
+                    .map(document_symbols);
+                self.respond(id, serde_json::to_value(symbols)?)
+            }
+            _ => {
+                let response = Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", request.method),
+                );
+                self.connection
+                    .sender
+                    .send(Message::Response(response))
+                    .context("Failed to send response")
+            }
+        }
+    }
+}
+
+fn hover(snapshot: &Snapshot, position: Position) -> Option<Hover> {
+    let offset = to_offset(&snapshot.text, position);
+    let types = &snapshot.analysis.types;
+    let (span, value) = match named_type(snapshot, offset) {
+        Some((span, named)) => {
+            let name = &snapshot.text[span.start..span.end];
+            let comment = if named.recursive {
+                "// recursive\n"
+            } else {
+                ""
+            };
+            (span, format!("{comment}{name} = type {}", named.ty))
+        }
+        None => {
+            let (span, ty) = types
+                .symbols
+                .iter()
+                .filter(|(span, _)| span.contains(offset))
+                .min_by_key(|(span, _)| span.end - span.start)?;
+            let name = &snapshot.text[span.start..span.end];
+            (*span, format!("{name}: {ty}"))
+        }
+    };
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("```conjure\n{value}\n```"),
+        }),
+        range: Some(to_range(&snapshot.text, span)),
+    })
+}
+
+/// The named type whose symbol, or a reference to it, is at `offset`.
+fn named_type(snapshot: &Snapshot, offset: usize) -> Option<(Span, &NamedType)> {
+    let named = &snapshot.analysis.types.named;
+    if let Some(named) = named.iter().find(|n| n.symbol.span.contains(offset)) {
+        return Some((named.symbol.span, named));
+    }
+    let reference = snapshot.analysis.resolution.definition_at(offset)?;
+    match reference.definition {
+        Definition::Binding(span) => {
+            let named = named.iter().find(|n| n.symbol.span == span)?;
+            Some((reference.symbol.span, named))
+        }
+        _ => None,
+    }
+}
+
+fn definition(
+    snapshot: &Snapshot,
+    uri: &Url,
+    position: Position,
+) -> Option<GotoDefinitionResponse> {
+    let offset = to_offset(&snapshot.text, position);
+    let reference = snapshot.analysis.resolution.definition_at(offset)?;
+    let span = match reference.definition {
+        Definition::Binding(span) | Definition::Component(span) => span,
+        Definition::Builtin(_) | Definition::Native => return None,
+    };
+    Some(GotoDefinitionResponse::Scalar(Location {
+        uri: uri.clone(),
+        range: to_range(&snapshot.text, span),
+    }))
+}
+
+fn completion(snapshot: Option<&Snapshot>, position: Position) -> CompletionResponse {
+    let mut items = Vec::new();
+    if let Some(snapshot) = snapshot {
+        let offset = to_offset(&snapshot.text, position);
+        for (symbol, _) in snapshot.analysis.resolution.visible_at(offset) {
+            if items
+                .iter()
+                .any(|i: &CompletionItem| i.label == symbol.as_str())
+            {
+                continue;
+            }
+            let ty = snapshot.analysis.types.symbols.get(&symbol.span);
+            items.push(CompletionItem {
+                label: symbol.to_string(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: ty.map(ToString::to_string),
+                ..CompletionItem::default()
+            });
+        }
+    }
+    for builtin in Builtin::ALL {
+        if items.iter().any(|i| i.label == builtin.name()) {
+            continue;
+        }
+        let signatures: Vec<_> = builtin
+            .signatures()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        items.push(CompletionItem {
+            label: builtin.name().to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some(signatures.join(" | ")),
+            ..CompletionItem::default()
+        });
+    }
+    CompletionResponse::Array(items)
+}
+
+#[allow(deprecated)] // `DocumentSymbol::deprecated` must be initialized regardless
+fn document_symbols(snapshot: &Snapshot) -> DocumentSymbolResponse {
+    let symbols = snapshot
+        .conjuration
+        .bindings
+        .iter()
+        .flat_map(|binding| {
+            let symbols = binding.manifest.symbols();
+            symbols.into_iter().map(move |symbol| (binding, symbol))
+        })
+        .map(|(binding, symbol)| {
+            let kind = match binding.value {
+                ast::Value::Charm(_) | ast::Value::Spell(_) => SymbolKind::FUNCTION,
+                ast::Value::Type(_) => SymbolKind::TYPE_PARAMETER,
+                _ => SymbolKind::CONSTANT,
+            };
+            let range = to_range(&snapshot.text, symbol.span);
+            DocumentSymbol {
+                name: symbol.to_string(),
+                detail: snapshot
+                    .analysis
+                    .types
+                    .symbols
+                    .get(&symbol.span)
+                    .map(ToString::to_string),
+                kind,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: None,
+            }
+        })
+        .collect();
+    DocumentSymbolResponse::Nested(symbols)
+}
+
+fn to_lsp_diagnostic(text: &str, diagnostic: &diagnostic::Diagnostic) -> Diagnostic {
+    Diagnostic {
+        range: to_range(text, diagnostic.span),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("conjure".to_string()),
+        message: diagnostic.message.clone(),
+        ..Diagnostic::default()
+    }
+}
+
+/// The protocol position of a byte offset. The protocol counts columns in UTF-16 code units.
+fn to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+fn to_range(text: &str, span: Span) -> Range {
+    Range {
+        start: to_position(text, span.start),
+        end: to_position(text, span.end),
+    }
+}
+
+/// The byte offset of a protocol position, whose column counts UTF-16 code units.
+fn to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn columns_count_utf16_code_units() {
+        let text = "a👋b\néx";
+        let cases = [
+            (0, (0, 0)),
+            (1, (0, 1)),
+            (5, (0, 3)),
+            (7, (1, 0)),
+            (9, (1, 1)),
+        ];
+        for (offset, (line, character)) in cases {
+            let position = Position::new(line, character);
+            assert_eq!(to_position(text, offset), position, "{offset}");
+            assert_eq!(to_offset(text, position), offset, "{position:?}");
+        }
+        // Positions inside a character, past the end of a line or past the end of the text.
+        assert_eq!(to_offset(text, Position::new(0, 2)), 5);
+        assert_eq!(to_offset(text, Position::new(0, 99)), 6);
+        assert_eq!(to_offset(text, Position::new(5, 0)), text.len());
+        assert_eq!(to_position(text, text.len()), Position::new(1, 2));
+    }
+
+    struct Client {
+        connection: Connection,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = RequestId::from(self.next_id);
+            let request = Request::new(id.clone(), method.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) if response.id == id => response.result.unwrap(),
+                other => panic!("expected a response to {method}, received {other:?}"),
+            }
+        }
+
+        fn notify(&self, method: &str, params: Value) {
+            let notification = Notification::new(method.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        fn expect_notification(&self, method: &str) -> Value {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification) if notification.method == method => {
+                    notification.params
+                }
+                other => panic!("expected {method}, received {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn scripted_session() {
+        let (server, connection) = Connection::memory();
+        let server = std::thread::spawn(move || serve(&server));
+        let mut client = Client {
+            connection,
+            next_id: 0,
+        };
+
+        let initialized = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(initialized["capabilities"]["hoverProvider"], json!(true));
+        client.notify("initialized", json!({}));
+
+        let uri = "file:///wave.cj";
+        let text = "x = \"👋\"; y = x; w = missing;\n* = spell() -> nil { };\n";
+        client.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "conjure", "version": 1, "text": text }
+            }),
+        );
+        let published = client.expect_notification("textDocument/publishDiagnostics");
+        let diagnostics = published["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": { "line": 0, "character": 21 }, "end": { "line": 0, "character": 28 } })
+        );
+
+        // The `x` after the emoji is at byte 16, but at UTF-16 column 14.
+        let position =
+            json!({ "textDocument": { "uri": uri }, "position": { "line": 0, "character": 14 } });
+        let hover = client.request("textDocument/hover", position.clone());
+        assert_eq!(
+            hover["contents"]["value"],
+            json!("```conjure\nx: phrase\n```")
+        );
+        assert_eq!(
+            hover["range"],
+            json!({ "start": { "line": 0, "character": 14 }, "end": { "line": 0, "character": 15 } })
+        );
+        let definition = client.request("textDocument/definition", position);
+        assert_eq!(
+            definition,
+            json!({
+                "uri": uri,
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } }
+            })
+        );
+
+        assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+        client.notify("exit", Value::Null);
+        server.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use conjure::{
+    ast::{self, Capability, Span},
+    check,
+    diagnostic::{Diagnostic, LineIndex},
+    interpret::{Interpreter, Limits, StdHost},
+    syntax,
+};
+
+mod lsp;
+mod repl;
+
+/// Runs, checks and formats Conjure programs, evaluates them interactively, or serves them to
+/// editors.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Casts the `*` spell of a program
+    Run {
+        /// The program to run
+        file: PathBuf,
+
+        #[command(flatten)]
+        grants: Grants,
+
+        #[command(flatten)]
+        budget: Budget,
+    },
+    /// Reports problems in programs without running them
+    Check {
+        /// The programs to check
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Starts an interactive session
+    Repl {
+        #[command(flatten)]
+        grants: Grants,
+
+        #[command(flatten)]
+        budget: Budget,
+    },
+    /// Formats programs in place
+    Fmt {
+        /// The programs to format
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only report files that are not formatted, without changing them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Starts a language server on standard input and output
+    Lsp,
+}
+
+#[derive(Debug, clap::Args)]
+struct Grants {
+    /// The capabilities spells may use: console, filesystem, clock or randomness
+    #[arg(
+        long,
+        value_name = "CAPABILITY",
+        value_delimiter = ',',
+        value_parser = capability,
+        default_value = "console"
+    )]
+    allow: Vec<Capability>,
+}
+
+#[derive(Debug, clap::Args)]
+struct Budget {
+    /// Stop after this many steps of evaluation
+    #[arg(long, value_name = "STEPS")]
+    fuel: Option<u64>,
+
+    /// Stop when invocations and casts nest deeper than this
+    #[arg(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Stop when the values created take up more than this many bytes in total
+    #[arg(long, value_name = "BYTES")]
+    max_allocation: Option<usize>,
+}
+
+impl From<Budget> for Limits {
+    fn from(budget: Budget) -> Self {
+        Self {
+            fuel: budget.fuel,
+            depth: budget.max_depth,
+            allocation: budget.max_allocation,
+        }
+    }
+}
+
+fn capability(name: &str) -> Result<Capability, String> {
+    Capability::from_name(name).ok_or_else(|| format!("unknown capability `{name}`"))
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Run {
+            file,
+            grants,
+            budget,
+        } => run(&file, grants.allow, budget.into()),
+        Command::Check { files } => check_all(&files),
+        Command::Fmt { files, check } => format_all(&files, check),
+        Command::Repl { grants, budget } => repl::run(grants.allow, budget.into()).map(|()| true),
+        Command::Lsp => lsp::run().map(|()| true),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The number of sites of a runtime error's trace that are reported.
+const TRACE_LIMIT: usize = 10;
+
+/// A source file, kept around to point diagnostics at it.
+struct Source {
+    path: PathBuf,
+    text: String,
+}
+
+impl Source {
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            text,
+        })
+    }
+
+    fn report(&self, diagnostic: &Diagnostic) {
+        if diagnostic.span == Span::default() {
+            eprintln!("{}: {diagnostic}", self.path.display());
+            return;
+        }
+        let index = LineIndex::new(&self.text);
+        let (line, column) = index.position(diagnostic.span.start);
+        eprintln!(
+            "{}:{}:{}: {diagnostic}",
+            self.path.display(),
+            line + 1,
+            column + 1
+        );
+        let Some(text) = self.text.lines().nth(line) else {
+            return;
+        };
+        let (end_line, end_column) = index.position(diagnostic.span.end);
+        let width = if end_line == line {
+            usize::max(1, end_column - column)
+        } else {
+            1
+        };
+        eprintln!("    {text}");
+        eprintln!("    {}{}", " ".repeat(column), "^".repeat(width));
+    }
+
+    /// Reports the sites an error propagated out of, innermost first, up to a limit.
+    fn report_trace(&self, trace: &[Span]) {
+        let index = LineIndex::new(&self.text);
+        for site in trace.iter().take(TRACE_LIMIT) {
+            let (line, column) = index.position(site.start);
+            eprintln!(
+                "    from {}:{}:{}",
+                self.path.display(),
+                line + 1,
+                column + 1
+            );
+        }
+        if trace.len() > TRACE_LIMIT {
+            eprintln!("    ... and {} more", trace.len() - TRACE_LIMIT);
+        }
+    }
+
+    /// Parses and checks the source, reporting all diagnostics. Returns the conjuration if it has
+    /// no errors.
+    fn analyze(&self) -> Option<ast::Conjuration> {
+        let conjuration = match syntax::parse(&self.text) {
+            Ok(conjuration) => conjuration,
+            Err(diagnostic) => {
+                self.report(&diagnostic);
+                return None;
+            }
+        };
+        let analysis = check::check(&conjuration);
+        for diagnostic in &analysis.diagnostics {
+            self.report(diagnostic);
+        }
+        (!analysis.has_errors()).then_some(conjuration)
+    }
+}
+
+fn run(path: &Path, capabilities: Vec<Capability>, limits: Limits) -> anyhow::Result<bool> {
+    let source = Source::read(path)?;
+    let Some(conjuration) = source.analyze() else {
+        return Ok(false);
+    };
+    let mut host = StdHost;
+    let mut interpreter = Interpreter::new(&mut host);
+    interpreter.grant(capabilities);
+    interpreter.limit(limits);
+    match interpreter.run(&conjuration) {
+        Ok(_) => Ok(true),
+        Err(err) => {
+            source.report(&Diagnostic::error(err.to_string(), err.span));
+            source.report_trace(&err.trace);
+            Ok(false)
+        }
+    }
+}
+
+fn check_all(paths: &[PathBuf]) -> anyhow::Result<bool> {
+    let mut ok = true;
+    for path in paths {
+        ok &= Source::read(path)?.analyze().is_some();
+    }
+    Ok(ok)
+}
+
+fn format_all(paths: &[PathBuf], check_only: bool) -> anyhow::Result<bool> {
+    let mut ok = true;
+    for path in paths {
+        let source = Source::read(path)?;
+        let formatted = match syntax::format(&source.text) {
+            Ok(formatted) => formatted,
+            Err(diagnostic) => {
+                source.report(&diagnostic);
+                ok = false;
+                continue;
+            }
+        };
+        if formatted == source.text {
+            continue;
+        }
+        if check_only {
+            eprintln!("{} is not formatted", path.display());
+            ok = false;
+        } else {
+            fs::write(path, formatted)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(ok)
+}
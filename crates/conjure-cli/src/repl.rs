@@ -0,0 +1,143 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use conjure::{
+    ast::{self, Capability},
+    diagnostic::LineIndex,
+    interpret::{Limits, StdHost, Value},
+    session::{Entry, Session, SessionError},
+};
+use conjure_visualizer::{
+    font::FontRegistry,
+    layout::{LayoutConfig, LayoutParams, Node},
+    render::{self, OutputFormat},
+    visual::Figure,
+};
+
+const HELP: &str = "\
+Enter bindings (x = 1), values (add(x, 2)) or casts (utter!(\"hi\")).
+Commands:
+  :type <entry>   show the type of an entry without performing it
+  :draw           print the figure of the last entry
+  :draw <file>    draw the last entry to an .svg, .png or .pdf file
+  :help           show this message
+  :quit           leave the session";
+
+/// Reads entries from standard input until it ends, performing each one as it is completed.
+pub fn run(capabilities: Vec<Capability>, limits: Limits) -> anyhow::Result<()> {
+    let mut host = StdHost;
+    let mut session = Session::new(&mut host);
+    session.grant(capabilities);
+    session.limit(limits);
+    let mut last: Option<ast::Action> = None;
+    let interactive = io::IsTerminal::is_terminal(&io::stdin());
+    if interactive {
+        println!("Conjure {} (:help for help)", env!("CARGO_PKG_VERSION"));
+    }
+
+    let mut input = String::new();
+    loop {
+        if interactive {
+            print!("{}", if input.is_empty() { "> " } else { ". " });
+            io::stdout().flush()?;
+        }
+        // Spells may read from standard input too, so it is not kept locked between lines.
+        if io::stdin().read_line(&mut input)? == 0 {
+            break;
+        }
+        let entry = input.trim();
+        if entry.is_empty() {
+            input.clear();
+            continue;
+        }
+
+        if let Some(command) = entry.strip_prefix(':') {
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(n, a)| (n, a.trim()));
+            match name {
+                "quit" | "q" => break,
+                "help" | "h" => println!("{HELP}"),
+                "type" | "t" => match session.check(argument) {
+                    Ok(entry) if entry.bound.is_empty() => println!("{}", entry.ty),
+                    Ok(entry) => {
+                        for (symbol, ty) in &entry.bound {
+                            println!("{symbol}: {ty}");
+                        }
+                    }
+                    Err(err) => report(argument, &err),
+                },
+                "draw" | "d" => match &last {
+                    Some(action) => {
+                        if let Err(err) = draw(action, argument) {
+                            eprintln!("error: {err:#}");
+                        }
+                    }
+                    None => eprintln!("error: nothing has been entered yet"),
+                },
+                _ => eprintln!("error: unknown command `:{name}` (:help lists commands)"),
+            }
+            input.clear();
+            continue;
+        }
+
+        let source = input.clone();
+        match session.enter(&source) {
+            Ok((entry, value)) => {
+                print_entry(&session, &entry, &value);
+                last = Some(entry.action);
+            }
+            // An entry that ends too early is continued on the next line.
+            Err(SessionError::Rejected(diagnostics))
+                if diagnostics.len() == 1
+                    && diagnostics[0].span.start >= source.trim_end().len() =>
+            {
+                continue;
+            }
+            Err(err) => report(&source, &err),
+        }
+        input.clear();
+    }
+    Ok(())
+}
+
+fn print_entry(session: &Session, entry: &Entry, value: &Value) {
+    for (symbol, ty) in &entry.bound {
+        let value = session.lookup(symbol).cloned().unwrap_or(Value::Nil);
+        println!("{symbol}: {ty} = {value}");
+    }
+    if entry.bound.is_empty() && *value != Value::Nil {
+        println!("{value}: {}", entry.ty);
+    }
+}
+
+fn report(source: &str, err: &SessionError) {
+    let SessionError::Rejected(diagnostics) = err else {
+        eprintln!("{err}");
+        return;
+    };
+    let index = LineIndex::new(source);
+    for diagnostic in diagnostics {
+        let (line, column) = index.position(diagnostic.span.start);
+        eprintln!("{}:{}: {diagnostic}", line + 1, column + 1);
+    }
+}
+
+fn draw(action: &ast::Action, output: &str) -> anyhow::Result<()> {
+    let figure = Figure::from(action.clone());
+    if output.is_empty() {
+        println!("{figure:#?}");
+        return Ok(());
+    }
+    let path = Path::new(output);
+    let format = OutputFormat::from_path(path)?;
+    let registry = FontRegistry::with_builtins()?;
+    let params = LayoutParams::new(&registry, &LayoutConfig::default())?;
+    let root = Node::construct(&params, figure);
+    let bytes = render::render_to_bytes(format, &params, &root, 1024)?;
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
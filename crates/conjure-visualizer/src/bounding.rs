@@ -2,6 +2,8 @@ use std::{f64, ops::Range};
 
 use nalgebra::{vector, Rotation2, Vector2};
 
+use crate::transform::Transform;
+
 pub trait ConvexHull {
     fn convex_coords_range(&self) -> (Range<f64>, Range<f64>);
 
@@ -10,14 +12,36 @@ pub trait ConvexHull {
     fn convex_radius_at(&self, angle: f64) -> f64;
 }
 
+pub trait Inflate: ConvexHull {
+    type Inflated: ConvexHull;
+
+    /// Returns a shape that contains every point within `distance` of this shape.
+    fn inflate(self, distance: f64) -> Self::Inflated;
+}
+
 pub trait TransformShape {
-    fn translate(&mut self, amount: Vector2<f64>);
+    fn transform(&mut self, transform: &Transform);
 
-    fn rotate(&mut self, angle: f64);
+    fn translate(&mut self, amount: Vector2<f64>) {
+        self.transform(&Transform::translation(amount));
+    }
 
-    fn scale(&mut self, factor: f64);
+    fn rotate(&mut self, angle: f64) {
+        self.transform(&Transform::rotation(angle));
+    }
+
+    /// Scales the shape, unless the factor is not positive and finite.
+    fn scale(&mut self, factor: f64) {
+        if let Some(scaling) = Transform::scaling(factor) {
+            self.transform(&scaling);
+        }
+    }
 }
 
+pub trait Shape: ConvexHull + TransformShape {}
+
+impl<S: ConvexHull + TransformShape> Shape for S {}
+
 fn vector_to_line(from: Vector2<f64>, to: Vector2<f64>) -> Vector2<f64> {
     let diff = to - from;
     let factor = (from.magnitude_squared() - from.dot(&to)) / diff.magnitude_squared();
@@ -34,7 +58,7 @@ impl ConvexHull for Line {
     fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
         (
             f64::min(self.start.x, self.end.x)..f64::max(self.start.x, self.end.x),
-            f64::min(self.start.y, self.end.y)..f64::min(self.start.y, self.end.y),
+            f64::min(self.start.y, self.end.y)..f64::max(self.start.y, self.end.y),
         )
     }
 
@@ -42,30 +66,30 @@ impl ConvexHull for Line {
         f64::max(self.start.magnitude_squared(), self.end.magnitude_squared()).sqrt()
     }
 
-    fn convex_radius_at(&self, _angle: f64) -> f64 {
-        f64::max(
-            0.0,
-            vector_to_line(self.start, self.end).magnitude_squared(),
-        )
-        .sqrt()
+    fn convex_radius_at(&self, angle: f64) -> f64 {
+        let normal = vector![angle.cos(), angle.sin()];
+        f64::max(self.start.dot(&normal), self.end.dot(&normal))
     }
 }
 
-impl TransformShape for Line {
-    fn scale(&mut self, factor: f64) {
-        self.start *= factor;
-        self.end *= factor;
-    }
+impl Inflate for Line {
+    type Inflated = Rect;
 
-    fn rotate(&mut self, angle: f64) {
-        let rotation = Rotation2::new(angle);
-        self.start = rotation * self.start;
-        self.end = rotation * self.end;
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        let direction = self.end - self.start;
+        Rect::new(
+            direction.magnitude() + 2.0 * distance,
+            2.0 * distance,
+            direction.y.atan2(direction.x),
+            0.5 * (self.start + self.end),
+        )
     }
+}
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.start += amount;
-        self.end += amount;
+impl TransformShape for Line {
+    fn transform(&mut self, transform: &Transform) {
+        self.start = transform.apply(self.start);
+        self.end = transform.apply(self.end);
     }
 }
 
@@ -141,15 +165,12 @@ impl<P: Polygon> ConvexHull for P {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Circle {
     radius: f64,
-    offset: Vector2<f64>,
+    center: Vector2<f64>,
 }
 
 impl Circle {
     pub fn new(radius: f64, center: Vector2<f64>) -> Self {
-        Self {
-            radius,
-            offset: -center,
-        }
+        Self { radius, center }
     }
 
     pub fn from_radius(radius: f64) -> Self {
@@ -167,39 +188,39 @@ impl Circle {
 
     #[inline]
     pub fn center(&self) -> Vector2<f64> {
-        -self.offset
+        self.center
     }
 }
 
 impl ConvexHull for Circle {
     fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
         (
-            self.offset.x - self.radius..self.offset.x + self.radius,
-            self.offset.y - self.radius..self.offset.y + self.radius,
+            self.center.x - self.radius..self.center.x + self.radius,
+            self.center.y - self.radius..self.center.y + self.radius,
         )
     }
 
     fn convex_radius(&self) -> f64 {
-        self.radius + self.offset.magnitude()
+        self.radius + self.center.magnitude()
     }
 
     fn convex_radius_at(&self, angle: f64) -> f64 {
-        self.offset.x * angle.cos() + self.offset.y * angle.sin() + self.radius
+        self.center.x * angle.cos() + self.center.y * angle.sin() + self.radius
     }
 }
 
-impl TransformShape for Circle {
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.offset += amount;
-    }
+impl Inflate for Circle {
+    type Inflated = Circle;
 
-    fn rotate(&mut self, angle: f64) {
-        self.offset = Rotation2::new(-angle) * self.offset;
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Self::new(self.radius + distance, self.center)
     }
+}
 
-    fn scale(&mut self, factor: f64) {
-        self.radius *= factor;
-        self.offset *= factor;
+impl TransformShape for Circle {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.radius *= transform.scale_factor();
     }
 }
 
@@ -207,7 +228,7 @@ impl TransformShape for Circle {
 pub struct Rect {
     width: f64,
     height: f64,
-    offset: Vector2<f64>,
+    center: Vector2<f64>,
     rotation: f64,
 }
 
@@ -216,7 +237,7 @@ impl Rect {
         Self {
             width,
             height,
-            offset: -center,
+            center,
             rotation,
         }
     }
@@ -260,7 +281,7 @@ impl Rect {
 
     #[inline]
     pub fn center(&self) -> Vector2<f64> {
-        -self.offset
+        self.center
     }
 
     #[inline]
@@ -273,7 +294,7 @@ pub struct RectVertices {
     half_width: f64,
     half_height: f64,
     rotation: Rotation2<f64>,
-    offset: Vector2<f64>,
+    center: Vector2<f64>,
     idx: u8,
 }
 
@@ -283,7 +304,7 @@ impl RectVertices {
             half_width: rect.width / 2.0,
             half_height: rect.height / 2.0,
             rotation: Rotation2::new(rect.rotation),
-            offset: rect.offset,
+            center: rect.center,
             idx: 0,
         }
     }
@@ -302,7 +323,7 @@ impl Iterator for RectVertices {
         };
         self.idx += 1;
         let vertex =
-            self.rotation * vector![s_x * self.half_width, s_y * self.half_height] + self.offset;
+            self.rotation * vector![s_x * self.half_width, s_y * self.half_height] + self.center;
         Some(vertex)
     }
 }
@@ -315,20 +336,25 @@ impl Polygon for Rect {
     }
 }
 
-impl TransformShape for Rect {
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.offset += amount;
-    }
+impl Inflate for Rect {
+    type Inflated = Rect;
 
-    fn rotate(&mut self, angle: f64) {
-        self.rotation += angle;
-        self.offset = Rotation2::new(-angle) * self.offset;
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Self::new(
+            self.width + 2.0 * distance,
+            self.height + 2.0 * distance,
+            self.rotation,
+            self.center,
+        )
     }
+}
 
-    fn scale(&mut self, factor: f64) {
-        self.width *= factor;
-        self.height *= factor;
-        self.offset *= factor;
+impl TransformShape for Rect {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.rotation += transform.angle();
+        self.width *= transform.scale_factor();
+        self.height *= transform.scale_factor();
     }
 }
 
@@ -337,7 +363,7 @@ pub struct RegularPolygon {
     num_sides: usize,
     outer_radius: f64,
     rotation: f64,
-    offset: Vector2<f64>,
+    center: Vector2<f64>,
 }
 
 impl RegularPolygon {
@@ -346,11 +372,11 @@ impl RegularPolygon {
             num_sides,
             outer_radius,
             rotation,
-            offset: vector![0.0, 0.0],
+            center: Vector2::zeros(),
         }
     }
 
-    pub fn wrap(shape: &impl ConvexHull, num_sides: usize, rotation: f64) -> Self {
+    pub fn wrap(shape: &impl ConvexHull, num_sides: usize, rotation: f64, padding: f64) -> Self {
         let segment_angle = f64::consts::TAU / (num_sides as f64);
         let unpadded_inner_radius = (0..num_sides)
             .map(|i| {
@@ -358,19 +384,29 @@ impl RegularPolygon {
             })
             .reduce(f64::max)
             .unwrap_or_default();
-        let inner_radius = unpadded_inner_radius;
+        let inner_radius = unpadded_inner_radius + padding;
         let outer_radius = inner_radius / f64::cos(segment_angle / 2.0);
         Self::new(num_sides, outer_radius, rotation)
     }
 
+    #[inline]
+    pub fn inner_radius(&self) -> f64 {
+        self.outer_radius * f64::cos(f64::consts::PI / (self.num_sides as f64))
+    }
+
     #[inline]
     pub fn num_sides(&self) -> usize {
         self.num_sides
     }
 
+    #[inline]
+    pub fn outer_radius(&self) -> f64 {
+        self.outer_radius
+    }
+
     #[inline]
     pub fn center(&self) -> Vector2<f64> {
-        -self.offset
+        self.center
     }
 
     #[inline]
@@ -381,6 +417,8 @@ impl RegularPolygon {
 
 pub struct RegularPolygonVertices {
     outer_radius: f64,
+    rotation: f64,
+    center: Vector2<f64>,
     base_angle: f64,
     idx_iter: Range<usize>,
 }
@@ -389,6 +427,8 @@ impl RegularPolygonVertices {
     fn new(polygon: &RegularPolygon) -> Self {
         Self {
             outer_radius: polygon.outer_radius,
+            rotation: polygon.rotation,
+            center: polygon.center,
             base_angle: f64::consts::TAU / (polygon.num_sides as f64),
             idx_iter: 0..polygon.num_sides,
         }
@@ -400,8 +440,8 @@ impl Iterator for RegularPolygonVertices {
 
     fn next(&mut self) -> Option<Self::Item> {
         let idx = self.idx_iter.next()?;
-        let angle = (idx as f64) * self.base_angle;
-        let vertex = self.outer_radius * vector![angle.cos(), angle.sin()];
+        let angle = (idx as f64) * self.base_angle + self.rotation;
+        let vertex = self.outer_radius * vector![angle.cos(), angle.sin()] + self.center;
         Some(vertex)
     }
 }
@@ -414,23 +454,537 @@ impl Polygon for RegularPolygon {
     }
 }
 
+impl Inflate for RegularPolygon {
+    type Inflated = RegularPolygon;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        let outer_radius =
+            (self.inner_radius() + distance) * self.outer_radius / self.inner_radius();
+        Self {
+            outer_radius,
+            ..self
+        }
+    }
+}
+
 impl TransformShape for RegularPolygon {
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.offset += amount;
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.rotation += transform.angle();
+        self.outer_radius *= transform.scale_factor();
     }
+}
 
-    fn rotate(&mut self, angle: f64) {
-        self.rotation += angle;
-        self.offset = Rotation2::new(-angle) * self.offset;
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarPolygon {
+    num_points: usize,
+    step: usize,
+    outer_radius: f64,
+    rotation: f64,
+    center: Vector2<f64>,
+}
+
+impl StarPolygon {
+    pub fn new(num_points: usize, step: usize, outer_radius: f64, rotation: f64) -> Self {
+        assert!(
+            step >= 1 && 2 * step < num_points,
+            "Invalid star polygon {{{num_points}/{step}}}"
+        );
+        Self {
+            num_points,
+            step,
+            outer_radius,
+            rotation,
+            center: Vector2::zeros(),
+        }
     }
 
-    fn scale(&mut self, factor: f64) {
-        self.outer_radius *= factor;
-        self.offset *= factor;
+    pub fn wrap(
+        shape: &impl ConvexHull,
+        num_points: usize,
+        step: usize,
+        rotation: f64,
+        padding: f64,
+    ) -> Self {
+        let inner_polygon = RegularPolygon::wrap(
+            shape,
+            num_points,
+            rotation + f64::consts::PI / (num_points as f64),
+            padding,
+        );
+        let outer_radius =
+            inner_polygon.outer_radius() / Self::inner_outer_radius_ratio(num_points, step);
+        Self::new(num_points, step, outer_radius, rotation)
+    }
+
+    fn inner_outer_radius_ratio(num_points: usize, step: usize) -> f64 {
+        let num_points = num_points as f64;
+        let step = step as f64;
+        f64::cos(f64::consts::PI * step / num_points)
+            / f64::cos(f64::consts::PI * (step - 1.0) / num_points)
+    }
+
+    #[inline]
+    pub fn num_points(&self) -> usize {
+        self.num_points
+    }
+
+    #[inline]
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    #[inline]
+    pub fn outer_radius(&self) -> f64 {
+        self.outer_radius
+    }
+
+    #[inline]
+    pub fn inner_radius(&self) -> f64 {
+        self.outer_radius * Self::inner_outer_radius_ratio(self.num_points, self.step)
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector2<f64> {
+        self.center
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    pub fn inner_polygon(&self) -> RegularPolygon {
+        let mut polygon = RegularPolygon::new(
+            self.num_points,
+            self.inner_radius(),
+            self.rotation + f64::consts::PI / (self.num_points as f64),
+        );
+        polygon.translate(self.center);
+        polygon
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        let vertices: Vec<_> = self.vertices().collect();
+        (0..self.num_points)
+            .map(|i| Line {
+                start: vertices[i],
+                end: vertices[(i + self.step) % self.num_points],
+            })
+            .collect()
+    }
+}
+
+impl Polygon for StarPolygon {
+    type Vertices = RegularPolygonVertices;
+
+    fn vertices(&self) -> Self::Vertices {
+        RegularPolygonVertices {
+            outer_radius: self.outer_radius,
+            rotation: self.rotation,
+            center: self.center,
+            base_angle: f64::consts::TAU / (self.num_points as f64),
+            idx_iter: 0..self.num_points,
+        }
+    }
+}
+
+impl Inflate for StarPolygon {
+    type Inflated = StarPolygon;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        let edge_distance =
+            f64::cos(f64::consts::PI * (self.step as f64) / (self.num_points as f64));
+        Self {
+            outer_radius: self.outer_radius + distance / edge_distance,
+            ..self
+        }
+    }
+}
+
+impl TransformShape for StarPolygon {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.rotation += transform.angle();
+        self.outer_radius *= transform.scale_factor();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ellipse {
+    semi_major: f64,
+    semi_minor: f64,
+    rotation: f64,
+    center: Vector2<f64>,
+}
+
+impl Ellipse {
+    pub fn new(semi_major: f64, semi_minor: f64, rotation: f64, center: Vector2<f64>) -> Self {
+        Self {
+            semi_major,
+            semi_minor,
+            rotation,
+            center,
+        }
+    }
+
+    pub fn from_semi_axes(semi_major: f64, semi_minor: f64) -> Self {
+        Self::new(semi_major, semi_minor, 0.0, Vector2::zeros())
+    }
+
+    pub fn wrap(shape: &impl ConvexHull, rotation: f64) -> Self {
+        let half_width = f64::max(
+            shape.convex_radius_at(rotation),
+            shape.convex_radius_at(rotation + f64::consts::TAU * 0.5),
+        );
+        let half_height = f64::max(
+            shape.convex_radius_at(rotation + f64::consts::TAU * 0.25),
+            shape.convex_radius_at(rotation + f64::consts::TAU * 0.75),
+        );
+        Self::new(
+            f64::consts::SQRT_2 * half_width,
+            f64::consts::SQRT_2 * half_height,
+            rotation,
+            Vector2::zeros(),
+        )
+    }
+
+    #[inline]
+    pub fn semi_major(&self) -> f64 {
+        self.semi_major
+    }
+
+    #[inline]
+    pub fn semi_minor(&self) -> f64 {
+        self.semi_minor
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector2<f64> {
+        self.center
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    fn half_extent_at(&self, angle: f64) -> f64 {
+        let local_angle = angle - self.rotation;
+        f64::hypot(
+            self.semi_major * local_angle.cos(),
+            self.semi_minor * local_angle.sin(),
+        )
+    }
+}
+
+impl ConvexHull for Ellipse {
+    fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
+        let half_width = self.half_extent_at(0.0);
+        let half_height = self.half_extent_at(f64::consts::TAU * 0.25);
+        (
+            self.center.x - half_width..self.center.x + half_width,
+            self.center.y - half_height..self.center.y + half_height,
+        )
+    }
+
+    fn convex_radius(&self) -> f64 {
+        f64::max(self.semi_major, self.semi_minor) + self.center.magnitude()
+    }
+
+    fn convex_radius_at(&self, angle: f64) -> f64 {
+        self.center.x * angle.cos() + self.center.y * angle.sin() + self.half_extent_at(angle)
     }
 }
 
-impl ConvexHull for Box<dyn ConvexHull> {
+impl Inflate for Ellipse {
+    type Inflated = Ellipse;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        let factor = 1.0 + distance / f64::min(self.semi_major, self.semi_minor);
+        Self {
+            semi_major: self.semi_major * factor,
+            semi_minor: self.semi_minor * factor,
+            ..self
+        }
+    }
+}
+
+impl TransformShape for Ellipse {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.rotation += transform.angle();
+        self.semi_major *= transform.scale_factor();
+        self.semi_minor *= transform.scale_factor();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annulus {
+    inner_radius: f64,
+    outer_radius: f64,
+    center: Vector2<f64>,
+}
+
+impl Annulus {
+    /// An annulus between the two radii. An inner radius beyond the outer one is clamped to it,
+    /// leaving a circle line.
+    pub fn new(inner_radius: f64, outer_radius: f64, center: Vector2<f64>) -> Self {
+        Self {
+            inner_radius: inner_radius.min(outer_radius),
+            outer_radius,
+            center,
+        }
+    }
+
+    pub fn from_radii(inner_radius: f64, outer_radius: f64) -> Self {
+        Self::new(inner_radius, outer_radius, Vector2::zeros())
+    }
+
+    pub fn around(shape: &impl ConvexHull, band_width: f64) -> Self {
+        let inner_radius = shape.convex_radius();
+        Self::from_radii(inner_radius, inner_radius + band_width)
+    }
+
+    #[inline]
+    pub fn inner_radius(&self) -> f64 {
+        self.inner_radius
+    }
+
+    #[inline]
+    pub fn outer_radius(&self) -> f64 {
+        self.outer_radius
+    }
+
+    #[inline]
+    pub fn mean_radius(&self) -> f64 {
+        0.5 * (self.inner_radius + self.outer_radius)
+    }
+
+    #[inline]
+    pub fn band_width(&self) -> f64 {
+        self.outer_radius - self.inner_radius
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector2<f64> {
+        self.center
+    }
+
+    pub fn inner(&self) -> Circle {
+        Circle::new(self.inner_radius, self.center)
+    }
+
+    pub fn outer(&self) -> Circle {
+        Circle::new(self.outer_radius, self.center)
+    }
+}
+
+impl ConvexHull for Annulus {
+    fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
+        self.outer().convex_coords_range()
+    }
+
+    fn convex_radius(&self) -> f64 {
+        self.outer().convex_radius()
+    }
+
+    fn convex_radius_at(&self, angle: f64) -> f64 {
+        self.outer().convex_radius_at(angle)
+    }
+}
+
+impl Inflate for Annulus {
+    type Inflated = Annulus;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Self::new(
+            f64::max(0.0, self.inner_radius - distance),
+            self.outer_radius + distance,
+            self.center,
+        )
+    }
+}
+
+impl TransformShape for Annulus {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.inner_radius *= transform.scale_factor();
+        self.outer_radius *= transform.scale_factor();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arc {
+    radius: f64,
+    start_angle: f64,
+    sweep: f64,
+    center: Vector2<f64>,
+}
+
+impl Arc {
+    pub fn new(radius: f64, start_angle: f64, sweep: f64, center: Vector2<f64>) -> Self {
+        let (start_angle, sweep) = if sweep < 0.0 {
+            (start_angle + sweep, -sweep)
+        } else {
+            (start_angle, sweep)
+        };
+        Self {
+            radius,
+            start_angle,
+            sweep: f64::min(sweep, f64::consts::TAU),
+            center,
+        }
+    }
+
+    pub fn from_radius_angles(radius: f64, start_angle: f64, sweep: f64) -> Self {
+        Self::new(radius, start_angle, sweep, Vector2::zeros())
+    }
+
+    #[inline]
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    #[inline]
+    pub fn start_angle(&self) -> f64 {
+        self.start_angle
+    }
+
+    #[inline]
+    pub fn end_angle(&self) -> f64 {
+        self.start_angle + self.sweep
+    }
+
+    #[inline]
+    pub fn sweep(&self) -> f64 {
+        self.sweep
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector2<f64> {
+        self.center
+    }
+
+    #[inline]
+    pub fn start(&self) -> Vector2<f64> {
+        self.point_at(self.start_angle)
+    }
+
+    #[inline]
+    pub fn end(&self) -> Vector2<f64> {
+        self.point_at(self.end_angle())
+    }
+
+    pub fn point_at(&self, angle: f64) -> Vector2<f64> {
+        self.center + self.radius * vector![angle.cos(), angle.sin()]
+    }
+
+    pub fn contains_angle(&self, angle: f64) -> bool {
+        (angle - self.start_angle).rem_euclid(f64::consts::TAU) <= self.sweep
+    }
+}
+
+impl ConvexHull for Arc {
+    fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
+        (
+            -self.convex_radius_at(f64::consts::TAU * 0.5)..self.convex_radius_at(0.0),
+            -self.convex_radius_at(f64::consts::TAU * 0.75)
+                ..self.convex_radius_at(f64::consts::TAU * 0.25),
+        )
+    }
+
+    fn convex_radius(&self) -> f64 {
+        let center_angle = self.center.y.atan2(self.center.x);
+        if self.contains_angle(center_angle) {
+            return self.radius + self.center.magnitude();
+        }
+        f64::max(self.start().magnitude(), self.end().magnitude())
+    }
+
+    fn convex_radius_at(&self, angle: f64) -> f64 {
+        let normal = vector![angle.cos(), angle.sin()];
+        if self.contains_angle(angle) {
+            return self.center.dot(&normal) + self.radius;
+        }
+        f64::max(self.start().dot(&normal), self.end().dot(&normal))
+    }
+}
+
+impl Inflate for Arc {
+    type Inflated = Inflated<Arc>;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Inflated::new(self, distance)
+    }
+}
+
+impl TransformShape for Arc {
+    fn transform(&mut self, transform: &Transform) {
+        self.center = transform.apply(self.center);
+        self.start_angle += transform.angle();
+        self.radius *= transform.scale_factor();
+    }
+}
+
+/// The exact convex hull of a shape widened by a fixed distance, for shapes that have no closed
+/// form for their own offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inflated<S> {
+    shape: S,
+    distance: f64,
+}
+
+impl<S> Inflated<S> {
+    pub fn new(shape: S, distance: f64) -> Self {
+        Self { shape, distance }
+    }
+
+    #[inline]
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    #[inline]
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+impl<S: ConvexHull> ConvexHull for Inflated<S> {
+    fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
+        let (x_range, y_range) = self.shape.convex_coords_range();
+        (
+            x_range.start - self.distance..x_range.end + self.distance,
+            y_range.start - self.distance..y_range.end + self.distance,
+        )
+    }
+
+    fn convex_radius(&self) -> f64 {
+        self.shape.convex_radius() + self.distance
+    }
+
+    fn convex_radius_at(&self, angle: f64) -> f64 {
+        self.shape.convex_radius_at(angle) + self.distance
+    }
+}
+
+impl<S: ConvexHull> Inflate for Inflated<S> {
+    type Inflated = Inflated<S>;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Self::new(self.shape, self.distance + distance)
+    }
+}
+
+impl<S: TransformShape> TransformShape for Inflated<S> {
+    fn transform(&mut self, transform: &Transform) {
+        self.shape.transform(transform);
+        self.distance *= transform.scale_factor();
+    }
+}
+
+impl ConvexHull for Box<dyn Shape> {
     fn convex_coords_range(&self) -> (Range<f64>, Range<f64>) {
         (**self).convex_coords_range()
     }
@@ -468,3 +1022,31 @@ impl<S: ConvexHull> ConvexHull for Vec<S> {
             .fold(0.0, f64::max)
     }
 }
+
+impl Inflate for Box<dyn Shape> {
+    type Inflated = Inflated<Box<dyn Shape>>;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        Inflated::new(self, distance)
+    }
+}
+
+impl TransformShape for Box<dyn Shape> {
+    fn transform(&mut self, transform: &Transform) {
+        (**self).transform(transform)
+    }
+}
+
+impl<S: TransformShape> TransformShape for Vec<S> {
+    fn transform(&mut self, transform: &Transform) {
+        self.iter_mut().for_each(|s| s.transform(transform));
+    }
+}
+
+impl<S: Inflate> Inflate for Vec<S> {
+    type Inflated = Vec<S::Inflated>;
+
+    fn inflate(self, distance: f64) -> Self::Inflated {
+        self.into_iter().map(|s| s.inflate(distance)).collect()
+    }
+}
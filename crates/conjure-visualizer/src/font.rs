@@ -1,55 +1,381 @@
-use anyhow::Context;
-use nalgebra::vector;
+use std::{borrow::Cow, collections::HashMap, f64, fmt, fs, path::Path};
 
-use crate::bounding::{self, TransformShape};
+use anyhow::{anyhow, Context};
+use nalgebra::{vector, Vector2};
+use rustybuzz::ttf_parser;
+
+use crate::{
+    bounding::{self, TransformShape},
+    path,
+    transform::Transform,
+};
 
 const ZEYADA_REGULAR: &[u8] = include_bytes!("../assets/Zeyada-Regular.ttf");
+const DEJAVU_SERIF: &[u8] = include_bytes!("../assets/DejaVuSerif.ttf");
+const DEJAVU_MATH: &[u8] = include_bytes!("../assets/DejaVuMathTeXGyre.ttf");
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BuiltinFont {
     Zeyada,
+    DejaVuSerif,
+    /// Blackletter, drawn with the Fraktur letters of DejaVu Math TeX Gyre.
+    Fraktur,
+}
+
+impl BuiltinFont {
+    pub const ALL: [BuiltinFont; 3] = [
+        BuiltinFont::Zeyada,
+        BuiltinFont::DejaVuSerif,
+        BuiltinFont::Fraktur,
+    ];
+
+    pub fn family_name(self) -> &'static str {
+        match self {
+            BuiltinFont::Zeyada => "Zeyada",
+            BuiltinFont::DejaVuSerif => "DejaVu Serif",
+            BuiltinFont::Fraktur => "Fraktur",
+        }
+    }
+
+    fn data(self) -> &'static [u8] {
+        match self {
+            BuiltinFont::Zeyada => ZEYADA_REGULAR,
+            BuiltinFont::DejaVuSerif => DEJAVU_SERIF,
+            BuiltinFont::Fraktur => DEJAVU_MATH,
+        }
+    }
+
+    /// The character drawn for a character of the text.
+    fn substitute(self) -> fn(char) -> char {
+        match self {
+            BuiltinFont::Fraktur => fraktur,
+            BuiltinFont::Zeyada | BuiltinFont::DejaVuSerif => |c| c,
+        }
+    }
+}
+
+/// The Mathematical Fraktur counterpart of a Latin letter. Five capitals predate the block and
+/// live among the letterlike symbols instead.
+fn fraktur(c: char) -> char {
+    let code = match c {
+        'C' => 0x212D,
+        'H' => 0x210C,
+        'I' => 0x2111,
+        'R' => 0x211C,
+        'Z' => 0x2128,
+        'A'..='Z' => 0x1D504 + (c as u32 - 'A' as u32),
+        'a'..='z' => 0x1D51E + (c as u32 - 'a' as u32),
+        _ => return c,
+    };
+    char::from_u32(code).unwrap_or(c)
+}
+
+/// A glyph placed by the shaper. Its position is the glyph origin on the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    pub id: u16,
+    pub cluster: usize,
+    pub position: Vector2<f64>,
+    pub advance: f64,
 }
 
-#[derive(Debug)]
-pub struct Font(rusttype::Font<'static>);
+/// A single shaped line of text. Glyph positions share the coordinate space of `rect`, and
+/// `scale` converts font units into that space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphRun {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub rect: bounding::Rect,
+    pub scale: f64,
+}
+
+/// A glyph inscribed along a circular arc. `rotation` turns the glyph upright with its top
+/// facing away from the arc center, and `rect` is its rotated bounding rect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcGlyph {
+    pub glyph: PositionedGlyph,
+    pub rotation: f64,
+    pub rect: bounding::Rect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcText {
+    pub glyphs: Vec<ArcGlyph>,
+    pub scale: f64,
+}
+
+pub struct Font {
+    data: Cow<'static, [u8]>,
+    substitute: fn(char) -> char,
+}
 
 impl Font {
+    fn new(data: Cow<'static, [u8]>) -> anyhow::Result<Self> {
+        rustybuzz::Face::from_slice(&data, 0).context("Failed to parse font data")?;
+        Ok(Self {
+            data,
+            substitute: |c| c,
+        })
+    }
+
     pub fn builtin(font: BuiltinFont) -> anyhow::Result<Self> {
-        let font_data = match font {
-            BuiltinFont::Zeyada => ZEYADA_REGULAR,
-        };
-        let font = rusttype::Font::try_from_bytes(font_data)
-            .context("Failed to load builtin font: {font:?}")?;
-        Ok(Self(font))
+        let loaded = Self::new(Cow::Borrowed(font.data()))
+            .with_context(|| format!("Failed to load builtin font: {font:?}"))?;
+        Ok(Self {
+            substitute: font.substitute(),
+            ..loaded
+        })
     }
 
-    pub fn measure(&self, text: &str, size: f32) -> Vec<bounding::Rect> {
-        let lines: Vec<_> = text.lines().collect();
+    pub fn from_bytes(data: Vec<u8>) -> anyhow::Result<Self> {
+        Self::new(Cow::Owned(data))
+    }
 
-        let scale = rusttype::Scale::uniform(size);
-        let v_metrics = self.0.v_metrics(scale);
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read font file {}", path.display()))?;
+        Self::from_bytes(data).with_context(|| format!("Failed to load font {}", path.display()))
+    }
+
+    fn face(&self) -> rustybuzz::Face<'_> {
+        rustybuzz::Face::from_slice(&self.data, 0).expect("Font data is validated on load")
+    }
+
+    pub fn shape(&self, text: &str, size: f32) -> Vec<GlyphRun> {
+        let face = self.face();
+        let lines: Vec<_> = text.lines().collect();
 
-        let text_height = f64::from(v_metrics.ascent - v_metrics.descent);
-        let line_height = text_height + f64::from(v_metrics.line_gap);
-        let line_widths = lines.iter().map(|line| self.measure_line(line, scale));
+        let text_height = f64::from(size);
+        let scale = text_height / (f64::from(face.ascender()) - f64::from(face.descender()));
+        let ascent = f64::from(face.ascender()) * scale;
+        let line_height = text_height + f64::from(face.line_gap()) * scale;
 
-        let line_rects = line_widths.enumerate().map(|(i, width)| {
+        let runs = lines.iter().enumerate().map(|(i, line)| {
             let v_offset = (i as f64 - 0.5 * (lines.len() as f64 - 1.0)) * line_height;
+            let line: String = line.chars().map(self.substitute).collect();
+            let (mut glyphs, width) = Self::shape_line(&face, &line, scale);
+
+            let origin = vector![-0.5 * width, v_offset - 0.5 * text_height + ascent];
+            glyphs.iter_mut().for_each(|g| g.position += origin);
+
             let mut rect = bounding::Rect::from_width_height(width, text_height);
             rect.translate(vector![0.0, v_offset]);
-            rect
+            GlyphRun {
+                glyphs,
+                rect,
+                scale,
+            }
+        });
+
+        runs.collect()
+    }
+
+    /// Shapes `text` as a single line running clockwise along a circle around the origin, centered
+    /// on `center_angle`. The text is shrunk if it would not fit into the angular `span`.
+    pub fn shape_along_arc(
+        &self,
+        text: &str,
+        size: f32,
+        radius: f64,
+        center_angle: f64,
+        span: f64,
+    ) -> ArcText {
+        let line = text.lines().collect::<Vec<_>>().join(" ");
+        let Some(mut run) = self.shape(&line, size).pop() else {
+            return ArcText {
+                glyphs: vec![],
+                scale: 0.0,
+            };
+        };
+        let available_width = span * radius;
+        if run.rect.width() > available_width {
+            let factor = available_width / run.rect.width();
+            run = self
+                .shape(&line, size * factor as f32)
+                .pop()
+                .expect("Reshaping a line yields a glyph run");
+        }
+
+        let text_height = run.rect.height();
+        let glyphs = run.glyphs.into_iter().map(|glyph| {
+            let center_x = glyph.position.x + 0.5 * glyph.advance;
+            let angle = center_angle + center_x / radius;
+            let rotation = angle + 0.25 * f64::consts::TAU;
+            let anchor = radius * vector![angle.cos(), angle.sin()];
+            let transform = Transform::translation(anchor)
+                * Transform::rotation(rotation)
+                * Transform::translation(vector![-center_x, 0.0]);
+            ArcGlyph {
+                rect: bounding::Rect::new(glyph.advance, text_height, rotation, anchor),
+                rotation,
+                glyph: PositionedGlyph {
+                    position: transform.apply(glyph.position),
+                    ..glyph
+                },
+            }
         });
 
-        line_rects.collect()
+        ArcText {
+            glyphs: glyphs.collect(),
+            scale: run.scale,
+        }
+    }
+
+    /// Extracts the outline of a shaped glyph, turned by `rotation` around the glyph origin and
+    /// placed at the glyph position.
+    pub fn glyph_outline(&self, glyph: &PositionedGlyph, scale: f64, rotation: f64) -> path::Path {
+        let mut collector = OutlineCollector {
+            path: path::Path::new(),
+            scale,
+        };
+        self.face()
+            .outline_glyph(ttf_parser::GlyphId(glyph.id), &mut collector);
+        let mut path = collector.path;
+        path.transform(&Transform::new(glyph.position, rotation, 1.0));
+        path
+    }
+
+    pub fn run_outline(&self, run: &GlyphRun) -> path::Path {
+        let mut path = path::Path::new();
+        for glyph in &run.glyphs {
+            path.extend(self.glyph_outline(glyph, run.scale, 0.0));
+        }
+        path
+    }
+
+    pub fn arc_outline(&self, arc_text: &ArcText) -> path::Path {
+        let mut path = path::Path::new();
+        for arc_glyph in &arc_text.glyphs {
+            path.extend(self.glyph_outline(&arc_glyph.glyph, arc_text.scale, arc_glyph.rotation));
+        }
+        path
+    }
+
+    pub fn measure(&self, text: &str, size: f32) -> Vec<bounding::Rect> {
+        self.shape(text, size)
+            .into_iter()
+            .map(|run| run.rect)
+            .collect()
+    }
+
+    fn shape_line(face: &rustybuzz::Face, line: &str, scale: f64) -> (Vec<PositionedGlyph>, f64) {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(line);
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(face, &[], buffer);
+
+        let mut pen = 0.0;
+        let glyphs = output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| {
+                let glyph = PositionedGlyph {
+                    id: info.glyph_id as u16,
+                    cluster: info.cluster as usize,
+                    position: scale
+                        * vector![
+                            pen + f64::from(position.x_offset),
+                            -f64::from(position.y_offset)
+                        ],
+                    advance: f64::from(position.x_advance) * scale,
+                };
+                pen += f64::from(position.x_advance);
+                glyph
+            })
+            .collect();
+
+        (glyphs, pen * scale)
+    }
+}
+
+struct OutlineCollector {
+    path: path::Path,
+    scale: f64,
+}
+
+impl OutlineCollector {
+    fn point(&self, x: f32, y: f32) -> Vector2<f64> {
+        self.scale * vector![f64::from(x), -f64::from(y)]
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineCollector {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(self.point(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(self.point(x, y));
     }
 
-    fn measure_line(&self, line: &str, scale: rusttype::Scale) -> f64 {
-        let glyphs = self.0.layout(line, scale, rusttype::point(0.0, 0.0));
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.path.quad_to(self.point(x1, y1), self.point(x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.path
+            .cubic_to(self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("size", &self.data.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A collection of loaded [`Font`]s, keyed by their family name.
+#[derive(Debug, Default)]
+pub struct FontRegistry {
+    fonts: HashMap<String, Font>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_builtins() -> anyhow::Result<Self> {
+        let mut registry = Self::new();
+        for font in BuiltinFont::ALL {
+            registry.register(font.family_name(), Font::builtin(font)?);
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, family: impl Into<String>, font: Font) {
+        self.fonts.insert(family.into(), font);
+    }
+
+    pub fn load(
+        &mut self,
+        family: impl Into<String>,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let font = Font::from_path(path)?;
+        self.register(family, font);
+        Ok(())
+    }
+
+    pub fn get(&self, family: &str) -> anyhow::Result<&Font> {
+        self.fonts.get(family).ok_or_else(|| {
+            let mut families: Vec<_> = self.families().collect();
+            families.sort_unstable();
+            anyhow!(
+                "Unknown font family \"{family}\" (available: {})",
+                families.join(", ")
+            )
+        })
+    }
 
-        glyphs
-            .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width)
-            .last()
-            .unwrap_or(0.0)
-            .into()
+    pub fn families(&self) -> impl Iterator<Item = &str> {
+        self.fonts.keys().map(String::as_str)
     }
 }
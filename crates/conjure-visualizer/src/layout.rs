@@ -1,168 +1,439 @@
 use std::f64;
 
 use nalgebra::{vector, Vector2};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    bounding::{self, ConvexHull, TransformShape},
-    font::Font,
+    bounding::{self, ConvexHull, Inflate, Shape, TransformShape},
+    font::{ArcText, BuiltinFont, Font, FontRegistry, GlyphRun},
+    path::Path,
+    transform::Transform,
     visual::{self},
 };
 
-trait LayoutNode {
-    type Boundary: ConvexHull;
+pub trait LayoutNode {
+    type Boundary: ConvexHull + TransformShape;
 
-    fn boundary(&self) -> Self::Boundary;
+    /// The boundary of the node in its own, untransformed coordinate space.
+    fn local_boundary(&self) -> Self::Boundary;
 
-    fn scale(&mut self, factor: f64);
+    /// The transform from the node's coordinate space into that of its parent.
+    fn transform(&self) -> &Transform;
 
-    fn rotate(&mut self, angle: f64);
+    fn transform_mut(&mut self) -> &mut Transform;
 
-    fn translate(&mut self, amount: Vector2<f64>);
+    fn boundary(&self) -> Self::Boundary {
+        let mut boundary = self.local_boundary();
+        boundary.transform(self.transform());
+        boundary
+    }
+
+    fn apply_transform(&mut self, transform: &Transform) {
+        let current = self.transform_mut();
+        *current = *transform * *current;
+    }
+
+    /// Scales the node, unless the factor is not positive and finite.
+    fn scale(&mut self, factor: f64) {
+        if let Some(scaling) = Transform::scaling(factor) {
+            self.apply_transform(&scaling);
+        }
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.apply_transform(&Transform::rotation(angle));
+    }
+
+    fn translate(&mut self, amount: Vector2<f64>) {
+        self.apply_transform(&Transform::translation(amount));
+    }
 }
 
 #[derive(Debug)]
 pub struct LayoutParams<'a> {
     pub emphasis_rays_radius_ratio: f64,
+    pub emphasis_ouroboros_radius_ratio: f64,
     pub decoration_hat_relative_width: f64,
     pub decoration_hat_relative_height: f64,
     pub decoration_tilde_relative_width: f64,
     pub decoration_tilde_relative_height: f64,
     pub decoration_position_radius_ratio: f64,
-    pub circle_content_scale: f64,
+    pub label_relative_size: f64,
+    pub stroke_width: f64,
+    pub circle_content_margin: f64,
     pub double_stroke_radius_ratio: f64,
     pub circle_max_rim_overlap_ratio: f64,
     pub circle_min_rim_ratio: f64,
     pub circle_max_rim_ratio: f64,
-    pub polygon_content_scale: f64,
+    pub polygon_content_margin: f64,
+    pub inscribe_rim_text: bool,
+    pub inscription_span_ratio: f64,
+    pub link_segment_length: f64,
+    pub arrangement_spacing: f64,
     pub phrase_font: &'a Font,
     pub phrase_font_size: f32,
     pub symbol_font: &'a Font,
     pub symbol_font_size: f32,
 }
 
+impl<'a> LayoutParams<'a> {
+    pub fn new(registry: &'a FontRegistry, config: &LayoutConfig) -> anyhow::Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            emphasis_rays_radius_ratio: config.emphasis_rays_radius_ratio,
+            emphasis_ouroboros_radius_ratio: config.emphasis_ouroboros_radius_ratio,
+            decoration_hat_relative_width: config.decoration_hat_relative_width,
+            decoration_hat_relative_height: config.decoration_hat_relative_height,
+            decoration_tilde_relative_width: config.decoration_tilde_relative_width,
+            decoration_tilde_relative_height: config.decoration_tilde_relative_height,
+            decoration_position_radius_ratio: config.decoration_position_radius_ratio,
+            label_relative_size: config.label_relative_size,
+            stroke_width: config.stroke_width,
+            circle_content_margin: config.circle_content_margin,
+            double_stroke_radius_ratio: config.double_stroke_radius_ratio,
+            circle_max_rim_overlap_ratio: config.circle_max_rim_overlap_ratio,
+            circle_min_rim_ratio: config.circle_min_rim_ratio,
+            circle_max_rim_ratio: config.circle_max_rim_ratio,
+            polygon_content_margin: config.polygon_content_margin,
+            inscribe_rim_text: config.inscribe_rim_text,
+            inscription_span_ratio: config.inscription_span_ratio,
+            link_segment_length: config.link_segment_length,
+            arrangement_spacing: config.arrangement_spacing,
+            phrase_font: registry.get(&config.phrase_font)?,
+            phrase_font_size: config.phrase_font_size,
+            symbol_font: registry.get(&config.symbol_font)?,
+            symbol_font_size: config.symbol_font_size,
+        })
+    }
+}
+
+impl LayoutParams<'_> {
+    pub fn font(&self, style: TextStyle) -> (&Font, f32) {
+        match style {
+            TextStyle::Symbol => (self.symbol_font, self.symbol_font_size),
+            TextStyle::Phrase => (self.phrase_font, self.phrase_font_size),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    Symbol,
+    Phrase,
+}
+
+/// The configurable part of [`LayoutParams`]. Fonts are selected by family name from a
+/// [`FontRegistry`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub emphasis_rays_radius_ratio: f64,
+    pub emphasis_ouroboros_radius_ratio: f64,
+    pub decoration_hat_relative_width: f64,
+    pub decoration_hat_relative_height: f64,
+    pub decoration_tilde_relative_width: f64,
+    pub decoration_tilde_relative_height: f64,
+    pub decoration_position_radius_ratio: f64,
+    pub label_relative_size: f64,
+    pub stroke_width: f64,
+    pub circle_content_margin: f64,
+    pub double_stroke_radius_ratio: f64,
+    pub circle_max_rim_overlap_ratio: f64,
+    pub circle_min_rim_ratio: f64,
+    pub circle_max_rim_ratio: f64,
+    pub polygon_content_margin: f64,
+    pub inscribe_rim_text: bool,
+    pub inscription_span_ratio: f64,
+    pub link_segment_length: f64,
+    pub arrangement_spacing: f64,
+    pub phrase_font: String,
+    pub phrase_font_size: f32,
+    pub symbol_font: String,
+    pub symbol_font_size: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            emphasis_rays_radius_ratio: 1.3,
+            emphasis_ouroboros_radius_ratio: 1.2,
+            decoration_hat_relative_width: 0.6,
+            decoration_hat_relative_height: 0.2,
+            decoration_tilde_relative_width: 0.6,
+            decoration_tilde_relative_height: 0.15,
+            decoration_position_radius_ratio: 1.2,
+            label_relative_size: 0.5,
+            stroke_width: 2.0,
+            circle_content_margin: 2.0,
+            double_stroke_radius_ratio: 1.15,
+            circle_max_rim_overlap_ratio: 0.1,
+            circle_min_rim_ratio: 0.15,
+            circle_max_rim_ratio: 0.4,
+            polygon_content_margin: 2.0,
+            inscribe_rim_text: true,
+            inscription_span_ratio: 0.9,
+            link_segment_length: 12.0,
+            arrangement_spacing: 16.0,
+            phrase_font: BuiltinFont::DejaVuSerif.family_name().to_string(),
+            phrase_font_size: 16.0,
+            symbol_font: BuiltinFont::Zeyada.family_name().to_string(),
+            symbol_font_size: 24.0,
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Checks that the parameters can be laid out: radius ratios must be at least 1, sizes and
+    /// margins positive, and everything finite.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let radius_ratios = [
+            (
+                "emphasis_rays_radius_ratio",
+                self.emphasis_rays_radius_ratio,
+            ),
+            (
+                "emphasis_ouroboros_radius_ratio",
+                self.emphasis_ouroboros_radius_ratio,
+            ),
+            (
+                "decoration_position_radius_ratio",
+                self.decoration_position_radius_ratio,
+            ),
+            (
+                "double_stroke_radius_ratio",
+                self.double_stroke_radius_ratio,
+            ),
+        ];
+        for (name, value) in radius_ratios {
+            if !(value.is_finite() && value >= 1.0) {
+                anyhow::bail!("`{name}` must be a finite number of at least 1, not {value}");
+            }
+        }
+        let sizes = [
+            (
+                "decoration_hat_relative_width",
+                self.decoration_hat_relative_width,
+            ),
+            (
+                "decoration_hat_relative_height",
+                self.decoration_hat_relative_height,
+            ),
+            (
+                "decoration_tilde_relative_width",
+                self.decoration_tilde_relative_width,
+            ),
+            (
+                "decoration_tilde_relative_height",
+                self.decoration_tilde_relative_height,
+            ),
+            ("label_relative_size", self.label_relative_size),
+            ("stroke_width", self.stroke_width),
+            ("circle_content_margin", self.circle_content_margin),
+            ("circle_min_rim_ratio", self.circle_min_rim_ratio),
+            ("circle_max_rim_ratio", self.circle_max_rim_ratio),
+            ("polygon_content_margin", self.polygon_content_margin),
+            ("inscription_span_ratio", self.inscription_span_ratio),
+            ("link_segment_length", self.link_segment_length),
+            ("arrangement_spacing", self.arrangement_spacing),
+            ("phrase_font_size", f64::from(self.phrase_font_size)),
+            ("symbol_font_size", f64::from(self.symbol_font_size)),
+        ];
+        for (name, value) in sizes {
+            if !(value.is_finite() && value > 0.0) {
+                anyhow::bail!("`{name}` must be a positive finite number, not {value}");
+            }
+        }
+        let overlap = self.circle_max_rim_overlap_ratio;
+        if !(overlap.is_finite() && overlap >= 0.0) {
+            anyhow::bail!(
+                "`circle_max_rim_overlap_ratio` must be a finite number of at least 0, not {overlap}"
+            );
+        }
+        Ok(())
+    }
+}
+
 pub struct Symbol {
     pub name: String,
-    pub boundary: Vec<bounding::Rect>,
+    pub lines: Vec<GlyphRun>,
+    pub transform: Transform,
 }
 
 impl Symbol {
     fn construct(params: &LayoutParams, symbol: visual::Symbol) -> Self {
-        let boundary = params
-            .symbol_font
-            .measure(&symbol.0, params.symbol_font_size);
+        let lines = params.symbol_font.shape(&symbol.0, params.symbol_font_size);
         Self {
             name: symbol.0,
-            boundary,
+            lines,
+            transform: Transform::identity(),
+        }
+    }
+
+    pub fn outline(&self, params: &LayoutParams) -> Path {
+        Self::lines_outline(params.symbol_font, &self.lines)
+    }
+
+    fn lines_outline(font: &Font, lines: &[GlyphRun]) -> Path {
+        let mut path = Path::new();
+        for line in lines {
+            path.extend(font.run_outline(line));
         }
+        path
     }
 }
 
 impl LayoutNode for Symbol {
     type Boundary = Vec<bounding::Rect>;
 
-    fn boundary(&self) -> Self::Boundary {
-        self.boundary.clone()
-    }
-
-    fn scale(&mut self, factor: f64) {
-        self.boundary.iter_mut().for_each(|b| b.scale(factor));
+    fn local_boundary(&self) -> Self::Boundary {
+        self.lines.iter().map(|l| l.rect.clone()).collect()
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.iter_mut().for_each(|b| b.rotate(angle));
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.iter_mut().for_each(|b| b.translate(amount));
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
 pub struct Phrase {
     pub text: String,
-    pub boundary: Vec<bounding::Rect>,
+    pub lines: Vec<GlyphRun>,
+    pub transform: Transform,
 }
 
 impl Phrase {
     fn construct(params: &LayoutParams, phrase: visual::Phrase) -> Self {
-        let boundary = params
-            .phrase_font
-            .measure(&phrase.0, params.phrase_font_size);
+        let lines = params.phrase_font.shape(&phrase.0, params.phrase_font_size);
         Self {
             text: phrase.0,
-            boundary,
+            lines,
+            transform: Transform::identity(),
         }
     }
+
+    pub fn outline(&self, params: &LayoutParams) -> Path {
+        Symbol::lines_outline(params.phrase_font, &self.lines)
+    }
 }
 
 impl LayoutNode for Phrase {
     type Boundary = Vec<bounding::Rect>;
 
-    fn boundary(&self) -> Self::Boundary {
-        self.boundary.clone()
+    fn local_boundary(&self) -> Self::Boundary {
+        self.lines.iter().map(|l| l.rect.clone()).collect()
     }
 
-    fn scale(&mut self, factor: f64) {
-        self.boundary.iter_mut().for_each(|b| b.scale(factor));
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.iter_mut().for_each(|b| b.rotate(angle));
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
+}
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.iter_mut().for_each(|b| b.translate(amount))
+/// Text running along a circular arc around the node origin, such as a phrase or symbol on the
+/// rim of a [`Circle`].
+pub struct Inscription {
+    pub text: String,
+    pub style: TextStyle,
+    pub arc_text: ArcText,
+    pub transform: Transform,
+}
+
+impl Inscription {
+    fn inscribe(
+        params: &LayoutParams,
+        node: &Node,
+        radius: f64,
+        center_angle: f64,
+        span: f64,
+    ) -> Option<Self> {
+        let (text, style) = match node {
+            Node::Symbol(symbol) => (&symbol.name, TextStyle::Symbol),
+            Node::Phrase(phrase) => (&phrase.text, TextStyle::Phrase),
+            _ => return None,
+        };
+        let (font, size) = params.font(style);
+        let size = size * node.transform().scale_factor() as f32;
+        let arc_text = font.shape_along_arc(text, size, radius, center_angle, span);
+        Some(Self {
+            text: text.clone(),
+            style,
+            arc_text,
+            transform: Transform::identity(),
+        })
+    }
+
+    pub fn outline(&self, params: &LayoutParams) -> Path {
+        let (font, _) = params.font(self.style);
+        font.arc_outline(&self.arc_text)
+    }
+}
+
+impl LayoutNode for Inscription {
+    type Boundary = Vec<bounding::Rect>;
+
+    fn local_boundary(&self) -> Self::Boundary {
+        self.arc_text
+            .glyphs
+            .iter()
+            .map(|g| g.rect.clone())
+            .collect()
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
 pub struct Pentagram {
-    pub boundary: bounding::RegularPolygon,
+    pub boundary: bounding::StarPolygon,
     pub child: Box<Node>,
+    pub transform: Transform,
 }
 
 impl Pentagram {
-    const INNER_ROTATION: f64 = 0.25 * f64::consts::TAU;
-    const OUTER_ROTATION: f64 = -0.25 * f64::consts::TAU;
-    const INNER_OUTER_RADIUS_RATIO: f64 = 2.618033988749895;
+    const ROTATION: f64 = -0.25 * f64::consts::TAU;
 
     fn construct(params: &LayoutParams, pentagram: visual::Pentagram) -> Self {
-        let mut child = Node::construct(params, *pentagram.content);
-        let inner_pentagon =
-            bounding::RegularPolygon::wrap(&child.boundary(), 5, Self::INNER_ROTATION);
-
-        let boundary = bounding::RegularPolygon::new(
+        let child = Node::construct(params, *pentagram.content);
+        let boundary = bounding::StarPolygon::wrap(
+            &child.boundary(),
             5,
-            Self::INNER_OUTER_RADIUS_RATIO * inner_pentagon.convex_radius(),
-            Self::OUTER_ROTATION,
+            2,
+            Self::ROTATION,
+            params.polygon_content_margin * params.stroke_width,
         );
 
-        child.scale(params.polygon_content_scale);
-
         Self {
             boundary,
             child: Box::new(child),
+            transform: Transform::identity(),
         }
     }
 }
 
 impl LayoutNode for Pentagram {
-    type Boundary = bounding::RegularPolygon;
+    type Boundary = bounding::StarPolygon;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         self.boundary.clone()
     }
 
-    fn scale(&mut self, factor: f64) {
-        self.boundary.scale(factor);
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.rotate(angle);
-        self.child.rotate(angle);
-    }
-
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.translate(amount);
-        self.child.translate(amount);
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
@@ -171,8 +442,10 @@ pub struct Circle {
     pub pattern: visual::CirclePattern,
     pub double: bool,
     pub boundary: bounding::Circle,
+    pub band: bounding::Annulus,
     pub rim: Vec<Node>,
     pub content: Box<Node>,
+    pub transform: Transform,
 }
 
 impl Circle {
@@ -185,34 +458,41 @@ impl Circle {
 
         Self::apply_content_constraints(params, &mut content, &rim);
 
-        let inner_circle = bounding::Circle::wrap(content.boundary());
-        let outer_radius = inner_circle.radius() * radius_ratio;
-        let mean_radius = (inner_circle.radius() + outer_radius) * 0.5;
+        let inner_circle = bounding::Circle::wrap(content.boundary())
+            .inflate(params.circle_content_margin * params.stroke_width);
+        // Without content to scale, the circle itself has to grow to make room for the rim.
+        let inner_radius = f64::max(
+            inner_circle.radius(),
+            Self::highest_rim_size(&rim) / params.circle_max_rim_ratio,
+        );
+        let outer_radius = inner_radius * radius_ratio;
+        let mean_radius = (inner_radius + outer_radius) * 0.5;
 
         Self::apply_rim_constraints(params, &mut rim, mean_radius);
-        Self::position_rim_items(params, &mut rim, mean_radius, inner_circle.radius());
-
-        content.scale(params.circle_content_scale);
+        Self::position_rim_items(params, &mut rim, mean_radius, inner_radius);
 
         Self {
             stroke: circle.stroke,
             pattern: circle.pattern,
             double: circle.double,
             boundary: bounding::Circle::from_radius(outer_radius),
+            band: bounding::Annulus::from_radii(inner_radius, outer_radius),
             rim,
             content: Box::new(content),
+            transform: Transform::identity(),
         }
     }
 
-    fn apply_content_constraints(params: &LayoutParams, content: &mut Node, rim: &[Node]) {
-        let highest_rim_size = rim
-            .iter()
+    fn highest_rim_size(rim: &[Node]) -> f64 {
+        rim.iter()
             .map(|n| n.boundary().convex_radius())
-            .fold(0.0, f64::max);
+            .fold(0.0, f64::max)
+    }
 
-        let min_content_size = highest_rim_size / params.circle_max_rim_ratio;
+    fn apply_content_constraints(params: &LayoutParams, content: &mut Node, rim: &[Node]) {
+        let min_content_size = Self::highest_rim_size(rim) / params.circle_max_rim_ratio;
         let content_radius = content.boundary().convex_radius();
-        if content_radius < min_content_size {
+        if content_radius > 0.0 && content_radius < min_content_size {
             let factor = min_content_size / content_radius;
             content.scale(factor);
         }
@@ -222,7 +502,7 @@ impl Circle {
         let min_rim_size = params.circle_min_rim_ratio * mean_radius;
         for rim_node in rim {
             let radius = rim_node.boundary().convex_radius();
-            if radius < min_rim_size {
+            if radius > 0.0 && radius < min_rim_size {
                 let factor = min_rim_size / radius;
                 rim_node.scale(factor);
             }
@@ -237,9 +517,19 @@ impl Circle {
     ) {
         let num_rim_items = rim.len();
         let max_rim_overlap = params.circle_max_rim_overlap_ratio * inner_radius;
+        let inscription_span =
+            params.inscription_span_ratio * f64::consts::TAU / (num_rim_items as f64);
         for (i, rim_node) in rim.iter_mut().enumerate() {
             let orientation = (i as f64) * f64::consts::TAU / (num_rim_items as f64);
             let angle = orientation + Self::BASE_RIM_ROTATION;
+            if params.inscribe_rim_text {
+                if let Some(inscription) =
+                    Inscription::inscribe(params, rim_node, mean_radius, angle, inscription_span)
+                {
+                    *rim_node = Node::Inscription(inscription);
+                    continue;
+                }
+            }
             let inward_radius = rim_node
                 .boundary()
                 .convex_radius_at(angle - 0.5 * f64::consts::TAU);
@@ -267,30 +557,100 @@ impl Circle {
 }
 
 impl LayoutNode for Circle {
-    type Boundary = Vec<Box<dyn ConvexHull>>;
+    type Boundary = Vec<Box<dyn Shape>>;
 
-    fn boundary(&self) -> Self::Boundary {
-        let mut boundary: Vec<Box<dyn ConvexHull>> = Vec::with_capacity(self.rim.len() + 1);
+    fn local_boundary(&self) -> Self::Boundary {
+        let mut boundary: Vec<Box<dyn Shape>> = Vec::with_capacity(self.rim.len() + 1);
         boundary.push(Box::new(self.boundary.clone()));
         boundary.extend(self.rim.iter().map(|n| n.boundary()));
         boundary
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.translate(amount);
-        self.content.translate(amount);
-        self.rim.iter_mut().for_each(|n| n.translate(amount));
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.rotate(angle);
-        self.rim.iter_mut().for_each(|n| n.rotate(angle));
-        self.content.rotate(angle);
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
+}
 
-    fn scale(&mut self, factor: f64) {
-        self.boundary.scale(factor);
-        self.rim.iter_mut().for_each(|n| n.scale(factor))
+pub struct SplitCircle {
+    pub stroke: visual::StrokePattern,
+    pub boundary: bounding::Circle,
+    /// The lines between adjacent sectors.
+    pub dividers: Vec<bounding::Line>,
+    pub arms: Vec<Node>,
+    pub transform: Transform,
+}
+
+impl SplitCircle {
+    const BASE_ROTATION: f64 = f64::consts::TAU * -0.25;
+
+    fn construct(params: &LayoutParams, split: visual::SplitCircle) -> Self {
+        let mut arms: Vec<Node> = split
+            .arms
+            .into_iter()
+            .map(|f| Node::construct(params, f))
+            .collect();
+
+        let num_arms = arms.len() as f64;
+        let margin = params.circle_content_margin * params.stroke_width;
+        let arm_radius = arms
+            .iter()
+            .map(|n| n.boundary().convex_radius())
+            .fold(0.0, f64::max)
+            + margin;
+        // Far enough from the center for every arm to fit between the dividers of its sector.
+        let distance = if arms.len() > 1 {
+            arm_radius / f64::sin(0.5 * f64::consts::TAU / num_arms)
+        } else {
+            0.0
+        };
+        let radius = distance + arm_radius;
+
+        let sector = f64::consts::TAU / num_arms;
+        for (i, arm) in arms.iter_mut().enumerate() {
+            let angle = Self::BASE_ROTATION + i as f64 * sector;
+            arm.translate(distance * vector![angle.cos(), angle.sin()]);
+        }
+        let dividers = if arms.len() > 1 {
+            (0..arms.len())
+                .map(|i| {
+                    let angle = Self::BASE_ROTATION + (i as f64 + 0.5) * sector;
+                    bounding::Line {
+                        start: vector![0.0, 0.0],
+                        end: radius * vector![angle.cos(), angle.sin()],
+                    }
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            stroke: split.stroke,
+            boundary: bounding::Circle::from_radius(radius),
+            dividers,
+            arms,
+            transform: Transform::identity(),
+        }
+    }
+}
+
+impl LayoutNode for SplitCircle {
+    type Boundary = bounding::Circle;
+
+    fn local_boundary(&self) -> Self::Boundary {
+        self.boundary.clone()
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
@@ -299,21 +659,26 @@ pub struct RegularPolygon {
     pub stroke: visual::StrokePattern,
     pub boundary: bounding::RegularPolygon,
     pub child: Box<Node>,
+    pub transform: Transform,
 }
 
 impl RegularPolygon {
     const BASE_ROTATION: f64 = f64::consts::TAU * -0.25;
 
     fn construct(params: &LayoutParams, polygon: visual::RegularPolygon) -> Self {
-        let mut child = Node::construct(params, *polygon.content);
-        let boundary =
-            bounding::RegularPolygon::wrap(&child.boundary(), polygon.sides, Self::BASE_ROTATION);
-        child.scale(params.polygon_content_scale);
+        let child = Node::construct(params, *polygon.content);
+        let boundary = bounding::RegularPolygon::wrap(
+            &child.boundary(),
+            polygon.sides,
+            Self::BASE_ROTATION,
+            params.polygon_content_margin * params.stroke_width,
+        );
         Self {
             sides: polygon.sides,
             stroke: polygon.stroke,
             boundary,
             child: Box::new(child),
+            transform: Transform::identity(),
         }
     }
 }
@@ -321,23 +686,16 @@ impl RegularPolygon {
 impl LayoutNode for RegularPolygon {
     type Boundary = bounding::RegularPolygon;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         self.boundary.clone()
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.translate(amount);
-        self.child.translate(amount);
-    }
-
-    fn scale(&mut self, factor: f64) {
-        self.boundary.scale(factor);
-        self.child.scale(factor);
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.rotate(angle);
-        self.child.rotate(angle);
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
@@ -345,6 +703,7 @@ pub struct Decorated {
     pub kind: visual::DecorationKind,
     pub decoration_rect: bounding::Rect,
     pub child: Box<Node>,
+    pub transform: Transform,
 }
 
 struct DecorationParams {
@@ -362,6 +721,7 @@ impl Decorated {
             kind: decorated.kind,
             decoration_rect,
             child: Box::new(child),
+            transform: Transform::identity(),
         }
     }
 
@@ -402,28 +762,67 @@ impl Decorated {
 }
 
 impl LayoutNode for Decorated {
-    type Boundary = Vec<Box<dyn ConvexHull>>;
+    type Boundary = Vec<Box<dyn Shape>>;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         vec![
             Box::new(self.decoration_rect.clone()),
             self.child.boundary(),
         ]
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.decoration_rect.translate(amount);
-        self.child.translate(amount);
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.decoration_rect.rotate(angle);
-        self.child.rotate(angle);
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
+}
 
-    fn scale(&mut self, factor: f64) {
-        self.decoration_rect.scale(factor);
-        self.child.scale(factor);
+pub struct Labeled {
+    pub label: Box<Node>,
+    pub child: Box<Node>,
+    pub transform: Transform,
+}
+
+impl Labeled {
+    fn construct(params: &LayoutParams, labeled: visual::Labeled) -> Self {
+        let child = Node::construct(params, *labeled.content);
+        let mut label = Node::Symbol(Symbol::construct(params, labeled.label));
+
+        let label_radius = label.boundary().convex_radius();
+        let child_radius = child.boundary().convex_radius();
+        if label_radius > 0.0 && child_radius > 0.0 {
+            label.scale(params.label_relative_size * child_radius / label_radius);
+        }
+        let up = f64::consts::TAU * -0.25;
+        let distance = child.boundary().convex_radius_at(up)
+            + label.boundary().convex_radius_at(-up)
+            + params.stroke_width;
+        label.translate(distance * vector![up.cos(), up.sin()]);
+
+        Self {
+            label: Box::new(label),
+            child: Box::new(child),
+            transform: Transform::identity(),
+        }
+    }
+}
+
+impl LayoutNode for Labeled {
+    type Boundary = Vec<Box<dyn Shape>>;
+
+    fn local_boundary(&self) -> Self::Boundary {
+        vec![self.label.boundary(), self.child.boundary()]
+    }
+
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
@@ -431,6 +830,7 @@ pub struct Emphasized {
     pub kind: visual::EmphasisKind,
     pub boundary: bounding::Circle,
     pub child: Box<Node>,
+    pub transform: Transform,
 }
 
 impl Emphasized {
@@ -443,12 +843,14 @@ impl Emphasized {
             kind: emphasized.kind,
             boundary,
             child: Box::new(child),
+            transform: Transform::identity(),
         }
     }
 
     fn get_radius_ratio(params: &LayoutParams, kind: visual::EmphasisKind) -> f64 {
         match kind {
             visual::EmphasisKind::Rays => params.emphasis_rays_radius_ratio,
+            visual::EmphasisKind::Ouroboros => params.emphasis_ouroboros_radius_ratio,
         }
     }
 }
@@ -456,23 +858,16 @@ impl Emphasized {
 impl LayoutNode for Emphasized {
     type Boundary = bounding::Circle;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         self.boundary.clone()
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.boundary.translate(amount);
-        self.child.translate(amount);
-    }
-
-    fn rotate(&mut self, angle: f64) {
-        self.boundary.rotate(angle);
-        self.child.rotate(angle);
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn scale(&mut self, factor: f64) {
-        self.boundary.scale(factor);
-        self.child.scale(factor)
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
@@ -480,139 +875,283 @@ pub struct Link {
     pub stroke: visual::StrokePattern,
     pub segments: Vec<bounding::Line>,
     pub items: Vec<Node>,
+    pub transform: Transform,
 }
 
 impl Link {
     const BASE_ANGLE: f64 = f64::consts::TAU * -0.25;
 
     fn construct(params: &LayoutParams, link: visual::Link) -> Self {
-        let mut items: Vec<Node> = Vec::with_capacity(link.items.len());
-        let mut segments: Vec<bounding::Line> = Vec::with_capacity(link.items.len() - 1);
+        let mut items: Vec<Node> = link
+            .items
+            .into_iter()
+            .map(|f| Node::construct(params, f))
+            .collect();
 
-        let num_sides: f64 = link.items.len() as f64;
-        let start_angle = Self::BASE_ANGLE + 0.5 * (num_sides - 1.0) / num_sides * f64::consts::TAU;
+        let num_items = items.len() as f64;
+        let start_angle = Self::BASE_ANGLE + 0.5 * (num_items - 1.0) / num_items * f64::consts::TAU;
 
-        let mut prev_position: Option<Vector2<f64>>;
-        for i in 0..link.items.len() {
-            let node = Node::construct(params, link.items[i].clone());
-            let angle = start_angle + i as f64 / num_sides * f64::consts::TAU;
+        let max_item_radius = items
+            .iter()
+            .map(|n| n.boundary().convex_radius())
+            .fold(0.0, f64::max);
+        let spacing = max_item_radius + 0.5 * params.link_segment_length * params.stroke_width;
+        let radius = if items.len() > 1 {
+            spacing / f64::sin(0.5 * f64::consts::TAU / num_items)
+        } else {
+            0.0
+        };
+
+        let positions: Vec<Vector2<f64>> = (0..items.len())
+            .map(|i| {
+                let angle = start_angle + i as f64 / num_items * f64::consts::TAU;
+                radius * vector![angle.cos(), angle.sin()]
+            })
+            .collect();
+
+        let segments = items
+            .windows(2)
+            .zip(positions.windows(2))
+            .map(|(nodes, ends)| {
+                let direction = (ends[1] - ends[0]).normalize();
+                let angle = direction.y.atan2(direction.x);
+                let start_offset = nodes[0].boundary().convex_radius_at(angle);
+                let end_offset = nodes[1]
+                    .boundary()
+                    .convex_radius_at(angle + 0.5 * f64::consts::TAU);
+                bounding::Line {
+                    start: ends[0] + start_offset * direction,
+                    end: ends[1] - end_offset * direction,
+                }
+            })
+            .collect();
+
+        for (item, position) in items.iter_mut().zip(positions) {
+            item.translate(position);
         }
 
-        todo!()
+        Self {
+            stroke: link.stroke,
+            segments,
+            items,
+            transform: Transform::identity(),
+        }
     }
 }
 
 impl LayoutNode for Link {
-    type Boundary = Vec<Box<dyn ConvexHull>>;
+    type Boundary = Vec<Box<dyn Shape>>;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         self.segments
             .iter()
-            .map(|s| Box::new(s.clone()) as Box<dyn ConvexHull>)
+            .map(|s| Box::new(s.clone()) as Box<dyn Shape>)
             .chain(self.items.iter().map(|i| i.boundary()))
             .collect()
     }
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.segments.iter_mut().for_each(|s| s.translate(amount));
-        self.items.iter_mut().for_each(|i| i.translate(amount));
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.segments.iter_mut().for_each(|s| s.rotate(angle));
-        self.items.iter_mut().for_each(|i| i.rotate(angle));
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
+}
 
-    fn scale(&mut self, factor: f64) {
-        self.segments.iter_mut().for_each(|s| s.scale(factor));
-        self.items.iter_mut().for_each(|i| i.scale(factor));
-    }
+pub struct Arrangement {
+    pub items: Vec<Node>,
+    pub transform: Transform,
 }
 
-impl LayoutNode for Vec<Node> {
-    type Boundary = Vec<Box<dyn ConvexHull>>;
+impl Arrangement {
+    fn construct(params: &LayoutParams, figures: Vec<visual::Figure>) -> Self {
+        let mut items: Vec<Node> = figures
+            .into_iter()
+            .map(|f| Node::construct(params, f))
+            .collect();
 
-    fn boundary(&self) -> Self::Boundary {
-        self.iter().map(|n| n.boundary()).collect::<Vec<_>>()
+        let gap = params.arrangement_spacing * params.stroke_width;
+        let extents: Vec<(f64, f64)> = items
+            .iter()
+            .map(|n| {
+                let boundary = n.boundary();
+                (
+                    boundary.convex_radius_at(f64::consts::TAU * -0.25),
+                    boundary.convex_radius_at(f64::consts::TAU * 0.25),
+                )
+            })
+            .collect();
+        let total_height = extents.iter().map(|(up, down)| up + down).sum::<f64>()
+            + gap * (items.len().saturating_sub(1) as f64);
+
+        let mut cursor = -0.5 * total_height;
+        for (item, (up, down)) in items.iter_mut().zip(extents) {
+            cursor += up;
+            item.translate(vector![0.0, cursor]);
+            cursor += down + gap;
+        }
+
+        Self {
+            items,
+            transform: Transform::identity(),
+        }
     }
+}
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        self.iter_mut().for_each(|n| n.translate(amount));
+impl LayoutNode for Arrangement {
+    type Boundary = Vec<Box<dyn Shape>>;
+
+    fn local_boundary(&self) -> Self::Boundary {
+        self.items.iter().map(|n| n.boundary()).collect::<Vec<_>>()
     }
 
-    fn rotate(&mut self, angle: f64) {
-        self.iter_mut().for_each(|n| n.rotate(angle));
+    fn transform(&self) -> &Transform {
+        &self.transform
     }
 
-    fn scale(&mut self, factor: f64) {
-        self.iter_mut().for_each(|n| n.scale(factor));
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
     }
 }
 
 pub enum Node {
     Symbol(Symbol),
     Phrase(Phrase),
+    Inscription(Inscription),
     Pentagram(Pentagram),
     Circle(Circle),
+    SplitCircle(SplitCircle),
     RegularPolygon(RegularPolygon),
+    Decorated(Decorated),
+    Labeled(Labeled),
+    Emphasized(Emphasized),
     Link(Link),
-    Arrangement(Vec<Node>),
+    Arrangement(Arrangement),
 }
 
 impl Node {
-    fn construct(params: &LayoutParams, figure: visual::Figure) -> Self {
-        todo!()
+    pub fn construct(params: &LayoutParams, figure: visual::Figure) -> Self {
+        match figure {
+            visual::Figure::Symbol(s) => Self::Symbol(Symbol::construct(params, s)),
+            visual::Figure::Phrase(p) => Self::Phrase(Phrase::construct(params, p)),
+            visual::Figure::Pentagram(p) => Self::Pentagram(Pentagram::construct(params, p)),
+            visual::Figure::Circle(c) => Self::Circle(Circle::construct(params, c)),
+            visual::Figure::SplitCircle(s) => Self::SplitCircle(SplitCircle::construct(params, s)),
+            visual::Figure::RegularPolygon(p) => {
+                Self::RegularPolygon(RegularPolygon::construct(params, p))
+            }
+            visual::Figure::Decorated(d) => Self::Decorated(Decorated::construct(params, d)),
+            visual::Figure::Labeled(l) => Self::Labeled(Labeled::construct(params, l)),
+            visual::Figure::Emphasized(e) => Self::Emphasized(Emphasized::construct(params, e)),
+            visual::Figure::Link(l) => Self::Link(Link::construct(params, l)),
+            visual::Figure::Arrangement(a) => Self::Arrangement(Arrangement::construct(params, a)),
+        }
     }
 }
 
 impl LayoutNode for Node {
-    type Boundary = Box<dyn ConvexHull>;
+    type Boundary = Box<dyn Shape>;
 
-    fn boundary(&self) -> Self::Boundary {
+    fn local_boundary(&self) -> Self::Boundary {
         match self {
-            Self::Symbol(s) => Box::new(s.boundary()),
-            Self::Phrase(p) => Box::new(p.boundary()),
-            Self::Pentagram(p) => Box::new(p.boundary()),
-            Self::Circle(c) => Box::new(c.boundary()),
-            Self::RegularPolygon(p) => Box::new(p.boundary()),
-            Self::Link(l) => Box::new(l.boundary()),
-            Self::Arrangement(a) => Box::new(a.boundary()),
+            Self::Symbol(s) => Box::new(s.local_boundary()),
+            Self::Phrase(p) => Box::new(p.local_boundary()),
+            Self::Inscription(i) => Box::new(i.local_boundary()),
+            Self::Pentagram(p) => Box::new(p.local_boundary()),
+            Self::Circle(c) => Box::new(c.local_boundary()),
+            Self::SplitCircle(s) => Box::new(s.local_boundary()),
+            Self::RegularPolygon(p) => Box::new(p.local_boundary()),
+            Self::Decorated(d) => Box::new(d.local_boundary()),
+            Self::Labeled(l) => Box::new(l.local_boundary()),
+            Self::Emphasized(e) => Box::new(e.local_boundary()),
+            Self::Link(l) => Box::new(l.local_boundary()),
+            Self::Arrangement(a) => Box::new(a.local_boundary()),
         }
     }
 
-    fn scale(&mut self, factor: f64) {
+    fn transform(&self) -> &Transform {
         match self {
-            Self::Symbol(s) => s.scale(factor),
-            Self::Phrase(p) => p.scale(factor),
-            Self::Pentagram(p) => p.scale(factor),
-            Self::Circle(c) => c.scale(factor),
-            Self::RegularPolygon(p) => p.scale(factor),
-            Self::Link(l) => l.scale(factor),
-            Self::Arrangement(a) => a.scale(factor),
+            Self::Symbol(s) => s.transform(),
+            Self::Phrase(p) => p.transform(),
+            Self::Inscription(i) => i.transform(),
+            Self::Pentagram(p) => p.transform(),
+            Self::Circle(c) => c.transform(),
+            Self::SplitCircle(s) => s.transform(),
+            Self::RegularPolygon(p) => p.transform(),
+            Self::Decorated(d) => d.transform(),
+            Self::Labeled(l) => l.transform(),
+            Self::Emphasized(e) => e.transform(),
+            Self::Link(l) => l.transform(),
+            Self::Arrangement(a) => a.transform(),
         }
     }
 
-    fn rotate(&mut self, angle: f64) {
+    fn transform_mut(&mut self) -> &mut Transform {
         match self {
-            Self::Symbol(s) => s.rotate(angle),
-            Self::Phrase(p) => p.rotate(angle),
-            Self::Pentagram(p) => p.rotate(angle),
-            Self::Circle(c) => c.rotate(angle),
-            Self::RegularPolygon(p) => p.rotate(angle),
-            Self::Link(l) => l.rotate(angle),
-            Self::Arrangement(a) => a.rotate(angle),
+            Self::Symbol(s) => s.transform_mut(),
+            Self::Phrase(p) => p.transform_mut(),
+            Self::Inscription(i) => i.transform_mut(),
+            Self::Pentagram(p) => p.transform_mut(),
+            Self::Circle(c) => c.transform_mut(),
+            Self::SplitCircle(s) => s.transform_mut(),
+            Self::RegularPolygon(p) => p.transform_mut(),
+            Self::Decorated(d) => d.transform_mut(),
+            Self::Labeled(l) => l.transform_mut(),
+            Self::Emphasized(e) => e.transform_mut(),
+            Self::Link(l) => l.transform_mut(),
+            Self::Arrangement(a) => a.transform_mut(),
         }
     }
+}
 
-    fn translate(&mut self, amount: Vector2<f64>) {
-        match self {
-            Self::Symbol(s) => s.translate(amount),
-            Self::Phrase(p) => p.translate(amount),
-            Self::Pentagram(p) => p.translate(amount),
-            Self::Circle(c) => c.translate(amount),
-            Self::RegularPolygon(p) => p.translate(amount),
-            Self::Link(l) => l.translate(amount),
-            Self::Arrangement(a) => a.translate(amount),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font::FontRegistry, visual::Figure};
+
+    #[test]
+    fn empty_labeled_values_are_laid_out() {
+        let registry = FontRegistry::with_builtins().unwrap();
+        let params = LayoutParams::new(&registry, &LayoutConfig::default()).unwrap();
+        for source in ["x = (a = ());", "x = (a = 1, b = ());"] {
+            let conjuration = conjure::syntax::parse(source).unwrap();
+            let root = Node::construct(&params, Figure::from(conjuration));
+            assert!(root.boundary().convex_radius().is_finite(), "{source}");
+        }
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let registry = FontRegistry::with_builtins().unwrap();
+        let configs = [
+            LayoutConfig {
+                double_stroke_radius_ratio: 0.5,
+                ..LayoutConfig::default()
+            },
+            LayoutConfig {
+                stroke_width: 0.0,
+                ..LayoutConfig::default()
+            },
+            LayoutConfig {
+                circle_content_margin: f64::INFINITY,
+                ..LayoutConfig::default()
+            },
+            LayoutConfig {
+                symbol_font_size: f32::NAN,
+                ..LayoutConfig::default()
+            },
+        ];
+        for config in configs {
+            assert!(LayoutParams::new(&registry, &config).is_err(), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn degenerate_scalings_are_rejected() {
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(Transform::scaling(factor), None, "{factor}");
         }
+        assert!(Transform::scaling(2.0).is_some());
     }
 }
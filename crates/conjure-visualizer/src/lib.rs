@@ -0,0 +1,7 @@
+pub mod bounding;
+pub mod font;
+pub mod layout;
+pub mod path;
+pub mod render;
+pub mod transform;
+pub mod visual;
@@ -1,44 +1,119 @@
-mod bounding;
-mod font;
-mod layout;
-mod visual;
-
-fn main() {
-    // let ast = Conjuration {
-    //     bindings: vec![Binding {
-    //         manifest: Manifest {
-    //             symbol: "*".to_string(),
-    //             ty: Type::Inferred,
-    //         },
-    //         value: Value::Spell(Spell {
-    //             components: vec![],
-    //             actions: vec![Action::Cast(Cast {
-    //                 spell: Box::new(Action::Value(Value::Symbol("utter".to_string()))),
-    //                 components: vec![Action::Value(Value::Element(Element::Phrase(
-    //                     "Hello World!".to_string(),
-    //                 )))],
-    //             })],
-    //             ty: Type::Nil,
-    //         }),
-    //     }],
-    // };
-
-    // dbg!(&ast);
-    // dbg!(Figure::from(ast));
-    //
-    // let mut weird_rect = bounding::Rect::from_width_height(2.0, 1.0);
-    // weird_rect.set_center(vector![-0.5, 0.0]);
-    // weird_rect.rotate(0.25 * f64::consts::TAU);
-    // dbg!(&weird_rect);
-
-    // let polygon = bounding::RegularPolygon::<5>::wrap(&weird_rect, 0.8, 0.0);
-    // dbg!(&polygon);
-
-    // let filling_circle = bounding::Circle::fill(&weird_rect, 0.0);
-    // dbg!(&filling_circle);
-
-    let rect = bounding::Rect::from_width_height(1.0, 1.0);
-    let triangle = bounding::RegularPolygon::fill(&rect, 3, 0.3, 0.0);
-    dbg!(&rect);
-    dbg!(&triangle);
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, Context};
+use clap::Parser;
+use conjure::{ast, diagnostic::LineIndex, syntax};
+use conjure_visualizer::{
+    font::FontRegistry,
+    layout::{LayoutConfig, LayoutParams, Node},
+    render::{self, OutputFormat},
+    visual::Figure,
+};
+
+/// Draws a Conjure program as a magic circle.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// The program to draw, either as source or as a JSON-encoded AST (.json)
+    input: PathBuf,
+
+    /// The file to write; its extension selects the format (svg, png or pdf)
+    #[arg(short, long, value_parser = parse_output)]
+    output: (PathBuf, OutputFormat),
+
+    /// Width and height of the output, in pixels (points for PDF)
+    #[arg(short, long, default_value_t = 1024)]
+    size: u32,
+
+    /// Font for symbols, either a builtin family name or a font file
+    #[arg(long)]
+    symbol_font: Option<String>,
+
+    /// Font for phrases, either a builtin family name or a font file
+    #[arg(long)]
+    phrase_font: Option<String>,
+
+    /// TOML file overriding layout parameters
+    #[arg(short, long)]
+    params: Option<PathBuf>,
+}
+
+fn parse_output(value: &str) -> anyhow::Result<(PathBuf, OutputFormat)> {
+    let path = PathBuf::from(value);
+    let format = OutputFormat::from_path(&path)?;
+    Ok((path, format))
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let conjuration = read_input(&args.input)?;
+
+    let mut config = match &args.params {
+        Some(path) => read_config(path)?,
+        None => LayoutConfig::default(),
+    };
+    let mut registry = FontRegistry::with_builtins()?;
+    if let Some(font) = args.symbol_font {
+        config.symbol_font = select_font(&mut registry, font)?;
+    }
+    if let Some(font) = args.phrase_font {
+        config.phrase_font = select_font(&mut registry, font)?;
+    }
+    let params = LayoutParams::new(&registry, &config)?;
+
+    let root = Node::construct(&params, Figure::from(conjuration));
+    let (output, format) = args.output;
+    let bytes = render::render_to_bytes(format, &params, &root, args.size)?;
+    fs::write(&output, bytes).with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(())
+}
+
+fn read_input(path: &Path) -> anyhow::Result<ast::Conjuration> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        return serde_json::from_str(&source)
+            .with_context(|| format!("Failed to parse AST from {}", path.display()));
+    }
+    syntax::parse(&source).map_err(|diagnostic| {
+        let (line, column) = LineIndex::new(&source).position(diagnostic.span.start);
+        anyhow!(
+            "{}:{}:{}: {}",
+            path.display(),
+            line + 1,
+            column + 1,
+            diagnostic.message
+        )
+    })
+}
+
+fn read_config(path: &Path) -> anyhow::Result<LayoutConfig> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&source)
+        .with_context(|| format!("Invalid layout parameters in {}", path.display()))
+}
+
+/// Resolves a font given on the command line to a family in `registry`, loading it first if it
+/// names a font file rather than a known family.
+fn select_font(registry: &mut FontRegistry, font: String) -> anyhow::Result<String> {
+    if registry.get(&font).is_ok() || !Path::new(&font).is_file() {
+        return Ok(font);
+    }
+    registry.load(font.clone(), &font)?;
+    Ok(font)
 }
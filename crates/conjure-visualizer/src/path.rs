@@ -0,0 +1,132 @@
+use std::f64;
+
+use nalgebra::{vector, Vector2};
+
+use crate::{bounding::TransformShape, transform::Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Vector2<f64>),
+    LineTo(Vector2<f64>),
+    QuadTo(Vector2<f64>, Vector2<f64>),
+    CubicTo(Vector2<f64>, Vector2<f64>, Vector2<f64>),
+    Close,
+}
+
+/// A vector path made of lines and quadratic or cubic Bézier curves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(start: Vector2<f64>, end: Vector2<f64>) -> Self {
+        let mut path = Self::new();
+        path.move_to(start);
+        path.line_to(end);
+        path
+    }
+
+    pub fn polygon(vertices: impl IntoIterator<Item = Vector2<f64>>) -> Self {
+        let mut path = Self::new();
+        for (i, vertex) in vertices.into_iter().enumerate() {
+            if i == 0 {
+                path.move_to(vertex);
+            } else {
+                path.line_to(vertex);
+            }
+        }
+        path.close();
+        path
+    }
+
+    pub fn circle(center: Vector2<f64>, radius: f64) -> Self {
+        let mut path = Self::arc(center, radius, 0.0, f64::consts::TAU);
+        path.close();
+        path
+    }
+
+    /// Approximates a circular arc with cubic Bézier curves of at most a quarter turn each.
+    pub fn arc(center: Vector2<f64>, radius: f64, start_angle: f64, sweep: f64) -> Self {
+        let num_segments = (sweep.abs() / (0.25 * f64::consts::TAU)).ceil().max(1.0);
+        let segment_sweep = sweep / num_segments;
+        let handle_length = 4.0 / 3.0 * f64::tan(0.25 * segment_sweep) * radius;
+
+        let point = |angle: f64| center + radius * vector![angle.cos(), angle.sin()];
+        let tangent = |angle: f64| vector![-angle.sin(), angle.cos()];
+
+        let mut path = Self::new();
+        path.move_to(point(start_angle));
+        for i in 0..num_segments as usize {
+            let from = start_angle + (i as f64) * segment_sweep;
+            let to = from + segment_sweep;
+            path.cubic_to(
+                point(from) + handle_length * tangent(from),
+                point(to) - handle_length * tangent(to),
+                point(to),
+            );
+        }
+        path
+    }
+
+    pub fn move_to(&mut self, to: Vector2<f64>) {
+        self.segments.push(PathSegment::MoveTo(to));
+    }
+
+    pub fn line_to(&mut self, to: Vector2<f64>) {
+        self.segments.push(PathSegment::LineTo(to));
+    }
+
+    pub fn quad_to(&mut self, control: Vector2<f64>, to: Vector2<f64>) {
+        self.segments.push(PathSegment::QuadTo(control, to));
+    }
+
+    pub fn cubic_to(&mut self, control_1: Vector2<f64>, control_2: Vector2<f64>, to: Vector2<f64>) {
+        self.segments
+            .push(PathSegment::CubicTo(control_1, control_2, to));
+    }
+
+    pub fn close(&mut self) {
+        self.segments.push(PathSegment::Close);
+    }
+
+    pub fn extend(&mut self, other: Path) {
+        self.segments.extend(other.segments);
+    }
+
+    #[inline]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl TransformShape for Path {
+    fn transform(&mut self, transform: &Transform) {
+        for segment in &mut self.segments {
+            match segment {
+                PathSegment::MoveTo(to) | PathSegment::LineTo(to) => {
+                    *to = transform.apply(*to);
+                }
+                PathSegment::QuadTo(control, to) => {
+                    *control = transform.apply(*control);
+                    *to = transform.apply(*to);
+                }
+                PathSegment::CubicTo(control_1, control_2, to) => {
+                    *control_1 = transform.apply(*control_1);
+                    *control_2 = transform.apply(*control_2);
+                    *to = transform.apply(*to);
+                }
+                PathSegment::Close => (),
+            }
+        }
+    }
+}
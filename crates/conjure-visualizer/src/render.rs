@@ -0,0 +1,359 @@
+use std::{f64, path::Path as FilePath};
+
+use anyhow::bail;
+use nalgebra::{vector, Vector2};
+
+use crate::{
+    bounding::{ConvexHull, Polygon, TransformShape},
+    layout::{self, LayoutNode, LayoutParams, Node},
+    path::Path,
+    transform::{Transform, TransformStack},
+    visual,
+};
+
+mod pdf;
+mod png;
+mod svg;
+
+pub use pdf::PdfCanvas;
+pub use png::PngCanvas;
+pub use svg::SvgCanvas;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Paint {
+    Stroke { width: f64, dashed: bool },
+    Fill,
+}
+
+/// A drawing surface. Paths passed to a canvas are already in world (output) coordinates.
+pub trait Canvas {
+    fn draw_path(&mut self, path: &Path, paint: Paint);
+
+    fn finish(self) -> anyhow::Result<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Svg,
+    Png,
+    Pdf,
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &FilePath) -> anyhow::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("svg") => Ok(Self::Svg),
+            Some("png") => Ok(Self::Png),
+            Some("pdf") => Ok(Self::Pdf),
+            Some(other) => {
+                bail!("Unsupported output format \"{other}\" (expected svg, png or pdf)")
+            }
+            None => bail!("Output path has no extension (expected .svg, .png or .pdf)"),
+        }
+    }
+}
+
+pub fn render_to_bytes(
+    format: OutputFormat,
+    params: &LayoutParams,
+    root: &Node,
+    size: u32,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        OutputFormat::Svg => render(params, root, SvgCanvas::new(size), size),
+        OutputFormat::Png => render(params, root, PngCanvas::new(size)?, size),
+        OutputFormat::Pdf => render(params, root, PdfCanvas::new(size), size),
+    }
+}
+
+/// Draws a layout tree onto a square canvas of the given size, scaling it to fit.
+pub fn render<C: Canvas>(
+    params: &LayoutParams,
+    root: &Node,
+    canvas: C,
+    size: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let view = fit_to_view(root, f64::from(size));
+    let mut renderer = Renderer {
+        params,
+        stroke_width: params.stroke_width * view.scale_factor(),
+        stack: TransformStack::new(view),
+        canvas,
+    };
+    renderer.node(root);
+    renderer.canvas.finish()
+}
+
+fn fit_to_view(root: &Node, size: f64) -> Transform {
+    const MARGIN_RATIO: f64 = 0.05;
+
+    let (x_range, y_range) = root.boundary().convex_coords_range();
+    let extent = f64::max(x_range.end - x_range.start, y_range.end - y_range.start);
+    let Some(scaling) = Transform::scaling((1.0 - 2.0 * MARGIN_RATIO) * size / extent) else {
+        return Transform::translation(vector![0.5 * size, 0.5 * size]);
+    };
+    let center = 0.5 * vector![x_range.start + x_range.end, y_range.start + y_range.end];
+    Transform::translation(vector![0.5 * size, 0.5 * size])
+        * scaling
+        * Transform::translation(-center)
+}
+
+struct Renderer<'a, 'p, C> {
+    params: &'a LayoutParams<'p>,
+    stroke_width: f64,
+    stack: TransformStack,
+    canvas: C,
+}
+
+impl<C: Canvas> Renderer<'_, '_, C> {
+    const NUM_RAYS: usize = 24;
+    const NUM_ARROWHEADS: usize = 12;
+
+    fn draw(&mut self, mut path: Path, paint: Paint) {
+        path.transform(&self.stack.current());
+        self.canvas.draw_path(&path, paint);
+    }
+
+    fn stroke(&mut self, path: Path, pattern: visual::StrokePattern) {
+        let paint = Paint::Stroke {
+            width: self.stroke_width,
+            dashed: pattern == visual::StrokePattern::Chain,
+        };
+        self.draw(path, paint);
+    }
+
+    fn node(&mut self, node: &Node) {
+        self.stack.push(node.transform());
+        match node {
+            Node::Symbol(symbol) => self.draw(symbol.outline(self.params), Paint::Fill),
+            Node::Phrase(phrase) => self.draw(phrase.outline(self.params), Paint::Fill),
+            Node::Inscription(inscription) => {
+                self.draw(inscription.outline(self.params), Paint::Fill)
+            }
+            Node::Pentagram(pentagram) => self.pentagram(pentagram),
+            Node::Circle(circle) => self.circle(circle),
+            Node::SplitCircle(split) => self.split_circle(split),
+            Node::RegularPolygon(polygon) => self.regular_polygon(polygon),
+            Node::Decorated(decorated) => self.decorated(decorated),
+            Node::Labeled(labeled) => {
+                self.node(&labeled.label);
+                self.node(&labeled.child);
+            }
+            Node::Emphasized(emphasized) => self.emphasized(emphasized),
+            Node::Link(link) => self.link(link),
+            Node::Arrangement(arrangement) => {
+                arrangement.items.iter().for_each(|n| self.node(n));
+            }
+        }
+        self.stack.pop();
+    }
+
+    fn pentagram(&mut self, pentagram: &layout::Pentagram) {
+        for line in pentagram.boundary.lines() {
+            self.stroke(
+                Path::line(line.start, line.end),
+                visual::StrokePattern::Line,
+            );
+        }
+        self.node(&pentagram.child);
+    }
+
+    fn circle(&mut self, circle: &layout::Circle) {
+        let outer = &circle.boundary;
+        self.stroke(Path::circle(outer.center(), outer.radius()), circle.stroke);
+        if circle.double {
+            let inner = circle.band.inner();
+            self.stroke(Path::circle(inner.center(), inner.radius()), circle.stroke);
+        }
+        self.circle_pattern(circle);
+        circle.rim.iter().for_each(|n| self.node(n));
+        self.node(&circle.content);
+    }
+
+    fn circle_pattern(&mut self, circle: &layout::Circle) {
+        let center = circle.band.center();
+        let outer_radius = circle.band.outer_radius();
+        let inner_radius = if circle.double {
+            circle.band.inner_radius()
+        } else {
+            outer_radius - self.params.circle_content_margin * self.params.stroke_width
+        };
+        let mean_radius = 0.5 * (inner_radius + outer_radius);
+        let band_width = outer_radius - inner_radius;
+        let line = visual::StrokePattern::Line;
+
+        let num_marks = |spacing: f64| {
+            usize::max(
+                3,
+                (f64::consts::TAU * mean_radius / spacing).floor() as usize,
+            )
+        };
+        let direction = |i: usize, n: usize| {
+            let angle = (i as f64) * f64::consts::TAU / (n as f64);
+            vector![angle.cos(), angle.sin()]
+        };
+
+        match circle.pattern {
+            visual::CirclePattern::None => (),
+            visual::CirclePattern::Rings => {
+                self.stroke(Path::circle(center, mean_radius), line);
+            }
+            visual::CirclePattern::ConcentricLines => {
+                for ratio in [1.0 / 3.0, 2.0 / 3.0] {
+                    let radius = inner_radius + ratio * band_width;
+                    self.stroke(Path::circle(center, radius), line);
+                }
+            }
+            visual::CirclePattern::Dots => {
+                let dot_radius = 0.2 * band_width;
+                let n = num_marks(4.0 * dot_radius);
+                for i in 0..n {
+                    let position = center + mean_radius * direction(i, n);
+                    self.draw(Path::circle(position, dot_radius), Paint::Fill);
+                }
+            }
+            visual::CirclePattern::StrokeTriangles | visual::CirclePattern::FillTriangles => {
+                let n = num_marks(band_width);
+                let vertex =
+                    |angle: f64, radius: f64| center + radius * vector![angle.cos(), angle.sin()];
+                for i in 0..n {
+                    let angle = (i as f64) * f64::consts::TAU / (n as f64);
+                    let half_step = 0.5 * f64::consts::TAU / (n as f64);
+                    let triangle = Path::polygon([
+                        vertex(angle - half_step, inner_radius),
+                        vertex(angle, outer_radius),
+                        vertex(angle + half_step, inner_radius),
+                    ]);
+                    if circle.pattern == visual::CirclePattern::FillTriangles {
+                        self.draw(triangle, Paint::Fill);
+                    } else {
+                        self.stroke(triangle, line);
+                    }
+                }
+            }
+            visual::CirclePattern::Runes => {
+                let n = num_marks(0.5 * band_width);
+                for i in 0..n {
+                    let length = if i % 2 == 0 { 0.8 } else { 0.4 } * band_width;
+                    let start = center + (mean_radius - 0.5 * length) * direction(i, n);
+                    let end = center + (mean_radius + 0.5 * length) * direction(i, n);
+                    self.stroke(Path::line(start, end), line);
+                }
+            }
+            visual::CirclePattern::Chevrons => {
+                let n = num_marks(3.0 * band_width);
+                let half_step = 0.25 * f64::consts::TAU / (n as f64);
+                let vertex =
+                    |angle: f64, radius: f64| center + radius * vector![angle.cos(), angle.sin()];
+                for i in 0..n {
+                    let angle = (i as f64) * f64::consts::TAU / (n as f64);
+                    let mut chevron = Path::new();
+                    chevron.move_to(vertex(angle - half_step, inner_radius));
+                    chevron.line_to(vertex(angle + half_step, mean_radius));
+                    chevron.line_to(vertex(angle - half_step, outer_radius));
+                    self.stroke(chevron, line);
+                }
+            }
+            visual::CirclePattern::Notches => {
+                let n = num_marks(5.0 * band_width);
+                let offset = 0.1 * f64::consts::TAU / (n as f64);
+                for i in 0..n {
+                    let angle = (i as f64) * f64::consts::TAU / (n as f64);
+                    for angle in [angle - offset, angle + offset] {
+                        let direction = vector![angle.cos(), angle.sin()];
+                        let start = center + inner_radius * direction;
+                        let end = center + outer_radius * direction;
+                        self.stroke(Path::line(start, end), line);
+                    }
+                }
+            }
+        }
+    }
+
+    fn split_circle(&mut self, split: &layout::SplitCircle) {
+        let outer = &split.boundary;
+        self.stroke(Path::circle(outer.center(), outer.radius()), split.stroke);
+        for divider in &split.dividers {
+            self.stroke(Path::line(divider.start, divider.end), split.stroke);
+        }
+        split.arms.iter().for_each(|n| self.node(n));
+    }
+
+    fn regular_polygon(&mut self, polygon: &layout::RegularPolygon) {
+        self.stroke(Path::polygon(polygon.boundary.vertices()), polygon.stroke);
+        self.node(&polygon.child);
+    }
+
+    fn decorated(&mut self, decorated: &layout::Decorated) {
+        let rect = &decorated.decoration_rect;
+        let center = rect.center();
+        let half_width = 0.5 * rect.width();
+        let half_height = 0.5 * rect.height();
+        let point = |x: f64, y: f64| center + vector![x * half_width, y * half_height];
+
+        let mut path = Path::new();
+        match decorated.kind {
+            visual::DecorationKind::Hat => {
+                path.move_to(point(-1.0, 1.0));
+                path.line_to(point(0.0, -1.0));
+                path.line_to(point(1.0, 1.0));
+            }
+            visual::DecorationKind::Tilde => {
+                path.move_to(point(-1.0, 0.0));
+                path.cubic_to(point(-0.5, -2.0), point(0.5, 2.0), point(1.0, 0.0));
+            }
+        }
+        self.stroke(path, visual::StrokePattern::Line);
+        self.node(&decorated.child);
+    }
+
+    fn emphasized(&mut self, emphasized: &layout::Emphasized) {
+        let center = emphasized.boundary.center();
+        let outer_radius = emphasized.boundary.radius();
+        let inner_radius = emphasized.child.boundary().convex_radius();
+        match emphasized.kind {
+            visual::EmphasisKind::Rays => {
+                for i in 0..Self::NUM_RAYS {
+                    let angle = (i as f64) * f64::consts::TAU / (Self::NUM_RAYS as f64);
+                    let direction: Vector2<f64> = vector![angle.cos(), angle.sin()];
+                    let ray_start = inner_radius + 0.5 * (outer_radius - inner_radius);
+                    self.stroke(
+                        Path::line(
+                            center + ray_start * direction,
+                            center + outer_radius * direction,
+                        ),
+                        visual::StrokePattern::Line,
+                    );
+                }
+            }
+            visual::EmphasisKind::Ouroboros => {
+                let radius = 0.5 * (inner_radius + outer_radius);
+                let half_width = 0.25 * (outer_radius - inner_radius);
+                let depth = 2.0 * half_width / radius;
+                let point =
+                    |angle: f64, radius: f64| center + radius * vector![angle.cos(), angle.sin()];
+                self.stroke(Path::circle(center, radius), visual::StrokePattern::Line);
+                for i in 0..Self::NUM_ARROWHEADS {
+                    let tip = (i as f64) * f64::consts::TAU / (Self::NUM_ARROWHEADS as f64);
+                    let mut path = Path::new();
+                    path.move_to(point(tip - depth, radius - half_width));
+                    path.line_to(point(tip, radius));
+                    path.line_to(point(tip - depth, radius + half_width));
+                    self.stroke(path, visual::StrokePattern::Line);
+                }
+            }
+        }
+        self.node(&emphasized.child);
+    }
+
+    fn link(&mut self, link: &layout::Link) {
+        for segment in &link.segments {
+            self.stroke(Path::line(segment.start, segment.end), link.stroke);
+        }
+        link.items.iter().for_each(|n| self.node(n));
+    }
+}
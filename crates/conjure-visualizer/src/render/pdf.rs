@@ -0,0 +1,113 @@
+use nalgebra::Vector2;
+use pdf_writer::{
+    types::{LineCapStyle, LineJoinStyle},
+    Content, Finish, Pdf, Rect, Ref,
+};
+
+use crate::path::{Path, PathSegment};
+
+use super::{Canvas, Paint};
+
+pub struct PdfCanvas {
+    size: u32,
+    content: Content,
+}
+
+impl PdfCanvas {
+    pub fn new(size: u32) -> Self {
+        let mut content = Content::new();
+        content.set_line_cap(LineCapStyle::RoundCap);
+        content.set_line_join(LineJoinStyle::RoundJoin);
+        Self { size, content }
+    }
+
+    fn point(&self, point: Vector2<f64>) -> (f32, f32) {
+        (point.x as f32, (f64::from(self.size) - point.y) as f32)
+    }
+
+    fn build_path(&mut self, path: &Path) {
+        let mut current = Vector2::zeros();
+        let mut contour_start = Vector2::zeros();
+        for segment in path.segments() {
+            match *segment {
+                PathSegment::MoveTo(to) => {
+                    let (x, y) = self.point(to);
+                    self.content.move_to(x, y);
+                    current = to;
+                    contour_start = to;
+                }
+                PathSegment::LineTo(to) => {
+                    let (x, y) = self.point(to);
+                    self.content.line_to(x, y);
+                    current = to;
+                }
+                PathSegment::QuadTo(control, to) => {
+                    let control_1 = current + 2.0 / 3.0 * (control - current);
+                    let control_2 = to + 2.0 / 3.0 * (control - to);
+                    self.cubic_to(control_1, control_2, to);
+                    current = to;
+                }
+                PathSegment::CubicTo(control_1, control_2, to) => {
+                    self.cubic_to(control_1, control_2, to);
+                    current = to;
+                }
+                PathSegment::Close => {
+                    self.content.close_path();
+                    current = contour_start;
+                }
+            }
+        }
+    }
+
+    fn cubic_to(&mut self, control_1: Vector2<f64>, control_2: Vector2<f64>, to: Vector2<f64>) {
+        let (x1, y1) = self.point(control_1);
+        let (x2, y2) = self.point(control_2);
+        let (x3, y3) = self.point(to);
+        self.content.cubic_to(x1, y1, x2, y2, x3, y3);
+    }
+}
+
+impl Canvas for PdfCanvas {
+    fn draw_path(&mut self, path: &Path, paint: Paint) {
+        if path.is_empty() {
+            return;
+        }
+        match paint {
+            Paint::Fill => {
+                self.build_path(path);
+                self.content.fill_nonzero();
+            }
+            Paint::Stroke { width, dashed } => {
+                let width = width as f32;
+                self.content.set_line_width(width);
+                if dashed {
+                    self.content
+                        .set_dash_pattern([4.0 * width, 2.0 * width], 0.0);
+                } else {
+                    self.content.set_dash_pattern([], 0.0);
+                }
+                self.build_path(path);
+                self.content.stroke();
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<Vec<u8>> {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let page_id = Ref::new(3);
+        let content_id = Ref::new(4);
+        let size = self.size as f32;
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id).kids([page_id]).count(1);
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, size, size));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.finish();
+        pdf.stream(content_id, &self.content.finish());
+        Ok(pdf.finish())
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Context;
+
+use crate::path::{Path, PathSegment};
+
+use super::{Canvas, Paint};
+
+pub struct PngCanvas {
+    pixmap: tiny_skia::Pixmap,
+}
+
+impl PngCanvas {
+    pub fn new(size: u32) -> anyhow::Result<Self> {
+        let mut pixmap =
+            tiny_skia::Pixmap::new(size, size).context("Invalid size for PNG output")?;
+        pixmap.fill(tiny_skia::Color::WHITE);
+        Ok(Self { pixmap })
+    }
+
+    fn skia_path(path: &Path) -> Option<tiny_skia::Path> {
+        let mut builder = tiny_skia::PathBuilder::new();
+        for segment in path.segments() {
+            match segment {
+                PathSegment::MoveTo(to) => builder.move_to(to.x as f32, to.y as f32),
+                PathSegment::LineTo(to) => builder.line_to(to.x as f32, to.y as f32),
+                PathSegment::QuadTo(c, to) => {
+                    builder.quad_to(c.x as f32, c.y as f32, to.x as f32, to.y as f32)
+                }
+                PathSegment::CubicTo(c1, c2, to) => builder.cubic_to(
+                    c1.x as f32,
+                    c1.y as f32,
+                    c2.x as f32,
+                    c2.y as f32,
+                    to.x as f32,
+                    to.y as f32,
+                ),
+                PathSegment::Close => builder.close(),
+            }
+        }
+        builder.finish()
+    }
+}
+
+impl Canvas for PngCanvas {
+    fn draw_path(&mut self, path: &Path, paint: Paint) {
+        let Some(path) = Self::skia_path(path) else {
+            return;
+        };
+        let mut skia_paint = tiny_skia::Paint::default();
+        skia_paint.set_color(tiny_skia::Color::BLACK);
+        skia_paint.anti_alias = true;
+
+        let transform = tiny_skia::Transform::identity();
+        match paint {
+            Paint::Fill => self.pixmap.fill_path(
+                &path,
+                &skia_paint,
+                tiny_skia::FillRule::Winding,
+                transform,
+                None,
+            ),
+            Paint::Stroke { width, dashed } => {
+                let width = width as f32;
+                let stroke = tiny_skia::Stroke {
+                    width,
+                    line_cap: tiny_skia::LineCap::Round,
+                    line_join: tiny_skia::LineJoin::Round,
+                    dash: dashed
+                        .then(|| tiny_skia::StrokeDash::new(vec![4.0 * width, 2.0 * width], 0.0))
+                        .flatten(),
+                    ..Default::default()
+                };
+                self.pixmap
+                    .stroke_path(&path, &skia_paint, &stroke, transform, None);
+            }
+        }
+    }
+
+    fn finish(self) -> anyhow::Result<Vec<u8>> {
+        self.pixmap.encode_png().context("Failed to encode PNG")
+    }
+}
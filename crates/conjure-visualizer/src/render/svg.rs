@@ -0,0 +1,75 @@
+use std::fmt::Write;
+
+use crate::path::{Path, PathSegment};
+
+use super::{Canvas, Paint};
+
+pub struct SvgCanvas {
+    size: u32,
+    body: String,
+}
+
+impl SvgCanvas {
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            body: String::new(),
+        }
+    }
+
+    fn path_data(path: &Path) -> String {
+        let mut data = String::new();
+        for segment in path.segments() {
+            let _ = match segment {
+                PathSegment::MoveTo(to) => write!(data, "M{:.3} {:.3}", to.x, to.y),
+                PathSegment::LineTo(to) => write!(data, "L{:.3} {:.3}", to.x, to.y),
+                PathSegment::QuadTo(c, to) => {
+                    write!(data, "Q{:.3} {:.3} {:.3} {:.3}", c.x, c.y, to.x, to.y)
+                }
+                PathSegment::CubicTo(c1, c2, to) => write!(
+                    data,
+                    "C{:.3} {:.3} {:.3} {:.3} {:.3} {:.3}",
+                    c1.x, c1.y, c2.x, c2.y, to.x, to.y
+                ),
+                PathSegment::Close => write!(data, "Z"),
+            };
+        }
+        data
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn draw_path(&mut self, path: &Path, paint: Paint) {
+        if path.is_empty() {
+            return;
+        }
+        let data = Self::path_data(path);
+        let _ = match paint {
+            Paint::Fill => writeln!(self.body, r#"  <path d="{data}" fill="black"/>"#),
+            Paint::Stroke { width, dashed } => {
+                let dash = if dashed {
+                    format!(
+                        r#" stroke-dasharray="{:.3} {:.3}""#,
+                        4.0 * width,
+                        2.0 * width
+                    )
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    self.body,
+                    r#"  <path d="{data}" fill="none" stroke="black" stroke-width="{width:.3}" stroke-linecap="round" stroke-linejoin="round"{dash}/>"#
+                )
+            }
+        };
+    }
+
+    fn finish(self) -> anyhow::Result<Vec<u8>> {
+        let size = self.size;
+        let svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" viewBox=\"0 0 {size} {size}\">\n  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{}</svg>\n",
+            self.body
+        );
+        Ok(svg.into_bytes())
+    }
+}
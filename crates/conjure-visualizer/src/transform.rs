@@ -0,0 +1,124 @@
+use std::ops::Mul;
+
+use nalgebra::{Matrix3, Point2, Similarity2, Vector2};
+
+/// A 2D similarity transform (translation, rotation and uniform scaling).
+///
+/// Transforms compose like matrices: `a * b` first applies `b`, then `a`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform(Similarity2<f64>);
+
+impl Transform {
+    pub fn new(translation: Vector2<f64>, angle: f64, factor: f64) -> Self {
+        Self(Similarity2::new(translation, angle, factor))
+    }
+
+    pub fn identity() -> Self {
+        Self(Similarity2::identity())
+    }
+
+    pub fn translation(amount: Vector2<f64>) -> Self {
+        Self::new(amount, 0.0, 1.0)
+    }
+
+    pub fn rotation(angle: f64) -> Self {
+        Self::new(Vector2::zeros(), angle, 1.0)
+    }
+
+    /// A scaling by `factor`, unless the factor is not positive and finite, which no similarity
+    /// can scale by.
+    pub fn scaling(factor: f64) -> Option<Self> {
+        (factor.is_finite() && factor > 0.0).then(|| Self::new(Vector2::zeros(), 0.0, factor))
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self(self.0.inverse())
+    }
+
+    pub fn apply(&self, point: Vector2<f64>) -> Vector2<f64> {
+        self.0.transform_point(&Point2::from(point)).coords
+    }
+
+    pub fn apply_vector(&self, vector: Vector2<f64>) -> Vector2<f64> {
+        self.0.transform_vector(&vector)
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.0.scaling()
+    }
+
+    #[inline]
+    pub fn angle(&self) -> f64 {
+        self.0.isometry.rotation.angle()
+    }
+
+    #[inline]
+    pub fn offset(&self) -> Vector2<f64> {
+        self.0.isometry.translation.vector
+    }
+
+    pub fn to_matrix(&self) -> Matrix3<f64> {
+        self.0.to_homogeneous()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+impl Mul<&Transform> for &Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: &Transform) -> Self::Output {
+        Transform(self.0 * rhs.0)
+    }
+}
+
+/// The stack of transforms from the root of a layout tree down to the node currently being
+/// visited. Renderers push a node's transform before drawing it and pop it afterwards, so that
+/// [`TransformStack::current`] always maps node-local coordinates to world coordinates.
+#[derive(Debug, Clone)]
+pub struct TransformStack {
+    stack: Vec<Transform>,
+}
+
+impl TransformStack {
+    pub fn new(root: Transform) -> Self {
+        Self { stack: vec![root] }
+    }
+
+    #[inline]
+    pub fn current(&self) -> Transform {
+        *self.stack.last().expect("Transform stack root was popped")
+    }
+
+    pub fn push(&mut self, local: &Transform) {
+        let world = &self.current() * local;
+        self.stack.push(world);
+    }
+
+    pub fn pop(&mut self) {
+        assert!(
+            self.stack.len() > 1,
+            "Cannot pop the root of a transform stack"
+        );
+        self.stack.pop();
+    }
+}
+
+impl Default for TransformStack {
+    fn default() -> Self {
+        Self::new(Transform::identity())
+    }
+}
@@ -1,4 +1,4 @@
-use conjure::ast;
+use conjure::{ast, resolve};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CirclePattern {
@@ -9,6 +9,10 @@ pub enum CirclePattern {
     Dots,
     Runes,
     Rings,
+    /// Pairs of lines across the band, marking tagged values.
+    Notches,
+    /// Arrowheads pointing around the band, marking sequences.
+    Chevrons,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,6 +30,13 @@ pub struct Circle {
     pub content: Box<Figure>,
 }
 
+/// A circle divided into equal sectors, one for each arm.
+#[derive(Debug, Clone)]
+pub struct SplitCircle {
+    pub stroke: StrokePattern,
+    pub arms: Vec<Figure>,
+}
+
 #[derive(Debug, Clone)]
 pub struct RegularPolygon {
     pub sides: usize,
@@ -51,9 +62,18 @@ pub struct Decorated {
     pub content: Box<Figure>,
 }
 
+/// A figure named by a symbol drawn above it, such as the value of a record field.
+#[derive(Debug, Clone)]
+pub struct Labeled {
+    pub label: Symbol,
+    pub content: Box<Figure>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EmphasisKind {
     Rays,
+    /// Arrowheads chasing each other around the content, marking recursion and repetition.
+    Ouroboros,
 }
 
 #[derive(Debug, Clone)]
@@ -79,8 +99,10 @@ pub enum Figure {
     Phrase(Phrase),
     Pentagram(Pentagram),
     Circle(Circle),
+    SplitCircle(SplitCircle),
     RegularPolygon(RegularPolygon),
     Decorated(Decorated),
+    Labeled(Labeled),
     Emphasized(Emphasized),
     Link(Link),
     Arrangement(Vec<Figure>),
@@ -91,31 +113,84 @@ impl From<ast::Type> for CirclePattern {
         match value {
             ast::Type::Inferred => CirclePattern::None,
             ast::Type::Nil => CirclePattern::Rings,
-            _ => todo!("Type {:?} not yet supported!", value),
+            ast::Type::Simple(ast::SimpleType::Truth) => CirclePattern::Dots,
+            ast::Type::Simple(ast::SimpleType::NaturalCount) => CirclePattern::StrokeTriangles,
+            ast::Type::Simple(ast::SimpleType::WholeCount) => CirclePattern::FillTriangles,
+            ast::Type::Simple(ast::SimpleType::Amount) => CirclePattern::ConcentricLines,
+            ast::Type::Simple(ast::SimpleType::Phrase) => CirclePattern::Runes,
+            ast::Type::Optional(inner) => (*inner).into(),
+            ast::Type::Sequence(_) => CirclePattern::Chevrons,
+            ast::Type::Union(_) => CirclePattern::Notches,
+            ast::Type::Symbol(_)
+            | ast::Type::Named(..)
+            | ast::Type::Variable(_)
+            | ast::Type::Conjoined(_)
+            | ast::Type::Record(_)
+            | ast::Type::Charm(_)
+            | ast::Type::Spell(_)
+            | ast::Type::Type => CirclePattern::None,
         }
     }
 }
 
+/// Patterns that take a value apart nest the manifests of its parts, each under its own hat. A
+/// dashed circle marks a pattern that only matches optional values that are present, and a notched
+/// one a pattern that matches a single variant.
 impl From<ast::Manifest> for Figure {
     fn from(value: ast::Manifest) -> Self {
+        let (stroke, content) = match value.pattern {
+            ast::Pattern::Symbol(symbol) => {
+                (StrokePattern::Line, Figure::Symbol(Symbol(symbol.name)))
+            }
+            ast::Pattern::Wildcard => {
+                (StrokePattern::Line, Figure::Symbol(Symbol("_".to_string())))
+            }
+            ast::Pattern::Conjunction(manifests) => (
+                StrokePattern::Line,
+                Figure::Link(Link {
+                    items: manifests.into_iter().map(Into::into).collect(),
+                    stroke: StrokePattern::Line,
+                }),
+            ),
+            ast::Pattern::Optional(inner) => (StrokePattern::Chain, (*inner).into()),
+            ast::Pattern::Variant { tag, payload } => (
+                StrokePattern::Line,
+                variant(tag, payload.map(|payload| (*payload).into())),
+            ),
+        };
         Figure::Decorated(Decorated {
             kind: DecorationKind::Hat,
             content: Box::new(Figure::Circle(Circle {
-                stroke: StrokePattern::Line,
+                stroke,
                 double: false,
                 pattern: value.ty.into(),
                 rim: vec![],
-                content: Box::new(Figure::Symbol(Symbol(value.symbol))),
+                content: Box::new(content),
             })),
         })
     }
 }
 
+/// A tag in a notched circle, with its payload on the rim.
+fn variant(tag: ast::Symbol, payload: Option<Figure>) -> Figure {
+    Figure::Circle(Circle {
+        stroke: StrokePattern::Line,
+        double: false,
+        pattern: CirclePattern::Notches,
+        rim: payload.into_iter().collect(),
+        content: Box::new(Figure::Symbol(Symbol(tag.name))),
+    })
+}
+
 impl From<ast::Element> for Figure {
     fn from(value: ast::Element) -> Self {
         match value {
             ast::Element::Phrase(phrase) => Figure::Phrase(Phrase(phrase)),
-            _ => todo!("Element type not yet supported!"),
+            ast::Element::Nil => Figure::Phrase(Phrase("nil".to_string())),
+            ast::Element::Truth(truth) => Figure::Phrase(Phrase(truth.to_string())),
+            ast::Element::NaturalCount(n) => Figure::Phrase(Phrase(n.to_string())),
+            ast::Element::WholeCount(n) => Figure::Phrase(Phrase(format!("{n:+}"))),
+            ast::Element::Amount(x) => Figure::Phrase(Phrase(x.to_string())),
         }
     }
 }
@@ -128,33 +203,179 @@ impl From<ast::Value> for Figure {
                 double: false,
                 pattern: CirclePattern::None,
                 rim: vec![],
-                content: Box::new(Figure::Symbol(Symbol(symbol))),
+                content: Box::new(Figure::Symbol(Symbol(symbol.name))),
             }),
             ast::Value::Element(element) => Figure::RegularPolygon(RegularPolygon {
                 sides: 5,
                 stroke: StrokePattern::Line,
                 content: Box::new(element.into()),
             }),
+            ast::Value::Boundary(boundary) => boundary.into(),
+            ast::Value::Conjunction(values) => Figure::Link(Link {
+                items: values.into_iter().map(Into::into).collect(),
+                stroke: StrokePattern::Line,
+            }),
+            ast::Value::Record(fields) => Figure::Circle(Circle {
+                stroke: StrokePattern::Line,
+                double: false,
+                pattern: CirclePattern::None,
+                rim: fields
+                    .into_iter()
+                    .map(|field| {
+                        Figure::Labeled(Labeled {
+                            label: Symbol(field.symbol.name),
+                            content: Box::new(field.value.into()),
+                        })
+                    })
+                    .collect(),
+                // Everything a record holds is on its rim.
+                content: Box::new(Figure::Arrangement(Vec::new())),
+            }),
+            ast::Value::Projection(projection) => Figure::Labeled(Labeled {
+                label: Symbol(projection.field.name),
+                content: Box::new((*projection.record).into()),
+            }),
+            ast::Value::Sequence(sequence) => Figure::Circle(Circle {
+                stroke: StrokePattern::Line,
+                double: false,
+                pattern: CirclePattern::Chevrons,
+                rim: sequence.elements.into_iter().map(Into::into).collect(),
+                content: Box::new(Figure::Arrangement(Vec::new())),
+            }),
+            ast::Value::Variant(value) => {
+                variant(value.tag, value.payload.map(|payload| (*payload).into()))
+            }
+            ast::Value::Interpolation(interpolation) => Figure::RegularPolygon(RegularPolygon {
+                sides: 5,
+                stroke: StrokePattern::Line,
+                content: Box::new(Figure::Arrangement(
+                    interpolation.pieces.into_iter().map(Into::into).collect(),
+                )),
+            }),
+            ast::Value::Charm(charm) => charm.into(),
+            ast::Value::Invocation(invocation) => Figure::Circle(Circle {
+                stroke: StrokePattern::Line,
+                double: false,
+                pattern: CirclePattern::None,
+                rim: invocation.components.into_iter().map(Into::into).collect(),
+                content: Box::new(Figure::RegularPolygon(RegularPolygon {
+                    sides: 3,
+                    stroke: StrokePattern::Line,
+                    content: Box::new((*invocation.charm).into()),
+                })),
+            }),
             ast::Value::Spell(spell) => spell.into(),
-            _ => todo!("Value type not yet supported!"),
+            ast::Value::Type(ty) => Figure::Decorated(Decorated {
+                kind: DecorationKind::Tilde,
+                content: Box::new(Figure::Phrase(Phrase(ty.to_string()))),
+            }),
+            ast::Value::Divination(divination) => {
+                let split = Figure::SplitCircle(SplitCircle {
+                    stroke: StrokePattern::Line,
+                    arms: divination.arms.into_iter().map(Into::into).collect(),
+                });
+                match divination.subject {
+                    Some(subject) => Figure::Link(Link {
+                        items: vec![(*subject).into(), split],
+                        stroke: StrokePattern::Line,
+                    }),
+                    None => split,
+                }
+            }
         }
     }
 }
 
+/// Embedded values sit in the phrase frame among the text around them, under their format spec.
+impl From<ast::Piece> for Figure {
+    fn from(value: ast::Piece) -> Self {
+        match value {
+            ast::Piece::Text(text) => Figure::Phrase(Phrase(text)),
+            ast::Piece::Embed(embed) => {
+                let figure = (*embed.value).into();
+                match embed.spec {
+                    Some(spec) => Figure::Labeled(Labeled {
+                        label: Symbol(spec.to_string()),
+                        content: Box::new(figure),
+                    }),
+                    None => figure,
+                }
+            }
+        }
+    }
+}
+
+impl From<ast::Arm> for Figure {
+    fn from(value: ast::Arm) -> Self {
+        let omen = match value.omen {
+            ast::Omen::Truth(omen) => omen.into(),
+            ast::Omen::Presence(presence) => Figure::Link(Link {
+                items: vec![presence.manifest.into(), presence.value.into()],
+                stroke: StrokePattern::Chain,
+            }),
+            ast::Omen::Pattern(manifest) => manifest.into(),
+            ast::Omen::Otherwise => return value.value.into(),
+        };
+        Figure::Link(Link {
+            items: vec![omen, value.value.into()],
+            stroke: StrokePattern::Line,
+        })
+    }
+}
+
 impl From<ast::Action> for Figure {
     fn from(value: ast::Action) -> Self {
         match value {
             ast::Action::Value(value) => value.into(),
-            ast::Action::Cast(cast) => Figure::Circle(Circle {
-                stroke: StrokePattern::Line,
-                double: true,
-                pattern: CirclePattern::None,
-                rim: cast.components.into_iter().map(Into::into).collect(),
-                content: Box::new(Figure::Pentagram(Pentagram {
-                    content: Box::new((*cast.spell).into()),
-                })),
+            ast::Action::Cast(cast) => {
+                let figure = Figure::Circle(Circle {
+                    stroke: StrokePattern::Line,
+                    double: true,
+                    pattern: CirclePattern::None,
+                    rim: cast.components.into_iter().map(Into::into).collect(),
+                    content: Box::new(Figure::Pentagram(Pentagram {
+                        content: Box::new((*cast.spell).into()),
+                    })),
+                });
+                let Some(recovery) = cast.recovery else {
+                    return figure;
+                };
+                Figure::Link(Link {
+                    items: vec![
+                        figure,
+                        Figure::Circle(Circle {
+                            stroke: StrokePattern::Chain,
+                            double: true,
+                            pattern: CirclePattern::None,
+                            rim: recovery.failure.into_iter().map(Into::into).collect(),
+                            content: Box::new(recovery.actions.into()),
+                        }),
+                    ],
+                    stroke: StrokePattern::Chain,
+                })
+            }
+            ast::Action::Binding(binding) => binding.into(),
+            ast::Action::Channel(channel) => Figure::Link(Link {
+                items: vec![
+                    channel.manifest.into(),
+                    ast::Action::Cast(channel.cast).into(),
+                ],
+                stroke: StrokePattern::Chain,
             }),
-            _ => todo!("Action type not yet supported!"),
+            ast::Action::Repetition(repetition) => {
+                let mut rim = vec![repetition.count.into()];
+                rim.extend(repetition.counter.map(Into::into));
+                Figure::Emphasized(Emphasized {
+                    kind: EmphasisKind::Ouroboros,
+                    content: Box::new(Figure::Circle(Circle {
+                        stroke: StrokePattern::Line,
+                        double: true,
+                        pattern: CirclePattern::None,
+                        rim,
+                        content: Box::new(repetition.actions.into()),
+                    })),
+                })
+            }
         }
     }
 }
@@ -164,7 +385,7 @@ impl From<ast::ActionSequence> for Figure {
         if value.len() == 1 {
             return value.into_iter().next().unwrap().into();
         }
-        todo!("Only action sequences of length 1 are supported for now")
+        Figure::Arrangement(value.into_iter().map(Into::into).collect())
     }
 }
 
@@ -183,10 +404,53 @@ impl From<ast::Spell> for Figure {
     }
 }
 
+/// The bindings of a boundary, followed by its value.
+fn boundary_content(boundary: ast::Boundary) -> Figure {
+    let value = (*boundary.value).into();
+    if boundary.bindings.is_empty() {
+        return value;
+    }
+    let mut items: Vec<Figure> = boundary.bindings.into_iter().map(Into::into).collect();
+    items.push(value);
+    Figure::Arrangement(items)
+}
+
+impl From<ast::Boundary> for Figure {
+    fn from(value: ast::Boundary) -> Self {
+        Figure::Circle(Circle {
+            stroke: StrokePattern::Line,
+            double: false,
+            pattern: value.ty.clone().into(),
+            rim: vec![],
+            content: Box::new(boundary_content(value)),
+        })
+    }
+}
+
+impl From<ast::Charm> for Figure {
+    fn from(value: ast::Charm) -> Self {
+        Figure::Circle(Circle {
+            stroke: StrokePattern::Line,
+            double: false,
+            pattern: value.boundary.ty.clone().into(),
+            rim: value.components.into_iter().map(Into::into).collect(),
+            content: Box::new(boundary_content(value.boundary)),
+        })
+    }
+}
+
 impl From<ast::Binding> for Figure {
     fn from(value: ast::Binding) -> Self {
+        let recursive = resolve::is_recursive(&value);
+        let mut figure = value.value.into();
+        if recursive {
+            figure = Figure::Emphasized(Emphasized {
+                kind: EmphasisKind::Ouroboros,
+                content: Box::new(figure),
+            });
+        }
         Figure::Link(Link {
-            items: vec![value.manifest.into(), value.value.into()],
+            items: vec![value.manifest.into(), figure],
             stroke: StrokePattern::Chain,
         })
     }
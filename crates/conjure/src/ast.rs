@@ -1,8 +1,17 @@
+use std::{
+    borrow::Borrow,
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
 /// Any simple (primitive) type. These types are used as building blocks for more complex
 /// types.
 ///
 /// All simple types have associated elements (literals).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SimpleType {
     Truth,
     NaturalCount,
@@ -14,7 +23,8 @@ pub enum SimpleType {
 /// The type of a [`Charm`] (pure function). Consists of a [`ConjoinedType`] (tuple type) for the charm components
 /// (parameters), and a product [`Type`] (return type).
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CharmType {
     pub components: ConjoinedType,
     pub product: Box<Type>,
@@ -22,41 +32,355 @@ pub struct CharmType {
 
 /// The type of a [`Spell`] (imperative function). Consists of a [`ConjoinedType`] (tuple type) for the spell
 /// components, and a product [`Type`] (return type).
-#[derive(Debug, Clone)]
+///
+/// A spell that may fail says so (`spell(phrase) -> natural fails`). A spell that cannot fail may
+/// be used wherever one that may is expected, but not the other way around.
+///
+/// A spell type also names the [`Capabilities`] the spell may use (`uses [console]`). Likewise, a
+/// spell using fewer capabilities may be used wherever one using more is expected.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpellType {
     pub components: ConjoinedType,
     pub product: Box<Type>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fails: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub capabilities: Capabilities,
 }
 
+/// Access to a part of the outside world. Spells can only reach the outside world through
+/// builtins, each of which needs a capability, and the host decides which capabilities it grants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Capability {
+    /// Uttering and reading lines.
+    Console,
+    /// Loading and storing files.
+    Filesystem,
+    /// Telling the time.
+    Clock,
+    /// Drawing random amounts.
+    Randomness,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [
+        Self::Console,
+        Self::Filesystem,
+        Self::Clock,
+        Self::Randomness,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Console => "console",
+            Self::Filesystem => "filesystem",
+            Self::Clock => "clock",
+            Self::Randomness => "randomness",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// A set of [`Capability`]s.
+pub type Capabilities = BTreeSet<Capability>;
+
 /// The type of a [`Conjunction`] (tuple). Consists of a list of [`Type`]s.
 pub type ConjoinedType = Vec<Type>;
 
+/// The type of a [`Record`]. Consists of a list of [`FieldType`]s. Two record types are compatible
+/// if they have the same fields, in any order.
+pub type RecordType = Vec<FieldType>;
+
+/// The type of a [`Variant`] (tagged value). Consists of the [`VariantType`]s a value may be
+/// tagged with. A union type accepts values of any union type whose variants it includes.
+///
+/// [`Type::Optional`] behaves like a builtin union of a present and an absent variant when
+/// divinations are checked for exhaustiveness.
+pub type UnionType = Vec<VariantType>;
+
+/// A variant of a [`UnionType`]. Consists of its tag, and the [`Type`] of its payload, if it
+/// carries one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VariantType {
+    pub tag: Symbol,
+    pub payload: Option<Type>,
+}
+
+/// A named field of a [`RecordType`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldType {
+    pub symbol: Symbol,
+    pub ty: Type,
+}
+
 /// The type of a [`Value`].
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Inferred,
     Nil,
     Optional(Box<Type>),
+    /// A type named by a binding whose value is a [`Value::Type`]. The type it names may refer to
+    /// itself.
     Symbol(Symbol),
+    /// A named type as the type checker refers to it: the symbol that names it, and a number that
+    /// tells it apart from other types of the same name. Never written in source.
+    Named(Symbol, usize),
+    /// A type parameter of a generic [`Charm`] or [`Spell`], which stands for any type.
+    Variable(Symbol),
     Simple(SimpleType),
     Conjoined(ConjoinedType),
+    Record(RecordType),
+    /// The type of a [`Sequence`], whose elements all have the inner type.
+    Sequence(Box<Type>),
+    Union(UnionType),
     Charm(CharmType),
     Spell(SpellType),
     Type,
 }
 
-/// A declaration of a [`Symbol`] (identifier), that associates it with a [`Type`].
+impl Type {
+    /// The named types this type refers to, from left to right.
+    pub fn symbols(&self) -> Vec<&Symbol> {
+        let mut symbols = Vec::new();
+        self.collect_symbols(&mut symbols);
+        symbols
+    }
+
+    fn collect_symbols<'a>(&'a self, symbols: &mut Vec<&'a Symbol>) {
+        match self {
+            Type::Symbol(symbol) | Type::Named(symbol, _) => symbols.push(symbol),
+            Type::Inferred | Type::Nil | Type::Variable(_) | Type::Simple(_) | Type::Type => (),
+            Type::Optional(inner) | Type::Sequence(inner) => inner.collect_symbols(symbols),
+            Type::Conjoined(types) => types.iter().for_each(|t| t.collect_symbols(symbols)),
+            Type::Record(fields) => fields.iter().for_each(|f| f.ty.collect_symbols(symbols)),
+            Type::Union(variants) => variants
+                .iter()
+                .filter_map(|v| v.payload.as_ref())
+                .for_each(|t| t.collect_symbols(symbols)),
+            Type::Charm(CharmType {
+                components,
+                product,
+            })
+            | Type::Spell(SpellType {
+                components,
+                product,
+                ..
+            }) => {
+                components.iter().for_each(|t| t.collect_symbols(symbols));
+                product.collect_symbols(symbols);
+            }
+        }
+    }
+}
+
+/// A declaration of the [`Symbol`]s (identifiers) a value is bound to. Consists of a [`Pattern`]
+/// that takes the value apart, and the [`Type`] of the whole value.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Manifest {
-    pub symbol: Symbol,
+    pub pattern: Pattern,
     pub ty: Type,
+    /// The pattern and its type.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Manifest {
+    /// The symbol the whole value is bound to, if the pattern is a plain symbol.
+    pub fn symbol(&self) -> Option<&Symbol> {
+        match &self.pattern {
+            Pattern::Symbol(symbol) => Some(symbol),
+            _ => None,
+        }
+    }
+
+    /// All symbols the pattern binds, from left to right.
+    pub fn symbols(&self) -> Vec<&Symbol> {
+        let mut symbols = Vec::new();
+        self.collect_symbols(&mut symbols);
+        symbols
+    }
+
+    /// The declared types of this manifest and of the manifests in its pattern, from left to right.
+    pub fn types(&self) -> Vec<&Type> {
+        let mut types = vec![&self.ty];
+        match &self.pattern {
+            Pattern::Symbol(_) | Pattern::Wildcard => (),
+            Pattern::Conjunction(manifests) => types.extend(manifests.iter().flat_map(Self::types)),
+            Pattern::Optional(inner) => types.extend(inner.types()),
+            Pattern::Variant { payload, .. } => {
+                types.extend(payload.iter().flat_map(|p| p.types()))
+            }
+        }
+        types
+    }
+
+    fn collect_symbols<'a>(&'a self, symbols: &mut Vec<&'a Symbol>) {
+        match &self.pattern {
+            Pattern::Symbol(symbol) => symbols.push(symbol),
+            Pattern::Wildcard => (),
+            Pattern::Conjunction(manifests) => {
+                manifests.iter().for_each(|m| m.collect_symbols(symbols));
+            }
+            Pattern::Optional(inner) => inner.collect_symbols(symbols),
+            Pattern::Variant { payload, .. } => {
+                if let Some(payload) = payload {
+                    payload.collect_symbols(symbols);
+                }
+            }
+        }
+    }
+}
+
+/// The shape of a value bound by a [`Manifest`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    /// Binds the whole value to a [`Symbol`].
+    Symbol(Symbol),
+    /// Matches any value without binding it (`_`).
+    Wildcard,
+    /// Takes a [`Conjunction`] apart, binding each of its values to a [`Manifest`].
+    Conjunction(Vec<Manifest>),
+    /// Matches an optional value that is not nil, binding its content to a [`Manifest`] (`x?`).
+    /// Such a pattern is refutable, so it may only be used where a failed match has somewhere
+    /// else to go, such as a [`Presence`].
+    Optional(Box<Manifest>),
+    /// Matches a [`Variant`] with the given tag, binding its payload to a [`Manifest`]
+    /// (`#tag(x)`). Without a manifest, the payload is ignored (`#tag`).
+    Variant {
+        tag: Symbol,
+        payload: Option<Box<Manifest>>,
+    },
+}
+
+/// A range of byte offsets into the source a node was parsed from.
+///
+/// Nodes that were not parsed from source (for example, ASTs built by hand or deserialized) carry
+/// the empty span at offset zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+}
+
+/// `span`, unless it is the empty span of a node that was not parsed from source.
+fn known(span: Span) -> Option<Span> {
+    (span != Span::default()).then_some(span)
+}
+
+/// The smallest span covering all the known spans, if there are any.
+fn cover(spans: impl IntoIterator<Item = Option<Span>>) -> Option<Span> {
+    spans.into_iter().flatten().reduce(Span::to)
+}
+
+/// An identifier, together with the [`Span`] it occurs at.
+///
+/// Symbols compare, hash and serialize by name only; the span is purely informational.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "String", into = "String")
+)]
+pub struct Symbol {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Symbol {
+    pub fn new(name: impl Into<String>, span: Span) -> Self {
+        Self {
+            name: name.into(),
+            span,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Self::new(name, Span::default())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::new(name, Span::default())
+    }
 }
 
-/// An identifier.
-pub type Symbol = String;
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.name
+    }
+}
 
 /// A literal.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Element {
     Nil,
     Truth(bool),
@@ -68,22 +392,153 @@ pub enum Element {
 
 /// A value.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Element(Element),
     Symbol(Symbol),
     Boundary(Boundary),
     Conjunction(Conjunction),
+    Record(Record),
+    Projection(Projection),
+    Sequence(Sequence),
+    Variant(Variant),
+    Interpolation(Interpolation),
     Charm(Charm),
     Invocation(Invocation),
     Spell(Spell),
     Type(Type),
+    Divination(Divination),
+}
+
+impl Value {
+    /// The span of the source the value was parsed from, as far as its parts tell. Elements and
+    /// types carry no span, so values made of nothing else have none.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Element(_) | Self::Type(_) => None,
+            Self::Symbol(symbol) => known(symbol.span),
+            Self::Boundary(boundary) => known(boundary.span),
+            Self::Conjunction(values) => cover(values.iter().map(Value::span)),
+            Self::Record(fields) => cover(
+                fields
+                    .iter()
+                    .flat_map(|f| [known(f.symbol.span), f.value.span()]),
+            ),
+            Self::Projection(projection) => {
+                cover([projection.record.span(), known(projection.field.span)])
+            }
+            Self::Sequence(sequence) => known(sequence.span),
+            Self::Variant(variant) => cover([
+                known(variant.tag.span),
+                variant.payload.as_ref().and_then(|p| p.span()),
+            ]),
+            Self::Interpolation(interpolation) => known(interpolation.span),
+            Self::Charm(charm) => known(charm.boundary.span),
+            Self::Invocation(invocation) => cover(
+                std::iter::once(invocation.charm.span())
+                    .chain(invocation.components.iter().map(Value::span)),
+            ),
+            Self::Spell(spell) => known(spell.span),
+            Self::Divination(divination) => known(divination.span),
+        }
+    }
 }
 
 /// A tuple of values.
 pub type Conjunction = Vec<Value>;
 
-/// A constant definition.
+/// A tuple of values named by [`Field`]s.
+pub type Record = Vec<Field>;
+
+/// A named value of a [`Record`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Field {
+    pub symbol: Symbol,
+    pub value: Value,
+}
+
+/// Any number of values of the same type, in order (`[a, b, c]`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sequence {
+    pub elements: Vec<Value>,
+    /// The brackets and everything between them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+/// A value tagged with a symbol (`#tag`), which may carry a payload [`Value`] (`#tag(value)`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variant {
+    pub tag: Symbol,
+    pub payload: Option<Box<Value>>,
+}
+
+/// A phrase with values embedded in it (`"{n} apples at {price:.2}"`).
+///
+/// An interpolation is shorthand for builtin invocations: an embedded value without a
+/// [`FormatSpec`] becomes `describe(value)`, one with a spec becomes `format(value, "spec")`, and
+/// the pieces are joined with `concat`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interpolation {
+    pub pieces: Vec<Piece>,
+    /// The quotes and everything between them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+impl Interpolation {
+    /// The embedded values, from left to right.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Text(_) => None,
+            Piece::Embed(embed) => Some(&*embed.value),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Piece {
+    Text(String),
+    Embed(Embed),
+}
+
+/// A [`Value`] embedded in an [`Interpolation`] (`{value}` or `{value:spec}`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embed {
+    pub value: Box<Value>,
+    pub spec: Option<FormatSpec>,
+}
+
+/// How a count or amount is written out (`+08.2`): with its sign even if it is positive, padded
+/// with zeros rather than spaces, at least `width` characters wide, and for amounts, with
+/// `precision` digits after the point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatSpec {
+    pub sign: bool,
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+}
+
+/// Access to a [`Field`] of a [`Record`]. Consists of a [`Value`] that should resolve to a record,
+/// and the [`Symbol`] of the field.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Projection {
+    pub record: Box<Value>,
+    pub field: Symbol,
+}
+
+/// A constant definition. A binding of a [`Charm`] or [`Spell`] may refer to itself.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Binding {
     pub manifest: Manifest,
     pub value: Value,
@@ -91,16 +546,24 @@ pub struct Binding {
 
 /// A scope with associated [`Binding`]s (definitions) and a return [`Value`].
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Boundary {
     pub bindings: Vec<Binding>,
     pub value: Box<Value>,
     pub ty: Type,
+    /// The braces delimiting the boundary.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
 }
 
-/// A pure function. Consists of a list of [`Manifest`]s (declarations) for its components
-/// (parameters), and its containing [`Boundary`] (scope).
+/// A pure function. Consists of the [`Symbol`]s of its type parameters, a list of [`Manifest`]s
+/// (declarations) for its components (parameters), and its containing [`Boundary`] (scope).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Charm {
+    /// The type variables the charm is generic over (`charm[T](x: T) -> T`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parameters: Vec<Symbol>,
     pub components: Vec<Manifest>,
     pub boundary: Boundary,
 }
@@ -108,6 +571,7 @@ pub struct Charm {
 /// A call to a pure function. Consists of a [`Value`] that should resolve to a [`Charm`]
 /// (pure function), and a list of [`Value`]s that return the charm components (parameters).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Invocation {
     pub charm: Box<Value>,
     pub components: Vec<Value>,
@@ -117,31 +581,171 @@ pub struct Invocation {
 /// (imperative function), and a list of [`Action`]s (imperative statements) that return the spell
 /// components (parameters).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cast {
     pub spell: Box<Action>,
     pub components: Vec<Action>,
+    /// What to do instead if the spell, or a cast among its components, fails
+    /// (`recover s!(x) as failure { ... }`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recovery: Option<Recovery>,
+}
+
+impl Cast {
+    /// The span of the source the cast was parsed from, as far as its parts tell; see
+    /// [`Value::span`].
+    pub fn span(&self) -> Option<Span> {
+        cover(
+            std::iter::once(self.spell.span())
+                .chain(self.components.iter().map(Action::span))
+                .chain(self.recovery.iter().map(|r| known(r.span))),
+        )
+    }
+}
+
+/// The [`Action`]s performed in place of a failed [`Cast`]. The optional [`Manifest`] is bound to
+/// the phrase the spell failed with. The product of the last action takes the place of the
+/// product of the cast.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recovery {
+    pub failure: Option<Manifest>,
+    pub actions: ActionSequence,
+    /// The `recover` keyword through the closing brace.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+/// A choice between [`Value`]s. The [`Arm`]s are tried in order, and the divination takes the value
+/// of the first one whose [`Omen`] holds.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Divination {
+    /// The value the arms' patterns are matched against, if any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub subject: Option<Box<Value>>,
+    pub arms: Vec<Arm>,
+    /// The `divine` keyword through the closing brace.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arm {
+    pub omen: Omen,
+    pub value: Value,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+/// The condition guarding an [`Arm`] of a [`Divination`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Omen {
+    /// Holds if the [`Value`] is `true`.
+    Truth(Value),
+    /// Holds if the [`Value`], which must be optional, is not nil.
+    Presence(Presence),
+    /// Holds if the subject of the divination matches the [`Manifest`]'s pattern, whose symbols
+    /// are then visible in the value of the arm.
+    Pattern(Manifest),
+    /// Always holds.
+    Otherwise,
+}
+
+/// An [`Omen`] that binds the content of an optional [`Value`] to a [`Manifest`] (declaration),
+/// which is visible in the value of its [`Arm`] only. It holds if the value is not nil and its
+/// content matches the manifest's pattern.
+///
+/// A value that is not optional is matched against the manifest directly, which must then be
+/// refutable for the omen to mean anything.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Presence {
+    pub manifest: Manifest,
+    pub value: Value,
 }
 
 /// A sequence of [`Action`]s (imperative statements).
 pub type ActionSequence = Vec<Action>;
 
-/// An imperative function. Consists of a list of [`Manifest`]s (declarations) for its components
-/// (parameters), and an [`ActionSequence`] (list of statements).
+/// An imperative function. Consists of the [`Symbol`]s of its type parameters, a list of
+/// [`Manifest`]s (declarations) for its components (parameters), and an [`ActionSequence`] (list
+/// of statements).
+///
+/// Only a spell declared with `fails` may cast spells that may fail without recovering them, and
+/// cast the builtin `fail`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Spell {
+    /// The type variables the spell is generic over.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parameters: Vec<Symbol>,
     pub components: Vec<Manifest>,
     pub actions: ActionSequence,
     pub ty: Type,
+    /// Whether failures may escape the spell.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fails: bool,
+    /// The capabilities the spell declares it may use. If it declares none, they are inferred from
+    /// the spells it casts.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub capabilities: Option<Capabilities>,
+    /// The braces delimiting the actions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+/// A binding of the product of a [`Cast`] to a [`Manifest`] (declaration). Unlike a [`Binding`],
+/// which binds a [`Value`], a channel may only occur among the [`Action`]s of a [`Spell`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Channel {
+    pub manifest: Manifest,
+    pub cast: Cast,
+}
+
+/// A repetition of an [`ActionSequence`] (list of statements) a natural number of times. The
+/// optional counter [`Manifest`] (declaration) is bound to the number of the current repetition,
+/// starting at zero.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repetition {
+    pub count: Value,
+    pub counter: Option<Manifest>,
+    pub actions: ActionSequence,
+    /// The `repeat` keyword through the closing brace.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action {
     Value(Value),
     Cast(Cast),
     Binding(Binding),
+    Channel(Channel),
+    Repetition(Repetition),
+}
+
+impl Action {
+    /// The span of the source the action was parsed from, as far as its parts tell; see
+    /// [`Value::span`].
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Value(value) => value.span(),
+            Self::Cast(cast) => cast.span(),
+            Self::Binding(binding) => cover([known(binding.manifest.span), binding.value.span()]),
+            Self::Channel(channel) => cover([known(channel.manifest.span), channel.cast.span()]),
+            Self::Repetition(repetition) => known(repetition.span),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Conjuration {
     pub bindings: Vec<Binding>,
 }
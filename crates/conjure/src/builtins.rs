@@ -0,0 +1,490 @@
+//! Charms and spells that every program can refer to without binding them.
+//!
+//! Arithmetic follows the semantics in [`numeric`]: it fails with an error rather than produce a
+//! wrong result. The conversions `natural`, `whole` and `amount` turn any number into a number of
+//! their kind, failing unless it stays the same; `round` rounds an amount to the nearest whole
+//! amount first, so that it can be converted to a count.
+//!
+//! `format` writes out a count or amount as a [`FormatSpec`] asks, which is how the embeds of an
+//! interpolated phrase with a spec are written out. Wholes keep their sign either way.
+//!
+//! The spell `fail` fails with the phrase it is cast with. Its product can stand for any type, as
+//! there never is one; only spells declared with `fails` may cast it without recovering it.
+//!
+//! Spells reach the outside world through the builtins `utter` and `read` (which need the console
+//! [`Capability`]), `load` and `store` (the filesystem), `now` (the clock) and `random`
+//! (randomness). The interpreter only casts them if the host grants the capability. `load` and
+//! `store` fail if the file cannot be loaded or stored.
+//!
+//! Some builtins are overloaded: they have several signatures, and an invocation or cast picks the
+//! first one whose components match. The builtins working on sequences are generic over the type of
+//! their elements.
+
+use std::rc::Rc;
+
+use crate::{
+    ast::{Capability, CharmType, FormatSpec, SimpleType, SpellType, Symbol, Type},
+    interpret::{Host, Value},
+    numeric::{self, Natural, NumericError, Operation},
+    syntax,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Utter,
+    Read,
+    Fail,
+    Load,
+    Store,
+    Now,
+    Random,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Natural,
+    Whole,
+    Amount,
+    Round,
+    Equals,
+    Less,
+    Not,
+    And,
+    Or,
+    Concat,
+    Describe,
+    Format,
+    Length,
+    Index,
+    Map,
+    Filter,
+    Fold,
+}
+
+const TRUTH: Type = Type::Simple(SimpleType::Truth);
+const NATURAL: Type = Type::Simple(SimpleType::NaturalCount);
+const WHOLE: Type = Type::Simple(SimpleType::WholeCount);
+const AMOUNT: Type = Type::Simple(SimpleType::Amount);
+const PHRASE: Type = Type::Simple(SimpleType::Phrase);
+
+fn charm<const N: usize>(components: [Type; N], product: Type) -> Type {
+    Type::Charm(CharmType {
+        components: components.into(),
+        product: Box::new(product),
+    })
+}
+
+fn sequence(element: Type) -> Type {
+    Type::Sequence(Box::new(element))
+}
+
+fn variable(name: &str) -> Type {
+    Type::Variable(Symbol::new(name.to_string(), Default::default()))
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 29] = [
+        Self::Utter,
+        Self::Read,
+        Self::Fail,
+        Self::Load,
+        Self::Store,
+        Self::Now,
+        Self::Random,
+        Self::Add,
+        Self::Subtract,
+        Self::Multiply,
+        Self::Divide,
+        Self::Remainder,
+        Self::Natural,
+        Self::Whole,
+        Self::Amount,
+        Self::Round,
+        Self::Equals,
+        Self::Less,
+        Self::Not,
+        Self::And,
+        Self::Or,
+        Self::Concat,
+        Self::Describe,
+        Self::Format,
+        Self::Length,
+        Self::Index,
+        Self::Map,
+        Self::Filter,
+        Self::Fold,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utter => "utter",
+            Self::Read => "read",
+            Self::Fail => "fail",
+            Self::Load => "load",
+            Self::Store => "store",
+            Self::Now => "now",
+            Self::Random => "random",
+            Self::Add => "add",
+            Self::Subtract => "subtract",
+            Self::Multiply => "multiply",
+            Self::Divide => "divide",
+            Self::Remainder => "remainder",
+            Self::Natural => "natural",
+            Self::Whole => "whole",
+            Self::Amount => "amount",
+            Self::Round => "round",
+            Self::Equals => "equals",
+            Self::Less => "less",
+            Self::Not => "not",
+            Self::And => "and",
+            Self::Or => "or",
+            Self::Concat => "concat",
+            Self::Describe => "describe",
+            Self::Format => "format",
+            Self::Length => "length",
+            Self::Index => "index",
+            Self::Map => "map",
+            Self::Filter => "filter",
+            Self::Fold => "fold",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    pub fn is_spell(&self) -> bool {
+        matches!(
+            self,
+            Self::Utter
+                | Self::Read
+                | Self::Fail
+                | Self::Load
+                | Self::Store
+                | Self::Now
+                | Self::Random
+        )
+    }
+
+    /// Whether casting this builtin may fail.
+    pub fn fails(&self) -> bool {
+        matches!(self, Self::Fail | Self::Load | Self::Store)
+    }
+
+    /// The capability this builtin needs, if any.
+    pub fn capability(&self) -> Option<Capability> {
+        match self {
+            Self::Utter | Self::Read => Some(Capability::Console),
+            Self::Load | Self::Store => Some(Capability::Filesystem),
+            Self::Now => Some(Capability::Clock),
+            Self::Random => Some(Capability::Randomness),
+            _ => None,
+        }
+    }
+
+    fn spell<const N: usize>(&self, components: [Type; N], product: Type) -> Type {
+        Type::Spell(SpellType {
+            components: components.into(),
+            product: Box::new(product),
+            fails: self.fails(),
+            capabilities: self.capability().into_iter().collect(),
+        })
+    }
+
+    /// Whether this builtin invokes charms among its components. Only the interpreter can apply
+    /// such builtins.
+    pub fn takes_charms(&self) -> bool {
+        matches!(self, Self::Map | Self::Filter | Self::Fold)
+    }
+
+    /// The types of this builtin, one for every overload, in the order overloads are tried.
+    pub fn signatures(&self) -> Vec<Type> {
+        let numbers = [NATURAL, WHOLE, AMOUNT];
+        let elements = [TRUTH, NATURAL, WHOLE, AMOUNT, PHRASE];
+        let t = variable("T");
+        match self {
+            Self::Utter => vec![self.spell([PHRASE], Type::Nil)],
+            Self::Read => vec![self.spell([], Type::Optional(Box::new(PHRASE)))],
+            Self::Fail => vec![self.spell([PHRASE], t.clone())],
+            Self::Load => vec![self.spell([PHRASE], PHRASE)],
+            Self::Store => vec![self.spell([PHRASE, PHRASE], Type::Nil)],
+            Self::Now | Self::Random => vec![self.spell([], AMOUNT)],
+            Self::Add | Self::Subtract | Self::Multiply | Self::Divide => numbers
+                .into_iter()
+                .map(|t| charm([t.clone(), t.clone()], t))
+                .collect(),
+            Self::Remainder => [NATURAL, WHOLE]
+                .into_iter()
+                .map(|t| charm([t.clone(), t.clone()], t))
+                .collect(),
+            Self::Natural => numbers.into_iter().map(|t| charm([t], NATURAL)).collect(),
+            Self::Whole => numbers.into_iter().map(|t| charm([t], WHOLE)).collect(),
+            Self::Amount => numbers.into_iter().map(|t| charm([t], AMOUNT)).collect(),
+            Self::Round => vec![charm([AMOUNT], AMOUNT)],
+            Self::Equals => elements
+                .into_iter()
+                .map(|t| charm([t.clone(), t], TRUTH))
+                .collect(),
+            Self::Less => numbers
+                .into_iter()
+                .chain([PHRASE])
+                .map(|t| charm([t.clone(), t], TRUTH))
+                .collect(),
+            Self::Not => vec![charm([TRUTH], TRUTH)],
+            Self::And | Self::Or => vec![charm([TRUTH, TRUTH], TRUTH)],
+            Self::Concat => vec![
+                charm([PHRASE, PHRASE], PHRASE),
+                charm([sequence(t.clone()), sequence(t.clone())], sequence(t)),
+            ],
+            Self::Describe => elements.into_iter().map(|t| charm([t], PHRASE)).collect(),
+            Self::Format => numbers
+                .into_iter()
+                .map(|t| charm([t, PHRASE], PHRASE))
+                .collect(),
+            Self::Length => vec![charm([sequence(t)], NATURAL)],
+            Self::Index => vec![charm(
+                [sequence(t.clone()), NATURAL],
+                Type::Optional(Box::new(t)),
+            )],
+            Self::Map => {
+                let u = variable("U");
+                vec![charm(
+                    [sequence(t.clone()), charm([t], u.clone())],
+                    sequence(u),
+                )]
+            }
+            Self::Filter => vec![charm(
+                [sequence(t.clone()), charm([t.clone()], TRUTH)],
+                sequence(t),
+            )],
+            Self::Fold => {
+                let a = variable("A");
+                vec![charm(
+                    [
+                        sequence(t.clone()),
+                        a.clone(),
+                        charm([a.clone(), t], a.clone()),
+                    ],
+                    a,
+                )]
+            }
+        }
+    }
+
+    /// Applies this builtin. Components are assumed to have been type checked; on a mismatch, an
+    /// error is returned rather than a panic.
+    ///
+    /// `fail` cannot be applied: casting it fails, which only the interpreter can do.
+    pub fn apply(&self, components: &[Value], host: &mut dyn Host) -> Result<Value, String> {
+        use Value::*;
+
+        let failed = |error: NumericError| format!("`{}` failed: {error}", self.name());
+        let result = match (self, components) {
+            (Self::Utter, [Phrase(text)]) => {
+                host.utter(text).map_err(|e| format!("cannot utter: {e}"))?;
+                Nil
+            }
+            (Self::Read, []) => match host.read().map_err(|e| format!("cannot read: {e}"))? {
+                Some(line) => Phrase(line.into()),
+                None => Nil,
+            },
+            (Self::Load, [Phrase(path)]) => Phrase(
+                host.load(path)
+                    .map_err(|e| format!("cannot load {path}: {e}"))?
+                    .into(),
+            ),
+            (Self::Store, [Phrase(path), Phrase(text)]) => {
+                host.store(path, text)
+                    .map_err(|e| format!("cannot store {path}: {e}"))?;
+                Nil
+            }
+            (Self::Now, []) => Amount(
+                host.now()
+                    .map_err(|e| format!("cannot tell the time: {e}"))?,
+            ),
+            (Self::Random, []) => Amount(
+                host.random()
+                    .map_err(|e| format!("cannot draw a random amount: {e}"))?,
+            ),
+
+            (operation, [NaturalCount(a), NaturalCount(b)]) if operation.is_arithmetic() => {
+                NaturalCount(numeric::naturals(self.operation(), a, b).map_err(failed)?)
+            }
+            (operation, [WholeCount(a), WholeCount(b)]) if operation.is_arithmetic() => {
+                WholeCount(numeric::wholes(self.operation(), a, b).map_err(failed)?)
+            }
+            (operation, [Amount(a), Amount(b)]) if operation.is_arithmetic() => {
+                Amount(numeric::amounts(self.operation(), *a, *b).map_err(failed)?)
+            }
+
+            (Self::Natural, [value @ (NaturalCount(_) | WholeCount(_) | Amount(_))]) => {
+                NaturalCount(
+                    match value {
+                        NaturalCount(n) => Ok(n.to_owned()),
+                        WholeCount(n) => numeric::natural_from_whole(n),
+                        Amount(x) => numeric::natural_from_amount(*x),
+                        _ => unreachable!(),
+                    }
+                    .map_err(|e| format!("cannot convert {value} to a natural: {e}"))?,
+                )
+            }
+            (Self::Whole, [value @ (NaturalCount(_) | WholeCount(_) | Amount(_))]) => WholeCount(
+                match value {
+                    NaturalCount(n) => numeric::whole_from_natural(n),
+                    WholeCount(n) => Ok(n.to_owned()),
+                    Amount(x) => numeric::whole_from_amount(*x),
+                    _ => unreachable!(),
+                }
+                .map_err(|e| format!("cannot convert {value} to a whole: {e}"))?,
+            ),
+            (Self::Amount, [value @ (NaturalCount(_) | WholeCount(_) | Amount(_))]) => Amount(
+                match value {
+                    NaturalCount(n) => numeric::amount_from_natural(n),
+                    WholeCount(n) => numeric::amount_from_whole(n),
+                    Amount(x) => Ok(*x),
+                    _ => unreachable!(),
+                }
+                .map_err(|e| format!("cannot convert {value} to an amount: {e}"))?,
+            ),
+            (Self::Round, [Amount(x)]) => Amount(x.round()),
+
+            (Self::Equals, [a, b]) => Truth(a == b),
+            (Self::Less, [NaturalCount(a), NaturalCount(b)]) => Truth(a < b),
+            (Self::Less, [WholeCount(a), WholeCount(b)]) => Truth(a < b),
+            (Self::Less, [Amount(a), Amount(b)]) => Truth(a < b),
+            (Self::Less, [Phrase(a), Phrase(b)]) => Truth(a < b),
+            (Self::Not, [Truth(a)]) => Truth(!a),
+            (Self::And, [Truth(a), Truth(b)]) => Truth(*a && *b),
+            (Self::Or, [Truth(a), Truth(b)]) => Truth(*a || *b),
+            (Self::Concat, [Phrase(a), Phrase(b)]) => Phrase(Rc::from(format!("{a}{b}"))),
+            (Self::Concat, [Sequence(a), Sequence(b)]) => {
+                Sequence(a.iter().chain(b.iter()).cloned().collect())
+            }
+            (Self::Describe, [Phrase(text)]) => Phrase(text.clone()),
+            (Self::Describe, [value]) => Phrase(value.to_string().into()),
+            (Self::Format, [value, Phrase(spec)]) => {
+                let spec = syntax::format_spec(spec)?;
+                Phrase(format_number(value, spec)?.into())
+            }
+            (Self::Length, [Sequence(elements)]) => {
+                NaturalCount(Natural::from(elements.len() as u64))
+            }
+            (Self::Index, [Sequence(elements), NaturalCount(i)]) => numeric::to_usize(i)
+                .and_then(|i| elements.get(i))
+                .cloned()
+                .unwrap_or(Nil),
+
+            _ => {
+                return Err(format!(
+                    "`{}` cannot be applied to ({})",
+                    self.name(),
+                    components
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            }
+        };
+        Ok(result)
+    }
+
+    /// An estimate of the bytes the product of applying this builtin takes up on the heap, made
+    /// before building it, so that the interpreter can refuse to build a product that would exceed
+    /// its allocation limit.
+    pub fn projected_footprint(&self, components: &[Value]) -> usize {
+        use std::mem::size_of;
+        use Value::*;
+
+        match (self, components) {
+            (operation, [NaturalCount(a), NaturalCount(b)]) if operation.is_arithmetic() => {
+                let (a, b) = (numeric::natural_size(a), numeric::natural_size(b));
+                numeric::result_size(self.operation(), a, b)
+            }
+            (operation, [WholeCount(a), WholeCount(b)]) if operation.is_arithmetic() => {
+                let (a, b) = (numeric::whole_size(a), numeric::whole_size(b));
+                numeric::result_size(self.operation(), a, b)
+            }
+            (Self::Concat, [Phrase(a), Phrase(b)]) => a.len().saturating_add(b.len()),
+            (Self::Concat, [Sequence(a), Sequence(b)]) => a
+                .len()
+                .saturating_add(b.len())
+                .saturating_mul(size_of::<Value>()),
+            (Self::Format, [_, Phrase(spec)]) => syntax::format_spec(spec).map_or(0, |spec| {
+                spec.width.unwrap_or(0).max(spec.precision.unwrap_or(0))
+            }),
+            (Self::Map | Self::Filter, [Sequence(elements), _]) => {
+                elements.len().saturating_mul(size_of::<Value>())
+            }
+            _ => 0,
+        }
+    }
+
+    fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Self::Add | Self::Subtract | Self::Multiply | Self::Divide | Self::Remainder
+        )
+    }
+
+    /// The operation of an arithmetic builtin.
+    fn operation(&self) -> Operation {
+        match self {
+            Self::Add => Operation::Add,
+            Self::Subtract => Operation::Subtract,
+            Self::Multiply => Operation::Multiply,
+            Self::Divide => Operation::Divide,
+            Self::Remainder => Operation::Remainder,
+            _ => unreachable!("`{}` is not arithmetic", self.name()),
+        }
+    }
+}
+
+/// Writes out a count or amount as `spec` asks.
+fn format_number(value: &Value, spec: FormatSpec) -> Result<String, String> {
+    let text = match (value, spec.precision) {
+        (Value::NaturalCount(n), None) => n.to_string(),
+        (Value::WholeCount(n), None) => n.to_string(),
+        (Value::Amount(x), Some(precision)) => format!("{x:.precision$}"),
+        (Value::Amount(x), None) => format!("{x:?}"),
+        (Value::NaturalCount(_) | Value::WholeCount(_), Some(_)) => {
+            return Err(format!("{value} cannot be written with a precision"))
+        }
+        _ => return Err(format!("{value} cannot be formatted")),
+    };
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None if spec.sign || matches!(value, Value::WholeCount(_)) => ("+", text.as_str()),
+        None => ("", text.as_str()),
+    };
+    let padding = spec
+        .width
+        .unwrap_or(0)
+        .saturating_sub(sign.len() + digits.chars().count());
+    Ok(if spec.zero {
+        format!("{sign}{}{digits}", "0".repeat(padding))
+    } else {
+        format!("{}{sign}{digits}", " ".repeat(padding))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpret::StdHost;
+
+    fn format(value: Value, spec: &str) -> Result<Value, String> {
+        Builtin::Format.apply(&[value, Value::Phrase(spec.into())], &mut StdHost)
+    }
+
+    #[test]
+    fn format_rejects_sizes_out_of_range() {
+        let error = format(Value::Amount(1.5), ".70000").unwrap_err();
+        assert!(error.contains("precision"), "{error}");
+        let error = format(Value::NaturalCount(Natural::from(1u8)), "99999999999999").unwrap_err();
+        assert!(error.contains("width"), "{error}");
+        let largest = format!(".{}", syntax::MAX_FORMAT_SIZE);
+        assert!(format(Value::Amount(1.5), &largest).is_ok());
+    }
+}
@@ -0,0 +1,76 @@
+//! Semantic analysis of a parsed conjuration.
+
+use crate::{
+    ast::{self, SpellType, Type},
+    diagnostic::Diagnostic,
+    resolve::{Resolution, Resolver},
+    typeck::{Checker, Types},
+};
+
+/// The results of all semantic analyses of a conjuration.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub resolution: Resolution,
+    pub types: Types,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// Runs name resolution and type checking, and checks that the conjuration has a `*` spell that
+/// can be cast without components.
+pub fn check(conjuration: &ast::Conjuration) -> Analysis {
+    check_with(conjuration, &[])
+}
+
+/// Like [`check`], for a conjuration that may also refer to the given charms and spells provided
+/// by its host, which have the given types. The conjuration may shadow them.
+pub fn check_with(conjuration: &ast::Conjuration, natives: &[(&str, &Type)]) -> Analysis {
+    let mut resolver = Resolver::new();
+    let mut checker = Checker::default();
+    for (name, ty) in natives {
+        resolver.declare_native(name);
+        checker.declare_native(name, ty);
+    }
+    resolver.push_scope();
+    for binding in &conjuration.bindings {
+        resolver.binding(binding);
+        checker.binding(binding);
+    }
+    let mut diagnostics = resolver.take_diagnostics();
+    // Unbound symbols are reported by the resolver already; the type checker treats them as
+    // having an unknown type, so it does not report them again.
+    diagnostics.extend(checker.take_diagnostics());
+    let resolution = resolver.into_resolution();
+    let types = checker.into_types();
+
+    let main = conjuration.bindings.iter().rev().find_map(|b| {
+        let symbols = b.manifest.symbols();
+        symbols.into_iter().find(|s| s.as_str() == "*")
+    });
+    match main {
+        Some(symbol) => match types.symbols.get(&symbol.span).map(|t| types.unfold(t)) {
+            Some(Type::Spell(SpellType { components, .. })) if components.is_empty() => (),
+            Some(Type::Inferred) | None => (),
+            Some(ty) => diagnostics.push(Diagnostic::error(
+                format!("`*` must be a spell without components, but has type {ty}"),
+                symbol.span,
+            )),
+        },
+        None => diagnostics.push(Diagnostic::warning(
+            "the conjuration has no `*` spell, so it cannot be run",
+            ast::Span::default(),
+        )),
+    }
+
+    diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+    Analysis {
+        resolution,
+        types,
+        diagnostics,
+    }
+}
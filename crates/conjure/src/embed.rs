@@ -0,0 +1,615 @@
+//! Embedding the interpreter in a Rust application.
+//!
+//! An [`Embedding`] runs a conjuration alongside charms and spells that the application provides
+//! as Rust closures, each with a declared type. The conjuration refers to them like to builtins,
+//! and the type checker holds both sides to the declared types. Values convert from and to `bool`,
+//! `u64`, `i64`, `f64`, `String` and tuples of those, so that the application can also invoke the
+//! charms and cast the spells of the conjuration:
+//!
+//! ```
+//! use conjure::{
+//!     ast::{CharmType, SimpleType, Type},
+//!     embed::Embedding,
+//!     interpret::StdHost,
+//! };
+//!
+//! let natural = Type::Simple(SimpleType::NaturalCount);
+//! let mut host = StdHost;
+//! let mut embedding = Embedding::new(&mut host);
+//! embedding.charm(
+//!     "square",
+//!     CharmType {
+//!         components: vec![natural.clone()],
+//!         product: Box::new(natural),
+//!     },
+//!     |components| {
+//!         let n = u64::try_from(&components[0])?;
+//!         Ok(n.checked_mul(n).ok_or("too large")?.into())
+//!     },
+//! );
+//! embedding
+//!     .load("area = charm(side: natural) -> natural { square(side) };")
+//!     .unwrap();
+//! let area = embedding.invoke("area", vec![7u64.into()]).unwrap();
+//! assert_eq!(u64::try_from(&area), Ok(49));
+//! ```
+
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    ast::{CharmType, ConjoinedType, SimpleType, SpellType, Type, VariantType},
+    check,
+    diagnostic::Diagnostic,
+    interpret::{Host, Interpreter, RuntimeError, Value},
+    numeric::{self, Natural, Whole},
+    syntax,
+    typeck::{self, Types},
+};
+
+type Function = dyn Fn(&[Value]) -> Result<Value, String>;
+
+/// A charm or spell implemented by the host.
+pub struct Native {
+    pub name: Rc<str>,
+    /// The declared type, which is a charm or spell type.
+    pub ty: Type,
+    function: Box<Function>,
+}
+
+impl Native {
+    /// The declared type, if the native is a spell.
+    pub fn spell(&self) -> Option<&SpellType> {
+        match &self.ty {
+            Type::Spell(spell) => Some(spell),
+            _ => None,
+        }
+    }
+
+    /// Calls the function with components that match the declared type.
+    pub fn call(&self, components: &[Value]) -> Result<Value, String> {
+        (self.function)(components)
+    }
+
+    /// Checks that the function produced a value of the declared product type when called with
+    /// `components`, so that a mistake in the host does not undermine the type checker. Declared
+    /// types cannot refer to the named types of a conjuration, which are checked after them, so
+    /// they are compared without any.
+    pub fn check_product(&self, components: &[Value], product: &Value) -> Result<(), String> {
+        let (declared, declared_product) = match &self.ty {
+            Type::Charm(charm) => (&charm.components, &*charm.product),
+            Type::Spell(spell) => (&spell.components, &*spell.product),
+            _ => return Ok(()),
+        };
+        // Checked together with the components, so that type variables stand for the same type
+        // in both.
+        let expected = declared.iter().chain([declared_product]).cloned().collect();
+        let actual = components.iter().chain([product]).map(type_of).collect();
+        if typeck::is_instance(&Type::Conjoined(expected), &Type::Conjoined(actual)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` produced {product}, which is not of its declared type {declared_product}",
+                self.name
+            ))
+        }
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("ty", &self.ty)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EmbedError {
+    /// The conjuration did not parse or did not pass semantic analysis.
+    Rejected(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+    /// The conjuration binds no charm or spell of the name the host asked for.
+    Unbound(String),
+    /// The components the host passed do not fit the charm or spell.
+    Mismatch(String),
+}
+
+impl fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Self::Runtime(err) => write!(f, "error: {err}"),
+            Self::Unbound(message) | Self::Mismatch(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for EmbedError {}
+
+/// A conjuration together with the charms and spells its host provides.
+pub struct Embedding<'h> {
+    natives: Vec<Rc<Native>>,
+    interpreter: Interpreter<'h>,
+    /// The types of the globals bound by the loaded conjuration, with the types of the conjuration
+    /// they came from, which define the named types they refer to.
+    globals: HashMap<String, (Type, Rc<Types>)>,
+}
+
+impl<'h> Embedding<'h> {
+    pub fn new(host: &'h mut dyn Host) -> Self {
+        Self {
+            natives: Vec::new(),
+            interpreter: Interpreter::new(host),
+            globals: HashMap::new(),
+        }
+    }
+
+    /// Provides a charm to the conjurations loaded from now on. The function is called with
+    /// components of the declared types, and must produce a value of the declared product type;
+    /// producing anything else is a runtime error.
+    pub fn charm(
+        &mut self,
+        name: &str,
+        ty: CharmType,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) -> &mut Self {
+        self.provide(name, Type::Charm(ty), Box::new(function))
+    }
+
+    /// Provides a spell to the conjurations loaded from now on, like [`Embedding::charm`]. If the
+    /// spell is declared to fail, its errors are failures, which the conjuration may recover from.
+    /// Casting it needs the capabilities it declares.
+    pub fn spell(
+        &mut self,
+        name: &str,
+        ty: SpellType,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) -> &mut Self {
+        self.provide(name, Type::Spell(ty), Box::new(function))
+    }
+
+    fn provide(&mut self, name: &str, ty: Type, function: Box<Function>) -> &mut Self {
+        let native = Rc::new(Native {
+            name: name.into(),
+            ty,
+            function,
+        });
+        self.interpreter
+            .define(name, Value::Native(Rc::clone(&native)));
+        self.natives.push(native);
+        self
+    }
+
+    /// The interpreter, to grant capabilities and set limits.
+    pub fn interpreter(&mut self) -> &mut Interpreter<'h> {
+        &mut self.interpreter
+    }
+
+    /// Parses and checks a conjuration, and evaluates its bindings. Warnings are ignored.
+    pub fn load(&mut self, source: &str) -> Result<(), EmbedError> {
+        let conjuration = syntax::parse(source).map_err(|d| EmbedError::Rejected(vec![d]))?;
+        let natives: Vec<_> = self
+            .natives
+            .iter()
+            .map(|n| (n.name.as_ref(), &n.ty))
+            .collect();
+        let analysis = check::check_with(&conjuration, &natives);
+        if analysis.has_errors() {
+            let errors = analysis
+                .diagnostics
+                .into_iter()
+                .filter(Diagnostic::is_error);
+            return Err(EmbedError::Rejected(errors.collect()));
+        }
+        self.interpreter
+            .load(&conjuration)
+            .map_err(EmbedError::Runtime)?;
+        let types = Rc::new(analysis.types);
+        for binding in &conjuration.bindings {
+            for symbol in binding.manifest.symbols() {
+                if let Some(ty) = types.symbols.get(&symbol.span) {
+                    let global = (ty.clone(), Rc::clone(&types));
+                    self.globals.insert(symbol.name.clone(), global);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The type of a global bound by the loaded conjuration.
+    pub fn type_of(&self, name: &str) -> Option<&Type> {
+        self.globals.get(name).map(|(ty, _)| ty)
+    }
+
+    /// Casts the `*` spell of the loaded conjuration.
+    pub fn run(&mut self) -> Result<Value, EmbedError> {
+        self.cast("*", Vec::new())
+    }
+
+    /// Invokes a charm bound by the loaded conjuration.
+    pub fn invoke(&mut self, name: &str, components: Vec<Value>) -> Result<Value, EmbedError> {
+        let charm = self.global(name, "charm", &components, |ty| match ty {
+            Type::Charm(charm) => Some(&charm.components),
+            _ => None,
+        })?;
+        self.interpreter
+            .invoke(&charm, components)
+            .map_err(EmbedError::Runtime)
+    }
+
+    /// Casts a spell bound by the loaded conjuration.
+    pub fn cast(&mut self, name: &str, components: Vec<Value>) -> Result<Value, EmbedError> {
+        let spell = self.global(name, "spell", &components, |ty| match ty {
+            Type::Spell(spell) => Some(&spell.components),
+            _ => None,
+        })?;
+        self.interpreter
+            .cast(&spell, components)
+            .map_err(EmbedError::Runtime)
+    }
+
+    /// The value of a global charm or spell, after checking that the components fit the component
+    /// types that `components_of` finds in its type.
+    fn global(
+        &self,
+        name: &str,
+        kind: &str,
+        components: &[Value],
+        components_of: impl Fn(&Type) -> Option<&ConjoinedType>,
+    ) -> Result<Value, EmbedError> {
+        let unbound = || EmbedError::Unbound(format!("`{name}` is not a bound {kind}"));
+        let (ty, types) = self.globals.get(name).ok_or_else(unbound)?;
+        let expected = components_of(types.unfold(ty)).ok_or_else(unbound)?;
+        let expected = Type::Conjoined(expected.clone());
+        let actual = Type::Conjoined(components.iter().map(type_of).collect());
+        if !types.is_instance(&expected, &actual) {
+            return Err(EmbedError::Mismatch(format!(
+                "`{name}` takes the components {expected}, not {actual}"
+            )));
+        }
+        let value = self.interpreter.globals().lookup(name).cloned();
+        value.ok_or_else(unbound)
+    }
+}
+
+/// The type of a value, as far as the value tells. The types of charms and spells, and of the
+/// elements of empty sequences, are left to be inferred.
+fn type_of(value: &Value) -> Type {
+    match value {
+        Value::Nil => Type::Nil,
+        Value::Truth(_) => Type::Simple(SimpleType::Truth),
+        Value::NaturalCount(_) => Type::Simple(SimpleType::NaturalCount),
+        Value::WholeCount(_) => Type::Simple(SimpleType::WholeCount),
+        Value::Amount(_) => Type::Simple(SimpleType::Amount),
+        Value::Phrase(_) => Type::Simple(SimpleType::Phrase),
+        Value::Conjunction(values) => Type::Conjoined(values.iter().map(type_of).collect()),
+        Value::Record(fields) => Type::Record(
+            fields
+                .iter()
+                .map(|(name, value)| crate::ast::FieldType {
+                    symbol: crate::ast::Symbol::new(name.to_string(), Default::default()),
+                    ty: type_of(value),
+                })
+                .collect(),
+        ),
+        Value::Sequence(elements) => {
+            Type::Sequence(Box::new(elements.first().map_or(Type::Inferred, type_of)))
+        }
+        Value::Variant(tag, payload) => Type::Union(vec![VariantType {
+            tag: crate::ast::Symbol::new(tag.to_string(), Default::default()),
+            payload: payload.as_deref().map(type_of),
+        }]),
+        Value::Type(_) => Type::Type,
+        Value::Charm(_) | Value::Spell(_) | Value::Builtin(_) => Type::Inferred,
+        Value::Native(native) => native.ty.clone(),
+    }
+}
+
+/// A value that does not have the type a Rust type was expected to be converted from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: Value,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Lets native charms and spells use `?` on conversions.
+impl From<ConversionError> for String {
+    fn from(error: ConversionError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Self::Nil
+    }
+}
+
+impl From<bool> for Value {
+    fn from(truth: bool) -> Self {
+        Self::Truth(truth)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Self::NaturalCount(Natural::from(n))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Self::WholeCount(Whole::from(n))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Self::Amount(x)
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Self::Phrase(text.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Self::Phrase(text.into())
+    }
+}
+
+fn mismatch(expected: &'static str, found: &Value) -> ConversionError {
+    ConversionError {
+        expected,
+        found: found.clone(),
+    }
+}
+
+impl TryFrom<&Value> for () {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Nil => Ok(()),
+            other => Err(mismatch("nil", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Truth(truth) => Ok(*truth),
+            other => Err(mismatch("a truth", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for u64 {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::NaturalCount(n) => numeric::to_u64(n).ok_or_else(|| mismatch("a u64", value)),
+            other => Err(mismatch("a natural", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for i64 {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::WholeCount(n) => numeric::to_i64(n).ok_or_else(|| mismatch("an i64", value)),
+            other => Err(mismatch("a whole", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Amount(x) => Ok(*x),
+            other => Err(mismatch("an amount", other)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Phrase(text) => Ok(text.to_string()),
+            other => Err(mismatch("a phrase", other)),
+        }
+    }
+}
+
+/// Conversions between tuples and conjunctions of the same length.
+macro_rules! conjunction {
+    ($expected:literal; $($part:ident),+) => {
+        impl<$($part: Into<Value>),+> From<($($part,)+)> for Value {
+            #[allow(non_snake_case)]
+            fn from(($($part,)+): ($($part,)+)) -> Self {
+                Self::Conjunction(Rc::new([$($part.into()),+]))
+            }
+        }
+
+        impl<$($part),+> TryFrom<&Value> for ($($part,)+)
+        where
+            $(for<'a> $part: TryFrom<&'a Value, Error = ConversionError>,)+
+        {
+            type Error = ConversionError;
+
+            #[allow(non_snake_case)]
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::Conjunction(values) => match &**values {
+                        [$($part),+] => Ok(($($part::try_from($part)?,)+)),
+                        _ => Err(mismatch($expected, value)),
+                    },
+                    other => Err(mismatch($expected, other)),
+                }
+            }
+        }
+    };
+}
+
+conjunction!("a conjunction of one value"; A);
+conjunction!("a conjunction of two values"; A, B);
+conjunction!("a conjunction of three values"; A, B, C);
+conjunction!("a conjunction of four values"; A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpret::StdHost;
+
+    fn natural() -> Type {
+        Type::Simple(SimpleType::NaturalCount)
+    }
+
+    fn phrase() -> Type {
+        Type::Simple(SimpleType::Phrase)
+    }
+
+    #[test]
+    fn natives_round_trip() {
+        let mut host = StdHost;
+        let mut embedding = Embedding::new(&mut host);
+        let parse = SpellType {
+            components: vec![phrase()],
+            product: Box::new(natural()),
+            fails: true,
+            capabilities: Default::default(),
+        };
+        embedding.spell("parse", parse, |components| {
+            let text = String::try_from(&components[0])?;
+            let n: u64 = text
+                .parse()
+                .map_err(|_| format!("`{text}` is not a number"))?;
+            Ok(n.into())
+        });
+        let source = "
+            swap = charm((a, b): (natural, phrase)) -> (phrase, natural) { (b, a) };
+            double = spell(text: phrase) -> natural fails { n = parse!(text); add(n, n); };
+            safe = spell(text: phrase) -> natural { recover double!(text) as reason { 0; }; };
+        ";
+        embedding.load(source).unwrap();
+
+        let swapped = embedding
+            .invoke("swap", vec![(7u64, "seven").into()])
+            .unwrap();
+        assert_eq!(
+            <(String, u64)>::try_from(&swapped),
+            Ok(("seven".to_string(), 7))
+        );
+        let doubled = embedding.cast("double", vec!["21".into()]).unwrap();
+        assert_eq!(u64::try_from(&doubled), Ok(42));
+        assert!(matches!(
+            embedding.cast("double", vec!["many".into()]),
+            Err(EmbedError::Runtime(_))
+        ));
+        let recovered = embedding.cast("safe", vec!["many".into()]).unwrap();
+        assert_eq!(u64::try_from(&recovered), Ok(0));
+        assert!(String::try_from(&recovered).is_err());
+        assert!(<(u64, u64)>::try_from(&swapped).is_err());
+    }
+
+    #[test]
+    fn misuse_from_rust_is_an_error() {
+        let mut host = StdHost;
+        let mut embedding = Embedding::new(&mut host);
+        embedding
+            .load("twice = charm(n: natural) -> natural { add(n, n) };")
+            .unwrap();
+        assert!(matches!(
+            embedding.invoke("twice", vec!["two".into()]),
+            Err(EmbedError::Mismatch(_))
+        ));
+        assert!(matches!(
+            embedding.invoke("twice", vec![2u64.into(), 2u64.into()]),
+            Err(EmbedError::Mismatch(_))
+        ));
+        assert!(matches!(
+            embedding.invoke("thrice", vec![2u64.into()]),
+            Err(EmbedError::Unbound(_))
+        ));
+        assert!(matches!(
+            embedding.cast("twice", vec![2u64.into()]),
+            Err(EmbedError::Unbound(_))
+        ));
+        assert!(matches!(
+            embedding.load("x: phrase = twice(2);"),
+            Err(EmbedError::Rejected(_))
+        ));
+    }
+
+    #[test]
+    fn components_are_checked_against_named_types() {
+        let mut host = StdHost;
+        let mut embedding = Embedding::new(&mut host);
+        let source = "
+            Shape = type #dot | #square(natural);
+            area = charm(s: Shape) -> natural { divine s { #dot => 0, #square(n) => multiply(n, n) } };
+        ";
+        embedding.load(source).unwrap();
+        let square = Value::Variant("square".into(), Some(Rc::new(3u64.into())));
+        let area = embedding.invoke("area", vec![square]).unwrap();
+        assert_eq!(u64::try_from(&area), Ok(9));
+        assert!(matches!(
+            embedding.invoke("area", vec![Value::Variant("bogus".into(), None)]),
+            Err(EmbedError::Mismatch(_))
+        ));
+        let wrong_payload = Value::Variant("square".into(), Some(Rc::new("three".into())));
+        assert!(matches!(
+            embedding.invoke("area", vec![wrong_payload]),
+            Err(EmbedError::Mismatch(_))
+        ));
+    }
+
+    #[test]
+    fn products_of_the_wrong_type_are_errors() {
+        let mut host = StdHost;
+        let mut embedding = Embedding::new(&mut host);
+        let ty = CharmType {
+            components: vec![natural()],
+            product: Box::new(natural()),
+        };
+        embedding.charm("wrong", ty, |_| Ok("seven".into()));
+        embedding
+            .load("f = charm(n: natural) -> natural { wrong(n) };")
+            .unwrap();
+        let error = embedding.invoke("f", vec![7u64.into()]).unwrap_err();
+        let EmbedError::Runtime(error) = error else {
+            panic!("expected a runtime error, found {error}");
+        };
+        assert!(error.message.contains("`wrong`"), "{}", error.message);
+    }
+}
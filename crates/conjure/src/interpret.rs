@@ -0,0 +1,1331 @@
+//! A tree-walking interpreter for conjurations.
+//!
+//! The interpreter recurses along with the program it runs. To keep deeply recursive charms and
+//! spells from overflowing the native stack, it moves to a new stack segment on the heap whenever
+//! the current one is close to running out.
+//!
+//! To run conjurations it does not trust, a host grants only the [`Capability`]s it means to, and
+//! bounds the steps, nesting and allocation of the run with [`Limits`].
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt, fs,
+    hash::BuildHasher,
+    io::{self, BufRead, Write},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    ast::{self, Capabilities, Capability, Span},
+    builtins::Builtin,
+    embed::Native,
+    numeric::{self, Natural, Whole},
+};
+
+/// The value of an evaluated [`ast::Value`].
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Truth(bool),
+    NaturalCount(Natural),
+    WholeCount(Whole),
+    Amount(f64),
+    Phrase(Rc<str>),
+    Conjunction(Rc<[Value]>),
+    /// Named values, in the order they were given.
+    Record(Rc<[(Rc<str>, Value)]>),
+    Sequence(Rc<[Value]>),
+    /// A tag, and the payload it carries, if any.
+    Variant(Rc<str>, Option<Rc<Value>>),
+    Charm(Rc<Closure<ast::Charm>>),
+    Spell(Rc<Closure<ast::Spell>>),
+    Builtin(Builtin),
+    /// A charm or spell provided by the host.
+    Native(Rc<Native>),
+    Type(Rc<ast::Type>),
+}
+
+/// A charm or spell definition, together with the environment it was defined in.
+#[derive(Debug)]
+pub struct Closure<T> {
+    pub definition: T,
+    pub environment: Environment,
+    /// The symbol the closure was bound to, under which it sees itself when it recurses. Binding
+    /// it here rather than in the environment avoids a reference cycle.
+    pub symbol: Option<Rc<str>>,
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Truth(a), Self::Truth(b)) => a == b,
+            (Self::NaturalCount(a), Self::NaturalCount(b)) => a == b,
+            (Self::WholeCount(a), Self::WholeCount(b)) => a == b,
+            (Self::Amount(a), Self::Amount(b)) => a == b,
+            (Self::Phrase(a), Self::Phrase(b)) => a == b,
+            (Self::Conjunction(a), Self::Conjunction(b)) => a == b,
+            (Self::Record(a), Self::Record(b)) => {
+                a.len() == b.len() && a.iter().all(|field| b.contains(field))
+            }
+            (Self::Sequence(a), Self::Sequence(b)) => a == b,
+            (Self::Variant(a, x), Self::Variant(b, y)) => a == b && x == y,
+            (Self::Charm(a), Self::Charm(b)) => Rc::ptr_eq(a, b),
+            (Self::Spell(a), Self::Spell(b)) => Rc::ptr_eq(a, b),
+            (Self::Builtin(a), Self::Builtin(b)) => a == b,
+            (Self::Native(a), Self::Native(b)) => Rc::ptr_eq(a, b),
+            (Self::Type(a), Self::Type(b)) => a == b,
+            _ => false,
+        })
+    }
+}
+
+/// Values may nest as deeply as the recursion that built them, so they are dropped with a worklist
+/// rather than recursively. Only the parts no other value shares are taken apart.
+impl Drop for Value {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        self.take_parts(&mut pending);
+        while let Some(mut value) = pending.pop() {
+            value.take_parts(&mut pending);
+        }
+    }
+}
+
+impl Value {
+    /// Moves the parts of the value that it alone owns and that have parts of their own into
+    /// `pending`, leaving nil in their place.
+    fn take_parts(&mut self, pending: &mut Vec<Value>) {
+        fn take(value: &mut Value, pending: &mut Vec<Value>) {
+            if value.has_parts() {
+                pending.push(std::mem::replace(value, Value::Nil));
+            }
+        }
+
+        match self {
+            Self::Conjunction(values) | Self::Sequence(values) => {
+                if let Some(values) = Rc::get_mut(values) {
+                    values.iter_mut().for_each(|value| take(value, pending));
+                }
+            }
+            Self::Record(fields) => {
+                if let Some(fields) = Rc::get_mut(fields) {
+                    fields
+                        .iter_mut()
+                        .for_each(|(_, value)| take(value, pending));
+                }
+            }
+            Self::Variant(_, Some(payload)) => {
+                if let Some(payload) = Rc::get_mut(payload) {
+                    take(payload, pending);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn has_parts(&self) -> bool {
+        match self {
+            Self::Conjunction(values) | Self::Sequence(values) => !values.is_empty(),
+            Self::Record(fields) => !fields.is_empty(),
+            Self::Variant(_, payload) => payload.is_some(),
+            _ => false,
+        }
+    }
+}
+
+impl From<&ast::Element> for Value {
+    fn from(element: &ast::Element) -> Self {
+        match element {
+            ast::Element::Nil => Self::Nil,
+            ast::Element::Truth(truth) => Self::Truth(*truth),
+            ast::Element::NaturalCount(n) => Self::NaturalCount(Natural::from(*n)),
+            ast::Element::WholeCount(n) => Self::WholeCount(Whole::from(*n)),
+            ast::Element::Amount(x) => Self::Amount(*x),
+            ast::Element::Phrase(text) => Self::Phrase(text.as_str().into()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || match self {
+            Self::Nil => f.write_str("nil"),
+            Self::Truth(truth) => write!(f, "{truth}"),
+            Self::NaturalCount(n) => write!(f, "{n}"),
+            Self::WholeCount(n) => write!(f, "{n:+}"),
+            Self::Amount(x) => write!(f, "{x:?}"),
+            Self::Phrase(text) => write!(f, "{text:?}"),
+            Self::Conjunction(values) => {
+                f.write_str("(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                if values.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            Self::Record(fields) => {
+                f.write_str("(")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name} = {value}")?;
+                }
+                f.write_str(")")
+            }
+            Self::Sequence(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Variant(tag, payload) => {
+                write!(f, "#{tag}")?;
+                match payload.as_deref() {
+                    Some(Self::Conjunction(values)) if values.len() != 1 => {
+                        write!(f, "{}", Self::Conjunction(values.clone()))
+                    }
+                    Some(payload) => write!(f, "({payload})"),
+                    None => Ok(()),
+                }
+            }
+            Self::Charm(_) => f.write_str("<charm>"),
+            Self::Spell(_) => f.write_str("<spell>"),
+            Self::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name()),
+            Self::Native(native) => write!(f, "<native {}>", native.name),
+            Self::Type(ty) => write!(f, "type {ty}"),
+        })
+    }
+}
+
+/// The symbols visible at some point of a program. Environments are persistent: binding a symbol
+/// creates a new environment and leaves the old one untouched, so closures can share them.
+#[derive(Debug, Clone, Default)]
+pub struct Environment(Option<Rc<Scope>>);
+
+#[derive(Debug)]
+struct Scope {
+    symbol: String,
+    value: Value,
+    parent: Environment,
+}
+
+impl Environment {
+    pub fn bind(&self, symbol: impl Into<String>, value: Value) -> Self {
+        Self(Some(Rc::new(Scope {
+            symbol: symbol.into(),
+            value,
+            parent: self.clone(),
+        })))
+    }
+
+    pub fn lookup(&self, symbol: &str) -> Option<&Value> {
+        let mut environment = self;
+        while let Some(scope) = &environment.0 {
+            if scope.symbol == symbol {
+                return Some(&scope.value);
+            }
+            environment = &scope.parent;
+        }
+        None
+    }
+}
+
+/// An estimate of the bytes a value took up on the heap when it was created, not counting the
+/// values it was made of.
+fn footprint(value: &Value) -> usize {
+    use std::mem::size_of;
+
+    match value {
+        Value::NaturalCount(n) => numeric::natural_size(n),
+        Value::WholeCount(n) => numeric::whole_size(n),
+        Value::Phrase(text) => text.len(),
+        Value::Conjunction(values) | Value::Sequence(values) => values.len() * size_of::<Value>(),
+        Value::Record(fields) => fields
+            .iter()
+            .map(|(name, _)| name.len() + size_of::<(Rc<str>, Value)>())
+            .sum(),
+        Value::Variant(tag, payload) => {
+            tag.len() + payload.as_ref().map_or(0, |_| size_of::<Value>())
+        }
+        Value::Charm(_) => size_of::<Closure<ast::Charm>>(),
+        Value::Spell(_) => size_of::<Closure<ast::Spell>>(),
+        _ => 0,
+    }
+}
+
+/// Binds the symbols of a manifest's pattern to the parts of a value. Returns `None` if the value
+/// does not match the pattern.
+fn destructure(
+    manifest: &ast::Manifest,
+    value: Value,
+    environment: Environment,
+) -> Option<Environment> {
+    match &manifest.pattern {
+        ast::Pattern::Symbol(symbol) => Some(environment.bind(symbol.as_str(), value)),
+        ast::Pattern::Wildcard => Some(environment),
+        ast::Pattern::Conjunction(manifests) => match &value {
+            Value::Conjunction(values) if values.len() == manifests.len() => manifests
+                .iter()
+                .zip(values.iter())
+                .try_fold(environment, |environment, (manifest, value)| {
+                    destructure(manifest, value.clone(), environment)
+                }),
+            _ => None,
+        },
+        ast::Pattern::Optional(inner) => match value {
+            Value::Nil => None,
+            content => destructure(inner, content, environment),
+        },
+        ast::Pattern::Variant { tag, payload } => match &value {
+            Value::Variant(name, content) if **name == **tag => match (payload, content) {
+                (None, _) => Some(environment),
+                (Some(payload), Some(content)) => {
+                    destructure(payload, (**content).clone(), environment)
+                }
+                (Some(_), None) => None,
+            },
+            _ => None,
+        },
+    }
+}
+
+/// The outside world, as seen by spells.
+///
+/// Hosts that cannot provide part of it leave the corresponding methods to report it as
+/// unsupported.
+pub trait Host {
+    fn utter(&mut self, text: &str) -> io::Result<()>;
+
+    /// Reads a line of input, without its line terminator. Returns `None` at the end of input.
+    fn read(&mut self) -> io::Result<Option<String>>;
+
+    /// Reads the whole file at `path`.
+    fn load(&mut self, path: &str) -> io::Result<String> {
+        let _ = path;
+        Err(unsupported("loading files"))
+    }
+
+    /// Replaces the contents of the file at `path` with `text`.
+    fn store(&mut self, path: &str, text: &str) -> io::Result<()> {
+        let _ = (path, text);
+        Err(unsupported("storing files"))
+    }
+
+    /// The current time, in seconds since the Unix epoch.
+    fn now(&mut self) -> io::Result<f64> {
+        Err(unsupported("telling the time"))
+    }
+
+    /// A random amount of at least 0 and less than 1.
+    fn random(&mut self) -> io::Result<f64> {
+        Err(unsupported("drawing random amounts"))
+    }
+}
+
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("the host does not support {what}"),
+    )
+}
+
+/// A [`Host`] connected to the standard input and output of the process.
+#[derive(Debug, Default)]
+pub struct StdHost;
+
+impl Host for StdHost {
+    fn utter(&mut self, text: &str) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{text}")
+    }
+
+    fn read(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+
+    fn load(&mut self, path: &str) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn store(&mut self, path: &str, text: &str) -> io::Result<()> {
+        fs::write(path, text)
+    }
+
+    fn now(&mut self) -> io::Result<f64> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .map_err(io::Error::other)
+    }
+
+    fn random(&mut self) -> io::Result<f64> {
+        // Every `RandomState` is seeded differently, which is random enough for spells.
+        let bits = RandomState::new().hash_one(0u8);
+        Ok((bits >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// The symbol that was being invoked or cast when the error occurred, or the construct that
+    /// failed, if known.
+    pub span: Span,
+    /// Whether a spell failed by casting `fail`, rather than the program going wrong. Only
+    /// failures can be recovered.
+    pub failure: bool,
+    /// The sites of the invocations and casts the error propagated out of, innermost first.
+    pub trace: Vec<Span>,
+    /// The limit the conjuration ran into, if that is what stopped it. The span then points at
+    /// the action being performed.
+    pub exhausted: Option<Limit>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            failure: false,
+            trace: Vec::new(),
+            exhausted: None,
+        }
+    }
+
+    pub fn failure(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            failure: true,
+            ..Self::new(message, span)
+        }
+    }
+
+    pub fn exhausted(limit: Limit, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            exhausted: Some(limit),
+            ..Self::new(message, span)
+        }
+    }
+
+    fn traced(mut self, site: Span) -> Self {
+        self.trace.push(site);
+        self
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failure {
+            f.write_str("unrecovered failure: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Bounds on what running a conjuration may take. Limits that are `None` are not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The number of steps, each of which evaluates a value or performs an action.
+    pub fuel: Option<u64>,
+    /// The number of charm invocations and spell casts that may be nested in each other.
+    pub depth: Option<usize>,
+    /// The number of bytes the values created may take up in total, whether or not they are still
+    /// alive. Sizes are estimated.
+    pub allocation: Option<usize>,
+}
+
+/// One of the [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Fuel,
+    Depth,
+    Allocation,
+}
+
+/// What running conjurations has taken so far, as counted against the [`Limits`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub fuel: u64,
+    /// The current number of nested invocations and casts.
+    pub depth: usize,
+    pub allocation: usize,
+}
+
+/// The stack space below which a new segment is allocated.
+pub(crate) const STACK_RED_ZONE: usize = 64 * 1024;
+
+/// The size of newly allocated stack segments.
+pub(crate) const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+pub struct Interpreter<'h> {
+    host: &'h mut dyn Host,
+    globals: Environment,
+    span: Span,
+    /// The capabilities the host grants to spells.
+    granted: Capabilities,
+    limits: Limits,
+    usage: Usage,
+    /// The span of the action being performed, which errors about exhausted limits point at.
+    action: Span,
+}
+
+impl<'h> Interpreter<'h> {
+    /// Creates an interpreter that grants no capabilities; see [`Interpreter::grant`].
+    pub fn new(host: &'h mut dyn Host) -> Self {
+        Self {
+            host,
+            globals: Environment::default(),
+            span: Span::default(),
+            granted: Capabilities::new(),
+            limits: Limits::default(),
+            usage: Usage::default(),
+            action: Span::default(),
+        }
+    }
+
+    /// Allows spells to use the given capabilities. Casting a builtin that needs a capability that
+    /// was not granted is an error, so that the host stays in control of what a conjuration can
+    /// reach, whatever its types claim.
+    pub fn grant(&mut self, capabilities: impl IntoIterator<Item = Capability>) {
+        self.granted.extend(capabilities);
+    }
+
+    /// Bounds what running conjurations may take from now on. The usage counted so far is kept,
+    /// so a host may raise a limit to let a conjuration continue where it ran out.
+    pub fn limit(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    /// Takes a step, unless the fuel has run out.
+    fn step(&mut self) -> Result<()> {
+        self.usage.fuel += 1;
+        match self.limits.fuel {
+            Some(fuel) if self.usage.fuel > fuel => Err(RuntimeError::exhausted(
+                Limit::Fuel,
+                format!("ran out of fuel after {fuel} steps"),
+                self.action,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs a charm invocation or spell cast nested in the current one, unless that would nest
+    /// them too deeply.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if let Some(depth) = self.limits.depth {
+            if self.usage.depth >= depth {
+                return Err(RuntimeError::exhausted(
+                    Limit::Depth,
+                    format!("exceeded the depth of {depth} nested invocations and casts"),
+                    self.action,
+                ));
+            }
+        }
+        self.usage.depth += 1;
+        let result = f(self);
+        self.usage.depth -= 1;
+        result
+    }
+
+    /// Counts a newly created value against the allocation limit.
+    fn allocate(&mut self, value: Value) -> Result<Value> {
+        let size = footprint(&value);
+        self.reserve(size)?;
+        self.usage.allocation = self.usage.allocation.saturating_add(size);
+        Ok(value)
+    }
+
+    /// Checks that creating values of `size` bytes stays within the allocation limit, before they
+    /// are created.
+    fn reserve(&self, size: usize) -> Result<()> {
+        match self.limits.allocation {
+            Some(allocation) if self.usage.allocation.saturating_add(size) > allocation => {
+                Err(RuntimeError::exhausted(
+                    Limit::Allocation,
+                    format!("exceeded the allocation limit of {allocation} bytes"),
+                    self.action,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the host grants a capability that the builtin or native spell `name` needs.
+    fn require(&self, name: &str, capability: Capability) -> Result<()> {
+        if self.granted.contains(&capability) {
+            return Ok(());
+        }
+        Err(self.error(format!(
+            "`{name}` needs the {capability} capability, which the host does not grant"
+        )))
+    }
+
+    /// Binds a global, as if it were bound by the conjuration before its own bindings.
+    pub fn define(&mut self, symbol: &str, value: Value) {
+        self.globals = self.globals.bind(symbol, value);
+    }
+
+    fn error(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::new(message, self.span)
+    }
+
+    pub fn globals(&self) -> &Environment {
+        &self.globals
+    }
+
+    /// Evaluates the bindings of a conjuration and casts its `*` spell.
+    pub fn run(&mut self, conjuration: &ast::Conjuration) -> Result<Value> {
+        self.load(conjuration)?;
+        let Some(main) = self.globals.lookup("*").cloned() else {
+            return Err(self.error("the conjuration has no `*` spell to cast"));
+        };
+        self.cast(&main, Vec::new())
+    }
+
+    /// Evaluates the bindings of a conjuration, making them available as globals.
+    pub fn load(&mut self, conjuration: &ast::Conjuration) -> Result<()> {
+        for binding in &conjuration.bindings {
+            self.bind(binding)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates a binding and adds it to the globals.
+    pub fn bind(&mut self, binding: &ast::Binding) -> Result<Value> {
+        let value = self.evaluate_binding(binding, &self.globals.clone())?;
+        self.globals =
+            self.bind_manifest(&binding.manifest, value.clone(), self.globals.clone())?;
+        Ok(value)
+    }
+
+    /// Evaluates a value in the global environment.
+    pub fn evaluate(&mut self, value: &ast::Value) -> Result<Value> {
+        self.evaluate_in(value, &self.globals.clone())
+    }
+
+    /// Performs an action in the global environment. Binding actions add to the globals.
+    pub fn perform(&mut self, action: &ast::Action) -> Result<Value> {
+        let mut globals = self.globals.clone();
+        let value = self.perform_in(action, &mut globals)?;
+        self.globals = globals;
+        Ok(value)
+    }
+
+    /// Evaluates the value of a binding. Charms and spells are given the symbol they are bound to,
+    /// so that they can recurse.
+    fn evaluate_binding(
+        &mut self,
+        binding: &ast::Binding,
+        environment: &Environment,
+    ) -> Result<Value> {
+        let symbol = binding.manifest.symbol().map(|s| s.as_str().into());
+        match &binding.value {
+            ast::Value::Charm(charm) => self.allocate(Value::Charm(Rc::new(Closure {
+                definition: charm.clone(),
+                environment: environment.clone(),
+                symbol,
+            }))),
+            ast::Value::Spell(spell) => self.allocate(Value::Spell(Rc::new(Closure {
+                definition: spell.clone(),
+                environment: environment.clone(),
+                symbol,
+            }))),
+            value => self.evaluate_in(value, environment),
+        }
+    }
+
+    fn evaluate_in(&mut self, value: &ast::Value, environment: &Environment) -> Result<Value> {
+        stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            self.evaluate_node(value, environment)
+        })
+    }
+
+    fn evaluate_node(&mut self, value: &ast::Value, environment: &Environment) -> Result<Value> {
+        self.step()?;
+        let created = matches!(
+            value,
+            ast::Value::Element(_)
+                | ast::Value::Conjunction(_)
+                | ast::Value::Record(_)
+                | ast::Value::Sequence(_)
+                | ast::Value::Variant(_)
+                | ast::Value::Interpolation(_)
+                | ast::Value::Charm(_)
+                | ast::Value::Spell(_)
+        );
+        let value = match value {
+            ast::Value::Element(element) => Ok(element.into()),
+            ast::Value::Symbol(symbol) => self.lookup(symbol, environment),
+            ast::Value::Boundary(boundary) => self.boundary(boundary, environment.clone()),
+            ast::Value::Conjunction(values) => values
+                .iter()
+                .map(|v| self.evaluate_in(v, environment))
+                .collect::<Result<Rc<[Value]>>>()
+                .map(Value::Conjunction),
+            ast::Value::Record(fields) => fields
+                .iter()
+                .map(|f| {
+                    Ok((
+                        f.symbol.as_str().into(),
+                        self.evaluate_in(&f.value, environment)?,
+                    ))
+                })
+                .collect::<Result<Rc<[_]>>>()
+                .map(Value::Record),
+            ast::Value::Projection(projection) => {
+                let field = &projection.field;
+                match &self.evaluate_in(&projection.record, environment)? {
+                    Value::Record(fields) => fields
+                        .iter()
+                        .find(|(name, _)| **name == **field)
+                        .map(|(_, value)| value.clone())
+                        .ok_or_else(|| {
+                            RuntimeError::new(
+                                format!("the record has no field `{field}`"),
+                                field.span,
+                            )
+                        }),
+                    other => Err(RuntimeError::new(
+                        format!("cannot project `{field}` from {other}, which is not a record"),
+                        field.span,
+                    )),
+                }
+            }
+            ast::Value::Sequence(sequence) => sequence
+                .elements
+                .iter()
+                .map(|v| self.evaluate_in(v, environment))
+                .collect::<Result<Rc<[Value]>>>()
+                .map(Value::Sequence),
+            ast::Value::Variant(variant) => {
+                let payload = match &variant.payload {
+                    Some(payload) => Some(Rc::new(self.evaluate_in(payload, environment)?)),
+                    None => None,
+                };
+                Ok(Value::Variant(variant.tag.as_str().into(), payload))
+            }
+            ast::Value::Interpolation(interpolation) => {
+                let mut phrase = String::new();
+                for piece in &interpolation.pieces {
+                    let embed = match piece {
+                        ast::Piece::Text(text) => {
+                            phrase.push_str(text);
+                            continue;
+                        }
+                        ast::Piece::Embed(embed) => embed,
+                    };
+                    let value = self.evaluate_in(&embed.value, environment)?;
+                    let (builtin, components) = match embed.spec {
+                        Some(spec) => (
+                            Builtin::Format,
+                            vec![value, Value::Phrase(spec.to_string().into())],
+                        ),
+                        None => (Builtin::Describe, vec![value]),
+                    };
+                    self.span = interpolation.span;
+                    match &self.invoke(&Value::Builtin(builtin), components)? {
+                        Value::Phrase(text) => phrase.push_str(text),
+                        other => {
+                            return Err(self.error(format!("cannot embed {other} in a phrase")))
+                        }
+                    }
+                }
+                Ok(Value::Phrase(phrase.into()))
+            }
+            ast::Value::Charm(charm) => Ok(Value::Charm(Rc::new(Closure {
+                definition: charm.clone(),
+                environment: environment.clone(),
+                symbol: None,
+            }))),
+            ast::Value::Invocation(invocation) => {
+                let charm = self.evaluate_in(&invocation.charm, environment)?;
+                let components = invocation
+                    .components
+                    .iter()
+                    .map(|v| self.evaluate_in(v, environment))
+                    .collect::<Result<Vec<_>>>()?;
+                if let ast::Value::Symbol(symbol) = &*invocation.charm {
+                    self.span = symbol.span;
+                }
+                let site = self.span;
+                self.invoke(&charm, components).map_err(|e| match charm {
+                    Value::Charm(_) => e.traced(site),
+                    _ => e,
+                })
+            }
+            ast::Value::Spell(spell) => Ok(Value::Spell(Rc::new(Closure {
+                definition: spell.clone(),
+                environment: environment.clone(),
+                symbol: None,
+            }))),
+            ast::Value::Type(ty) => Ok(Value::Type(Rc::new(ty.clone()))),
+            ast::Value::Divination(divination) => self.divination(divination, environment),
+        }?;
+        if created {
+            self.allocate(value)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn divination(
+        &mut self,
+        divination: &ast::Divination,
+        environment: &Environment,
+    ) -> Result<Value> {
+        let subject = match &divination.subject {
+            Some(subject) => Some(self.evaluate_in(subject, environment)?),
+            None => None,
+        };
+        for arm in &divination.arms {
+            match &arm.omen {
+                ast::Omen::Truth(value) => match self.evaluate_in(value, environment)? {
+                    Value::Truth(true) => return self.evaluate_in(&arm.value, environment),
+                    Value::Truth(false) => (),
+                    other => {
+                        return Err(RuntimeError::new(
+                            format!("cannot divine from {other}, which is not a truth"),
+                            arm.span,
+                        ))
+                    }
+                },
+                ast::Omen::Presence(presence) => {
+                    match self.evaluate_in(&presence.value, environment)? {
+                        Value::Nil => (),
+                        content => {
+                            let matched =
+                                destructure(&presence.manifest, content, environment.clone());
+                            if let Some(environment) = matched {
+                                return self.evaluate_in(&arm.value, &environment);
+                            }
+                        }
+                    }
+                }
+                ast::Omen::Pattern(manifest) => {
+                    let matched = subject
+                        .clone()
+                        .and_then(|subject| destructure(manifest, subject, environment.clone()));
+                    if let Some(environment) = matched {
+                        return self.evaluate_in(&arm.value, &environment);
+                    }
+                }
+                ast::Omen::Otherwise => return self.evaluate_in(&arm.value, environment),
+            }
+        }
+        Err(RuntimeError::new(
+            "no arm of the divination holds",
+            divination.span,
+        ))
+    }
+
+    fn lookup(&self, symbol: &ast::Symbol, environment: &Environment) -> Result<Value> {
+        if let Some(value) = environment.lookup(symbol) {
+            return Ok(value.clone());
+        }
+        match Builtin::from_name(symbol) {
+            Some(builtin) => Ok(Value::Builtin(builtin)),
+            None => Err(RuntimeError::new(
+                format!("`{symbol}` is not bound"),
+                symbol.span,
+            )),
+        }
+    }
+
+    fn boundary(
+        &mut self,
+        boundary: &ast::Boundary,
+        mut environment: Environment,
+    ) -> Result<Value> {
+        for binding in &boundary.bindings {
+            let value = self.evaluate_binding(binding, &environment)?;
+            environment = self.bind_manifest(&binding.manifest, value, environment)?;
+        }
+        self.evaluate_in(&boundary.value, &environment)
+    }
+
+    /// The environment a closure runs in: its own, with itself and the components bound.
+    fn closure_environment<T>(
+        &self,
+        closure: &Closure<T>,
+        itself: Value,
+        manifests: &[ast::Manifest],
+        components: Vec<Value>,
+    ) -> Result<Environment> {
+        let mut environment = closure.environment.clone();
+        if let Some(symbol) = &closure.symbol {
+            environment = environment.bind(&**symbol, itself);
+        }
+        self.bind_components(manifests, components, environment)
+    }
+
+    fn bind_components(
+        &self,
+        manifests: &[ast::Manifest],
+        components: Vec<Value>,
+        mut environment: Environment,
+    ) -> Result<Environment> {
+        if manifests.len() != components.len() {
+            return Err(self.error(format!(
+                "expected {} components, found {}",
+                manifests.len(),
+                components.len()
+            )));
+        }
+        for (manifest, value) in manifests.iter().zip(components) {
+            environment = self.bind_manifest(manifest, value, environment)?;
+        }
+        Ok(environment)
+    }
+
+    /// Binds a manifest that the type checker found to match every value of its type.
+    fn bind_manifest(
+        &self,
+        manifest: &ast::Manifest,
+        value: Value,
+        environment: Environment,
+    ) -> Result<Environment> {
+        destructure(manifest, value, environment).ok_or_else(|| {
+            RuntimeError::new(
+                format!("the value does not match `{}`", manifest.pattern),
+                manifest.span,
+            )
+        })
+    }
+
+    /// Invokes a charm with the given components.
+    pub fn invoke(&mut self, charm: &Value, components: Vec<Value>) -> Result<Value> {
+        match charm {
+            Value::Charm(closure) => self.nested(|interpreter| {
+                let charm = &closure.definition;
+                let environment = interpreter.closure_environment(
+                    closure,
+                    Value::Charm(closure.clone()),
+                    &charm.components,
+                    components,
+                )?;
+                interpreter.boundary(&charm.boundary, environment)
+            }),
+            Value::Builtin(builtin) if builtin.takes_charms() => {
+                self.reserve(builtin.projected_footprint(&components))?;
+                let product = self.apply_charms(*builtin, components)?;
+                self.allocate(product)
+            }
+            Value::Builtin(builtin) if !builtin.is_spell() => {
+                self.reserve(builtin.projected_footprint(&components))?;
+                let product = builtin
+                    .apply(&components, &mut *self.host)
+                    .map_err(|message| self.error(message))?;
+                self.allocate(product)
+            }
+            Value::Native(native) if native.spell().is_none() => {
+                let product = native
+                    .call(&components)
+                    .map_err(|message| self.error(message))?;
+                native
+                    .check_product(&components, &product)
+                    .map_err(|message| self.error(message))?;
+                self.allocate(product)
+            }
+            other => Err(self.error(format!("cannot invoke {other}, which is not a charm"))),
+        }
+    }
+
+    /// Applies a builtin that invokes the charms among its components.
+    fn apply_charms(&mut self, builtin: Builtin, components: Vec<Value>) -> Result<Value> {
+        let span = self.span;
+        match (builtin, components.as_slice()) {
+            (Builtin::Map, [Value::Sequence(elements), charm]) => elements
+                .iter()
+                .map(|element| self.invoke(charm, vec![element.clone()]))
+                .collect::<Result<Rc<[Value]>>>()
+                .map(Value::Sequence),
+            (Builtin::Filter, [Value::Sequence(elements), charm]) => {
+                let mut kept = Vec::new();
+                for element in elements.iter() {
+                    match self.invoke(charm, vec![element.clone()])? {
+                        Value::Truth(true) => kept.push(element.clone()),
+                        Value::Truth(false) => (),
+                        other => {
+                            return Err(RuntimeError::new(
+                                format!("cannot filter by {other}, which is not a truth"),
+                                span,
+                            ))
+                        }
+                    }
+                }
+                Ok(Value::Sequence(kept.into()))
+            }
+            (Builtin::Fold, [Value::Sequence(elements), initial, charm]) => elements
+                .iter()
+                .try_fold(initial.clone(), |accumulator, element| {
+                    self.invoke(charm, vec![accumulator, element.clone()])
+                }),
+            (builtin, components) => Err(RuntimeError::new(
+                format!(
+                    "`{}` cannot be applied to ({})",
+                    builtin.name(),
+                    components
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                span,
+            )),
+        }
+    }
+
+    /// Casts a spell with the given components.
+    pub fn cast(&mut self, spell: &Value, components: Vec<Value>) -> Result<Value> {
+        match spell {
+            Value::Spell(closure) => self.nested(|interpreter| {
+                let spell = &closure.definition;
+                let mut environment = interpreter.closure_environment(
+                    closure,
+                    Value::Spell(closure.clone()),
+                    &spell.components,
+                    components,
+                )?;
+                let mut product = Value::Nil;
+                for action in &spell.actions {
+                    product = interpreter.perform_in(action, &mut environment)?;
+                }
+                if matches!(spell.ty, ast::Type::Nil) {
+                    product = Value::Nil;
+                }
+                Ok(product)
+            }),
+            Value::Builtin(Builtin::Fail) => match components.as_slice() {
+                [Value::Phrase(message)] => Err(RuntimeError::failure(&**message, self.span)),
+                _ => Err(self.error("`fail` must be cast with a phrase")),
+            },
+            Value::Builtin(builtin) if builtin.is_spell() => {
+                if let Some(capability) = builtin.capability() {
+                    self.require(builtin.name(), capability)?;
+                }
+                self.reserve(builtin.projected_footprint(&components))?;
+                let product = builtin
+                    .apply(&components, &mut *self.host)
+                    .map_err(|message| {
+                        if builtin.fails() {
+                            RuntimeError::failure(message, self.span)
+                        } else {
+                            self.error(message)
+                        }
+                    })?;
+                self.allocate(product)
+            }
+            Value::Native(native) if native.spell().is_some() => {
+                let ty = native.spell().unwrap();
+                for &capability in &ty.capabilities {
+                    self.require(&native.name, capability)?;
+                }
+                let product = native.call(&components).map_err(|message| {
+                    if ty.fails {
+                        RuntimeError::failure(message, self.span)
+                    } else {
+                        self.error(message)
+                    }
+                })?;
+                native
+                    .check_product(&components, &product)
+                    .map_err(|message| self.error(message))?;
+                self.allocate(product)
+            }
+            other => Err(self.error(format!("cannot cast {other}, which is not a spell"))),
+        }
+    }
+
+    fn perform_in(&mut self, action: &ast::Action, environment: &mut Environment) -> Result<Value> {
+        let outer = self.action;
+        self.action = action.span().unwrap_or(outer);
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            self.perform_node(action, environment)
+        });
+        self.action = outer;
+        result
+    }
+
+    fn perform_node(
+        &mut self,
+        action: &ast::Action,
+        environment: &mut Environment,
+    ) -> Result<Value> {
+        self.step()?;
+        match action {
+            ast::Action::Value(value) => self.evaluate_in(value, environment),
+            ast::Action::Cast(cast) => self.cast_in(cast, environment),
+            ast::Action::Binding(binding) => {
+                let value = self.evaluate_binding(binding, environment)?;
+                *environment = self.bind_manifest(&binding.manifest, value, environment.clone())?;
+                Ok(Value::Nil)
+            }
+            ast::Action::Channel(channel) => {
+                let value = self.cast_in(&channel.cast, environment)?;
+                *environment = self.bind_manifest(&channel.manifest, value, environment.clone())?;
+                Ok(Value::Nil)
+            }
+            ast::Action::Repetition(repetition) => {
+                let count = match &self.evaluate_in(&repetition.count, environment)? {
+                    Value::NaturalCount(count) if numeric::to_u64(count).is_some() => {
+                        numeric::to_u64(count).unwrap()
+                    }
+                    other => {
+                        return Err(RuntimeError::new(
+                            format!("cannot repeat {other} times"),
+                            repetition.span,
+                        ))
+                    }
+                };
+                for i in 0..count {
+                    self.step()?;
+                    let counter = Value::NaturalCount(Natural::from(i));
+                    let mut scope = match &repetition.counter {
+                        Some(manifest) => {
+                            self.bind_manifest(manifest, counter, environment.clone())?
+                        }
+                        None => environment.clone(),
+                    };
+                    for action in &repetition.actions {
+                        self.perform_in(action, &mut scope)?;
+                    }
+                }
+                Ok(Value::Nil)
+            }
+        }
+    }
+
+    fn cast_in(&mut self, cast: &ast::Cast, environment: &mut Environment) -> Result<Value> {
+        let error = match self.cast_unrecovered(cast, environment) {
+            Err(error) if error.failure && cast.recovery.is_some() => error,
+            result => return result,
+        };
+        let recovery = cast.recovery.as_ref().unwrap();
+        let failure = Value::Phrase(error.message.into());
+        let mut scope = match &recovery.failure {
+            Some(manifest) => self.bind_manifest(manifest, failure, environment.clone())?,
+            None => environment.clone(),
+        };
+        let mut product = Value::Nil;
+        for action in &recovery.actions {
+            product = self.perform_in(action, &mut scope)?;
+        }
+        Ok(product)
+    }
+
+    /// Casts the spell of a cast, as if it had no recovery.
+    fn cast_unrecovered(
+        &mut self,
+        cast: &ast::Cast,
+        environment: &mut Environment,
+    ) -> Result<Value> {
+        let spell = self.perform_in(&cast.spell, environment)?;
+        let components = cast
+            .components
+            .iter()
+            .map(|a| self.perform_in(a, environment))
+            .collect::<Result<Vec<_>>>()?;
+        if let ast::Action::Value(ast::Value::Symbol(symbol)) = &*cast.spell {
+            self.span = symbol.span;
+        }
+        let site = self.span;
+        self.cast(&spell, components).map_err(|e| match spell {
+            Value::Spell(_) => e.traced(site),
+            _ => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax;
+
+    /// A host that collects what is uttered.
+    #[derive(Default)]
+    struct Scribe(Vec<String>);
+
+    impl Host for Scribe {
+        fn utter(&mut self, text: &str) -> io::Result<()> {
+            self.0.push(text.to_string());
+            Ok(())
+        }
+
+        fn read(&mut self) -> io::Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    fn run(source: &str, limits: Limits) -> Result<Value> {
+        let conjuration = syntax::parse(source).unwrap();
+        let mut host = Scribe::default();
+        let mut interpreter = Interpreter::new(&mut host);
+        interpreter.limit(limits);
+        interpreter.run(&conjuration)
+    }
+
+    #[test]
+    fn deep_values_are_dropped_without_overflow() {
+        let source = "
+            List = type #empty | #cons((natural, List));
+            build = charm(n: natural) -> List {
+                divine { equals(n, 0) => #empty, _ => #cons((n, build(subtract(n, 1)))) }
+            };
+            * = spell() -> nil { l = build(20000); };
+        ";
+        assert!(run(source, Limits::default()).is_ok());
+    }
+
+    #[test]
+    fn deep_values_are_compared_and_written_without_overflow() {
+        let build = || {
+            (0..100_000).fold(Value::Nil, |value, _| {
+                Value::Variant(
+                    "link".into(),
+                    Some(Rc::new(Value::Conjunction(Rc::new([value])))),
+                )
+            })
+        };
+        let (a, b) = (build(), build());
+        assert_eq!(a, b);
+        assert!(a.to_string().contains("#link((nil,))"));
+    }
+
+    #[test]
+    fn fuel_runs_out_in_an_empty_repetition() {
+        let limits = Limits {
+            fuel: Some(1000),
+            ..Limits::default()
+        };
+        let error = run("* = spell() -> nil { repeat 100000000000 { }; };", limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Fuel));
+    }
+
+    #[test]
+    fn fuel_runs_out_in_endless_recursion() {
+        let limits = Limits {
+            fuel: Some(1000),
+            ..Limits::default()
+        };
+        let source = "again = spell() -> nil { again!(); };\n* = spell() -> nil { again!(); };";
+        let error = run(source, limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Fuel));
+    }
+
+    #[test]
+    fn depth_runs_out_in_deep_recursion() {
+        let limits = Limits {
+            depth: Some(10),
+            ..Limits::default()
+        };
+        let source = "
+            count = charm(n: natural) -> natural {
+                divine { equals(n, 0) => 0, _ => count(subtract(n, 1)) }
+            };
+            * = spell() -> nil { n = count(20); };
+        ";
+        let error = run(source, limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Depth));
+        let shallow = source.replace("count(20)", "count(5)");
+        assert!(run(&shallow, limits).is_ok());
+    }
+
+    #[test]
+    fn allocation_runs_out_in_a_growing_sequence() {
+        let limits = Limits {
+            allocation: Some(10000),
+            ..Limits::default()
+        };
+        let source = "
+            grow = charm(items: [natural]) -> [natural] { concat(items, items) };
+            * = spell() -> nil {
+                repeat 64 { items = grow([1]); };
+            };
+        ";
+        assert!(run(source, limits).is_ok());
+        let source = "
+            grow = charm(items: [natural], k: natural) -> [natural] {
+                divine { equals(k, 0) => items, _ => grow(concat(items, items), subtract(k, 1)) }
+            };
+            * = spell() -> nil { items = grow([1], 20); };
+        ";
+        let error = run(source, limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Allocation));
+    }
+
+    #[test]
+    fn ungranted_capabilities_are_errors() {
+        let source = r#"* = spell() -> nil { utter!("the time is"); t = now!(); };"#;
+        let conjuration = syntax::parse(source).unwrap();
+        let mut host = Scribe::default();
+        let mut interpreter = Interpreter::new(&mut host);
+        interpreter.grant([Capability::Console]);
+        let error = interpreter.run(&conjuration).unwrap_err();
+        assert!(
+            error.message.contains("clock capability"),
+            "{}",
+            error.message
+        );
+        assert_eq!(error.exhausted, None);
+        assert_eq!(host.0, ["the time is"]);
+
+        let error = run(source, Limits::default()).unwrap_err();
+        assert!(
+            error.message.contains("console capability"),
+            "{}",
+            error.message
+        );
+    }
+
+    #[test]
+    fn allocation_is_checked_before_padding() {
+        let limits = Limits {
+            allocation: Some(1000),
+            ..Limits::default()
+        };
+        let source = r#"* = spell() -> nil { utter!(format(1, "60000")); };"#;
+        let error = run(source, limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Allocation));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn allocation_counts_large_counts() {
+        let limits = Limits {
+            allocation: Some(10000),
+            ..Limits::default()
+        };
+        let source = "
+            square = charm(n: natural, k: natural) -> natural {
+                divine { equals(k, 0) => n, _ => square(multiply(n, n), subtract(k, 1)) }
+            };
+            * = spell() -> nil { n = square(2, 30); };
+        ";
+        let error = run(source, limits).unwrap_err();
+        assert_eq!(error.exhausted, Some(Limit::Allocation));
+    }
+}
@@ -1 +1,11 @@
 pub mod ast;
+pub mod builtins;
+pub mod check;
+pub mod diagnostic;
+pub mod embed;
+pub mod interpret;
+pub mod numeric;
+pub mod resolve;
+pub mod session;
+pub mod syntax;
+pub mod typeck;
@@ -0,0 +1,416 @@
+//! The numbers that values are made of, and arithmetic on them.
+//!
+//! Counts are exact. Arithmetic on them fails rather than produce a wrong result, whether the
+//! result would be too large, below zero for a natural, or the quotient of a division by zero. By
+//! default, counts are 64 bits wide; with the `bigint` feature, they have arbitrary precision, so
+//! only naturals going below zero and divisions by zero fail.
+//!
+//! Amounts are floating point numbers. Arithmetic on them fails when the result is not a finite
+//! number, which includes dividing by zero; rounding to the nearest amount is not a failure. Where
+//! precision matters, as with money, count the smallest unit instead.
+//!
+//! Conversions between naturals, wholes and amounts fail unless the number is the same afterwards.
+
+use std::fmt;
+
+#[cfg(not(feature = "bigint"))]
+pub type Natural = u64;
+#[cfg(not(feature = "bigint"))]
+pub type Whole = i64;
+
+#[cfg(feature = "bigint")]
+pub type Natural = num_bigint::BigUint;
+#[cfg(feature = "bigint")]
+pub type Whole = num_bigint::BigInt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+/// Why a number could not be computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericError {
+    /// The number is too large for its kind.
+    Overflow,
+    /// The number is too small for its kind, such as a natural below zero.
+    Underflow,
+    DivisionByZero,
+    /// The number lies between two numbers of its kind, such as an amount with a fraction
+    /// converted to a count.
+    Inexact,
+}
+
+impl fmt::Display for NumericError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow => f.write_str("the result is too large"),
+            Self::Underflow => f.write_str("the result is too small"),
+            Self::DivisionByZero => f.write_str("division by zero"),
+            Self::Inexact => f.write_str("the result would lose precision"),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, NumericError>;
+
+/// The direction a result that does not fit went out of range in.
+fn out_of_range(negative: bool) -> NumericError {
+    if negative {
+        NumericError::Underflow
+    } else {
+        NumericError::Overflow
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn naturals(operation: Operation, a: &Natural, b: &Natural) -> Result<Natural> {
+    let (a, b) = (u128::from(*a), u128::from(*b));
+    let result = match operation {
+        Operation::Add => a + b,
+        Operation::Subtract => a.checked_sub(b).ok_or(NumericError::Underflow)?,
+        Operation::Multiply => a * b,
+        Operation::Divide => a.checked_div(b).ok_or(NumericError::DivisionByZero)?,
+        Operation::Remainder => a.checked_rem(b).ok_or(NumericError::DivisionByZero)?,
+    };
+    Natural::try_from(result).map_err(|_| NumericError::Overflow)
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn wholes(operation: Operation, a: &Whole, b: &Whole) -> Result<Whole> {
+    let (a, b) = (i128::from(*a), i128::from(*b));
+    let result = match operation {
+        Operation::Add => a + b,
+        Operation::Subtract => a - b,
+        Operation::Multiply => a * b,
+        Operation::Divide => a.checked_div(b).ok_or(NumericError::DivisionByZero)?,
+        Operation::Remainder => a.checked_rem(b).ok_or(NumericError::DivisionByZero)?,
+    };
+    Whole::try_from(result).map_err(|_| out_of_range(result < 0))
+}
+
+#[cfg(feature = "bigint")]
+pub fn naturals(operation: Operation, a: &Natural, b: &Natural) -> Result<Natural> {
+    use num_traits::{CheckedSub, Zero};
+
+    match operation {
+        Operation::Add => Ok(a + b),
+        Operation::Subtract => a.checked_sub(b).ok_or(NumericError::Underflow),
+        Operation::Multiply => Ok(a * b),
+        Operation::Divide | Operation::Remainder if b.is_zero() => {
+            Err(NumericError::DivisionByZero)
+        }
+        Operation::Divide => Ok(a / b),
+        Operation::Remainder => Ok(a % b),
+    }
+}
+
+#[cfg(feature = "bigint")]
+pub fn wholes(operation: Operation, a: &Whole, b: &Whole) -> Result<Whole> {
+    use num_traits::Zero;
+
+    match operation {
+        Operation::Add => Ok(a + b),
+        Operation::Subtract => Ok(a - b),
+        Operation::Multiply => Ok(a * b),
+        Operation::Divide | Operation::Remainder if b.is_zero() => {
+            Err(NumericError::DivisionByZero)
+        }
+        Operation::Divide => Ok(a / b),
+        Operation::Remainder => Ok(a % b),
+    }
+}
+
+pub fn amounts(operation: Operation, a: f64, b: f64) -> Result<f64> {
+    let result = match operation {
+        Operation::Add => a + b,
+        Operation::Subtract => a - b,
+        Operation::Multiply => a * b,
+        Operation::Divide | Operation::Remainder if b == 0.0 => {
+            return Err(NumericError::DivisionByZero)
+        }
+        Operation::Divide => a / b,
+        Operation::Remainder => a % b,
+    };
+    if result.is_finite() {
+        Ok(result)
+    } else {
+        Err(out_of_range(result < 0.0))
+    }
+}
+
+/// The whole number an amount is equal to, if it has no fraction.
+fn integral(amount: f64) -> Result<f64> {
+    if amount.is_finite() && amount.fract() == 0.0 {
+        Ok(amount)
+    } else {
+        Err(NumericError::Inexact)
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn natural_from_whole(whole: &Whole) -> Result<Natural> {
+    Natural::try_from(*whole).map_err(|_| NumericError::Underflow)
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn whole_from_natural(natural: &Natural) -> Result<Whole> {
+    Whole::try_from(*natural).map_err(|_| NumericError::Overflow)
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn natural_from_amount(amount: f64) -> Result<Natural> {
+    let amount = integral(amount)?;
+    if amount < 0.0 {
+        Err(NumericError::Underflow)
+    } else if amount >= Natural::MAX as f64 {
+        // The largest natural is not an amount; the closest amount is one above it.
+        Err(NumericError::Overflow)
+    } else {
+        Ok(amount as Natural)
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn whole_from_amount(amount: f64) -> Result<Whole> {
+    let amount = integral(amount)?;
+    if amount < Whole::MIN as f64 {
+        Err(NumericError::Underflow)
+    } else if amount >= Whole::MAX as f64 {
+        Err(NumericError::Overflow)
+    } else {
+        Ok(amount as Whole)
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn amount_from_natural(natural: &Natural) -> Result<f64> {
+    let amount = *natural as f64;
+    if amount as u128 == u128::from(*natural) {
+        Ok(amount)
+    } else {
+        Err(NumericError::Inexact)
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn amount_from_whole(whole: &Whole) -> Result<f64> {
+    let amount = *whole as f64;
+    if amount as i128 == i128::from(*whole) {
+        Ok(amount)
+    } else {
+        Err(NumericError::Inexact)
+    }
+}
+
+#[cfg(feature = "bigint")]
+pub fn natural_from_whole(whole: &Whole) -> Result<Natural> {
+    whole.to_biguint().ok_or(NumericError::Underflow)
+}
+
+#[cfg(feature = "bigint")]
+pub fn whole_from_natural(natural: &Natural) -> Result<Whole> {
+    Ok(Whole::from(natural.clone()))
+}
+
+#[cfg(feature = "bigint")]
+pub fn natural_from_amount(amount: f64) -> Result<Natural> {
+    use num_traits::FromPrimitive;
+
+    let amount = integral(amount)?;
+    Natural::from_f64(amount).ok_or(NumericError::Underflow)
+}
+
+#[cfg(feature = "bigint")]
+pub fn whole_from_amount(amount: f64) -> Result<Whole> {
+    use num_traits::FromPrimitive;
+
+    let amount = integral(amount)?;
+    Whole::from_f64(amount).ok_or(NumericError::Inexact)
+}
+
+#[cfg(feature = "bigint")]
+pub fn amount_from_natural(natural: &Natural) -> Result<f64> {
+    use num_traits::ToPrimitive;
+
+    let amount = natural.to_f64().filter(|x| x.is_finite());
+    match amount {
+        Some(amount) if natural_from_amount(amount).as_ref() == Ok(natural) => Ok(amount),
+        Some(_) => Err(NumericError::Inexact),
+        None => Err(NumericError::Overflow),
+    }
+}
+
+#[cfg(feature = "bigint")]
+pub fn amount_from_whole(whole: &Whole) -> Result<f64> {
+    use num_traits::ToPrimitive;
+
+    let amount = whole.to_f64().filter(|x| x.is_finite());
+    match amount {
+        Some(amount) if whole_from_amount(amount).as_ref() == Ok(whole) => Ok(amount),
+        Some(_) => Err(NumericError::Inexact),
+        None => Err(out_of_range(whole.sign() == num_bigint::Sign::Minus)),
+    }
+}
+
+/// A natural as a position in a sequence, if it can be one.
+pub fn to_usize(natural: &Natural) -> Option<usize> {
+    to_u64(natural).and_then(|n| usize::try_from(n).ok())
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn to_u64(natural: &Natural) -> Option<u64> {
+    Some(*natural)
+}
+
+#[cfg(feature = "bigint")]
+pub fn to_u64(natural: &Natural) -> Option<u64> {
+    u64::try_from(natural).ok()
+}
+
+#[cfg(not(feature = "bigint"))]
+pub fn to_i64(whole: &Whole) -> Option<i64> {
+    Some(*whole)
+}
+
+#[cfg(feature = "bigint")]
+pub fn to_i64(whole: &Whole) -> Option<i64> {
+    i64::try_from(whole).ok()
+}
+
+/// The bytes a natural takes up on the heap.
+#[cfg(not(feature = "bigint"))]
+pub fn natural_size(_: &Natural) -> usize {
+    0
+}
+
+/// The bytes a whole takes up on the heap.
+#[cfg(not(feature = "bigint"))]
+pub fn whole_size(_: &Whole) -> usize {
+    0
+}
+
+#[cfg(feature = "bigint")]
+pub fn natural_size(natural: &Natural) -> usize {
+    usize::try_from(natural.bits().div_ceil(8)).unwrap_or(usize::MAX)
+}
+
+#[cfg(feature = "bigint")]
+pub fn whole_size(whole: &Whole) -> usize {
+    usize::try_from(whole.bits().div_ceil(8)).unwrap_or(usize::MAX)
+}
+
+/// An upper bound on the bytes the result of an operation takes up on the heap, given the sizes
+/// of its operands.
+pub fn result_size(operation: Operation, a: usize, b: usize) -> usize {
+    match operation {
+        Operation::Add | Operation::Subtract => a.max(b).saturating_add(1),
+        Operation::Multiply => a.saturating_add(b),
+        Operation::Divide | Operation::Remainder => a,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn natural(n: u8) -> Natural {
+        Natural::from(n)
+    }
+
+    fn whole(n: i8) -> Whole {
+        Whole::from(n)
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        use NumericError::*;
+
+        assert_eq!(
+            naturals(Operation::Subtract, &natural(1), &natural(2)),
+            Err(Underflow)
+        );
+        for operation in [Operation::Divide, Operation::Remainder] {
+            assert_eq!(
+                naturals(operation, &natural(1), &natural(0)),
+                Err(DivisionByZero)
+            );
+            assert_eq!(
+                wholes(operation, &whole(-1), &whole(0)),
+                Err(DivisionByZero)
+            );
+            assert_eq!(amounts(operation, 1.0, 0.0), Err(DivisionByZero));
+        }
+        assert_eq!(
+            wholes(Operation::Subtract, &whole(1), &whole(2)),
+            Ok(whole(-1))
+        );
+        assert_eq!(amounts(Operation::Multiply, f64::MAX, 2.0), Err(Overflow));
+        assert_eq!(amounts(Operation::Multiply, f64::MAX, -2.0), Err(Underflow));
+    }
+
+    #[test]
+    fn conversion_errors() {
+        use NumericError::*;
+
+        assert_eq!(natural_from_whole(&whole(-1)), Err(Underflow));
+        assert_eq!(natural_from_whole(&whole(3)), Ok(natural(3)));
+        assert_eq!(natural_from_amount(2.5), Err(Inexact));
+        assert_eq!(natural_from_amount(-1.0), Err(Underflow));
+        assert_eq!(natural_from_amount(3.0), Ok(natural(3)));
+        assert_eq!(whole_from_amount(-2.5), Err(Inexact));
+        assert_eq!(whole_from_amount(f64::NAN), Err(Inexact));
+        assert_eq!(whole_from_amount(f64::INFINITY), Err(Inexact));
+        assert_eq!(amount_from_whole(&whole(-2)), Ok(-2.0));
+        let unrepresentable = (natural(1) << 53u32) + natural(1);
+        assert_eq!(amount_from_natural(&unrepresentable), Err(Inexact));
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    #[test]
+    fn fixed_width_overflow() {
+        use NumericError::*;
+
+        assert_eq!(
+            naturals(Operation::Add, &Natural::MAX, &natural(1)),
+            Err(Overflow)
+        );
+        assert_eq!(
+            naturals(Operation::Multiply, &Natural::MAX, &natural(2)),
+            Err(Overflow)
+        );
+        assert_eq!(
+            wholes(Operation::Subtract, &Whole::MIN, &whole(1)),
+            Err(Underflow)
+        );
+        assert_eq!(
+            wholes(Operation::Divide, &Whole::MIN, &whole(-1)),
+            Err(Overflow)
+        );
+        assert_eq!(whole_from_natural(&Natural::MAX), Err(Overflow));
+        assert_eq!(natural_from_amount(2f64.powi(64)), Err(Overflow));
+        assert_eq!(whole_from_amount(-(2f64.powi(64))), Err(Underflow));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn big_numbers_do_not_overflow() {
+        use NumericError::*;
+
+        let large = natural(1) << 64u32;
+        let product = naturals(Operation::Multiply, &large, &large).unwrap();
+        assert_eq!(product, natural(1) << 128u32);
+        assert_eq!(
+            whole_from_natural(&product),
+            Ok(Whole::from(product.clone()))
+        );
+        assert_eq!(natural_from_amount(2f64.powi(64)), Ok(large));
+        let huge = natural(1) << 2000u32;
+        assert_eq!(amount_from_natural(&huge), Err(Overflow));
+        assert_eq!(amount_from_whole(&-Whole::from(huge)), Err(Underflow));
+    }
+}
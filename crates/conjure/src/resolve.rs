@@ -0,0 +1,437 @@
+//! Name resolution: links every [`ast::Value::Symbol`] and named [`ast::Type::Symbol`] to the
+//! [`ast::Manifest`] that declares it.
+//!
+//! Bindings are visible to the bindings and actions that follow them in the same boundary or
+//! spell, and to everything nested in those. Components are visible throughout their charm or
+//! spell. A binding never sees itself, unless it binds a charm, spell or type to a single symbol,
+//! which may then recurse. The symbols bound by a presence omen or a pattern arm are visible in the
+//! value of its arm only, and the counter of a repetition in its actions only. Every symbol in a
+//! manifest's pattern is declared alike.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{self, Span, Symbol},
+    builtins::Builtin,
+    diagnostic::Diagnostic,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Definition {
+    /// A binding, identified by the span of its symbol.
+    Binding(Span),
+    /// A charm or spell component, identified by the span of its symbol.
+    Component(Span),
+    Builtin(Builtin),
+    /// A charm or spell provided by the host embedding the interpreter.
+    Native,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub symbol: Symbol,
+    pub definition: Definition,
+}
+
+/// A region of the source, together with the symbols declared directly in it.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub extent: Span,
+    pub declarations: Vec<(Symbol, Definition)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    pub references: Vec<Reference>,
+    pub scopes: Vec<Scope>,
+}
+
+impl Resolution {
+    /// The definition referred to by the symbol at `offset`, if any.
+    pub fn definition_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|r| r.symbol.span.contains(offset))
+    }
+
+    /// The symbols that may be referred to at `offset`, innermost first. Builtins are not
+    /// included.
+    pub fn visible_at(&self, offset: usize) -> Vec<&(Symbol, Definition)> {
+        let mut scopes: Vec<_> = self
+            .scopes
+            .iter()
+            .filter(|scope| scope.extent.contains(offset))
+            .rev()
+            .collect();
+        scopes.sort_by_key(|scope| scope.extent.end - scope.extent.start);
+        scopes
+            .into_iter()
+            .flat_map(|scope| scope.declarations.iter().rev())
+            .filter(|(symbol, definition)| {
+                matches!(definition, Definition::Component(_)) || symbol.span.end <= offset
+            })
+            .collect()
+    }
+}
+
+pub fn resolve(conjuration: &ast::Conjuration) -> (Resolution, Vec<Diagnostic>) {
+    let mut resolver = Resolver::new();
+    for binding in &conjuration.bindings {
+        resolver.binding(binding);
+    }
+    let diagnostics = resolver.take_diagnostics();
+    (resolver.into_resolution(), diagnostics)
+}
+
+/// Resolves symbols incrementally, for hosts that receive a program piece by piece.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    /// The symbols of every open scope, with the index of the scope in the resolution.
+    scopes: Vec<(HashMap<String, Definition>, usize)>,
+    resolution: Resolution,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        let mut resolver = Self {
+            scopes: Vec::new(),
+            resolution: Resolution::default(),
+            diagnostics: Vec::new(),
+        };
+        resolver.push_scope();
+        resolver
+    }
+
+    /// Opens a new scope that lasts until the resolver is dropped. Symbols declared after this may
+    /// shadow earlier ones instead of clashing with them.
+    pub fn push_scope(&mut self) {
+        self.open_scope(Span::new(0, usize::MAX));
+    }
+
+    fn open_scope(&mut self, extent: Span) {
+        self.scopes
+            .push((HashMap::new(), self.resolution.scopes.len()));
+        self.resolution.scopes.push(Scope {
+            extent,
+            declarations: Vec::new(),
+        });
+    }
+
+    /// Declares a charm or spell provided by the host, in the current scope.
+    pub fn declare_native(&mut self, name: &str) {
+        self.declare(
+            &Symbol::new(name.to_string(), Span::default()),
+            Definition::Native,
+        );
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn into_resolution(self) -> Resolution {
+        self.resolution
+    }
+
+    fn declare(&mut self, symbol: &Symbol, definition: Definition) {
+        let (scope, index) = self.scopes.last_mut().expect("No scope to declare in");
+        let index = *index;
+        if scope.insert(symbol.name.clone(), definition).is_some() {
+            self.diagnostics.push(Diagnostic::error(
+                format!("`{symbol}` is already bound in this scope"),
+                symbol.span,
+            ));
+        }
+        self.resolution.scopes[index]
+            .declarations
+            .push((symbol.clone(), definition));
+    }
+
+    fn reference(&mut self, symbol: &Symbol) {
+        let definition = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|(scope, _)| scope.get(symbol.as_str()).copied())
+            .or_else(|| Builtin::from_name(symbol).map(Definition::Builtin));
+        match definition {
+            Some(definition) => self.resolution.references.push(Reference {
+                symbol: symbol.clone(),
+                definition,
+            }),
+            None => self.diagnostics.push(Diagnostic::error(
+                format!("`{symbol}` is not bound"),
+                symbol.span,
+            )),
+        }
+    }
+
+    fn scoped(&mut self, extent: Span, f: impl FnOnce(&mut Self)) {
+        self.open_scope(extent);
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn ty(&mut self, ty: &ast::Type) {
+        ty.symbols().into_iter().for_each(|s| self.reference(s));
+    }
+
+    fn components(&mut self, components: &[ast::Manifest]) {
+        for ty in components.iter().flat_map(ast::Manifest::types) {
+            self.ty(ty);
+        }
+        for symbol in components.iter().flat_map(ast::Manifest::symbols) {
+            self.declare(symbol, Definition::Component(symbol.span));
+        }
+    }
+
+    fn manifest(&mut self, manifest: &ast::Manifest) {
+        manifest.types().into_iter().for_each(|t| self.ty(t));
+        for symbol in manifest.symbols() {
+            self.declare(symbol, Definition::Binding(symbol.span));
+        }
+    }
+
+    pub fn binding(&mut self, binding: &ast::Binding) {
+        let recursive = is_closure(&binding.value) || matches!(binding.value, ast::Value::Type(_));
+        if binding.manifest.symbol().is_some() && recursive {
+            self.manifest(&binding.manifest);
+            self.value(&binding.value);
+        } else {
+            self.value(&binding.value);
+            self.manifest(&binding.manifest);
+        }
+    }
+
+    fn value(&mut self, value: &ast::Value) {
+        match value {
+            ast::Value::Element(_) => (),
+            ast::Value::Type(ty) => self.ty(ty),
+            ast::Value::Symbol(symbol) => self.reference(symbol),
+            ast::Value::Boundary(boundary) => self.scoped(boundary.span, |r| r.boundary(boundary)),
+            ast::Value::Conjunction(values) => values.iter().for_each(|v| self.value(v)),
+            ast::Value::Record(fields) => fields.iter().for_each(|f| self.value(&f.value)),
+            ast::Value::Projection(projection) => self.value(&projection.record),
+            ast::Value::Sequence(sequence) => sequence.elements.iter().for_each(|v| self.value(v)),
+            ast::Value::Variant(variant) => {
+                if let Some(payload) = &variant.payload {
+                    self.value(payload);
+                }
+            }
+            ast::Value::Interpolation(interpolation) => {
+                interpolation.values().for_each(|v| self.value(v))
+            }
+            ast::Value::Charm(charm) => self.scoped(charm.boundary.span, |r| {
+                r.components(&charm.components);
+                r.scoped(charm.boundary.span, |r| r.boundary(&charm.boundary));
+            }),
+            ast::Value::Invocation(invocation) => {
+                self.value(&invocation.charm);
+                invocation.components.iter().for_each(|v| self.value(v));
+            }
+            ast::Value::Spell(spell) => self.scoped(spell.span, |r| {
+                r.components(&spell.components);
+                r.ty(&spell.ty);
+                r.scoped(spell.span, |r| {
+                    spell.actions.iter().for_each(|a| r.action(a))
+                });
+            }),
+            ast::Value::Divination(divination) => {
+                if let Some(subject) = &divination.subject {
+                    self.value(subject);
+                }
+                divination.arms.iter().for_each(|arm| self.arm(arm))
+            }
+        }
+    }
+
+    fn arm(&mut self, arm: &ast::Arm) {
+        match &arm.omen {
+            ast::Omen::Truth(value) => self.value(value),
+            ast::Omen::Presence(presence) => {
+                self.value(&presence.value);
+                return self.scoped(arm.span, |r| {
+                    r.manifest(&presence.manifest);
+                    r.value(&arm.value);
+                });
+            }
+            ast::Omen::Pattern(manifest) => {
+                return self.scoped(arm.span, |r| {
+                    r.manifest(manifest);
+                    r.value(&arm.value);
+                });
+            }
+            ast::Omen::Otherwise => (),
+        }
+        self.value(&arm.value);
+    }
+
+    fn boundary(&mut self, boundary: &ast::Boundary) {
+        self.ty(&boundary.ty);
+        boundary.bindings.iter().for_each(|b| self.binding(b));
+        self.value(&boundary.value);
+    }
+
+    pub fn action(&mut self, action: &ast::Action) {
+        match action {
+            ast::Action::Value(value) => self.value(value),
+            ast::Action::Cast(cast) => self.cast(cast),
+            ast::Action::Binding(binding) => self.binding(binding),
+            ast::Action::Channel(channel) => {
+                self.cast(&channel.cast);
+                self.manifest(&channel.manifest);
+            }
+            ast::Action::Repetition(repetition) => {
+                self.value(&repetition.count);
+                self.scoped(repetition.span, |r| {
+                    if let Some(counter) = &repetition.counter {
+                        r.manifest(counter);
+                    }
+                    repetition.actions.iter().for_each(|a| r.action(a));
+                });
+            }
+        }
+    }
+
+    fn cast(&mut self, cast: &ast::Cast) {
+        self.action(&cast.spell);
+        cast.components.iter().for_each(|a| self.action(a));
+        if let Some(recovery) = &cast.recovery {
+            self.scoped(recovery.span, |r| {
+                if let Some(failure) = &recovery.failure {
+                    r.manifest(failure);
+                }
+                recovery.actions.iter().for_each(|a| r.action(a));
+            });
+        }
+    }
+}
+
+fn is_closure(value: &ast::Value) -> bool {
+    matches!(value, ast::Value::Charm(_) | ast::Value::Spell(_))
+}
+
+/// Whether a binding refers to itself. This needs no resolution, so it also works on ASTs that were
+/// not parsed from source.
+pub fn is_recursive(binding: &ast::Binding) -> bool {
+    let symbol = binding.manifest.symbol();
+    is_closure(&binding.value) && symbol.is_some_and(|s| mentions(&binding.value, s))
+}
+
+/// Whether `value` refers to `name` without it being shadowed.
+fn mentions(value: &ast::Value, name: &str) -> bool {
+    match value {
+        ast::Value::Element(_) | ast::Value::Type(_) => false,
+        ast::Value::Symbol(symbol) => symbol.as_str() == name,
+        ast::Value::Boundary(boundary) => boundary_mentions(boundary, name),
+        ast::Value::Conjunction(values) => values.iter().any(|v| mentions(v, name)),
+        ast::Value::Record(fields) => fields.iter().any(|f| mentions(&f.value, name)),
+        ast::Value::Projection(projection) => mentions(&projection.record, name),
+        ast::Value::Sequence(sequence) => sequence.elements.iter().any(|v| mentions(v, name)),
+        ast::Value::Variant(variant) => variant
+            .payload
+            .as_ref()
+            .is_some_and(|payload| mentions(payload, name)),
+        ast::Value::Interpolation(interpolation) => {
+            interpolation.values().any(|v| mentions(v, name))
+        }
+        ast::Value::Charm(charm) => {
+            !declares(&charm.components, name) && boundary_mentions(&charm.boundary, name)
+        }
+        ast::Value::Invocation(invocation) => {
+            mentions(&invocation.charm, name)
+                || invocation.components.iter().any(|v| mentions(v, name))
+        }
+        ast::Value::Spell(spell) => {
+            !declares(&spell.components, name) && actions_mention(&spell.actions, name)
+        }
+        ast::Value::Divination(divination) => {
+            let subject = divination.subject.as_ref();
+            subject.is_some_and(|subject| mentions(subject, name))
+                || divination.arms.iter().any(|arm| match &arm.omen {
+                    ast::Omen::Truth(omen) => mentions(omen, name) || mentions(&arm.value, name),
+                    ast::Omen::Presence(presence) => {
+                        mentions(&presence.value, name)
+                            || (!binds(&presence.manifest, name) && mentions(&arm.value, name))
+                    }
+                    ast::Omen::Pattern(manifest) => {
+                        !binds(manifest, name) && mentions(&arm.value, name)
+                    }
+                    ast::Omen::Otherwise => mentions(&arm.value, name),
+                })
+        }
+    }
+}
+
+fn declares(manifests: &[ast::Manifest], name: &str) -> bool {
+    manifests.iter().any(|m| binds(m, name))
+}
+
+fn binds(manifest: &ast::Manifest, name: &str) -> bool {
+    manifest.symbols().iter().any(|s| s.as_str() == name)
+}
+
+/// Whether a binding refers to `name`, and whether it shadows it for what follows.
+fn binding_mentions(binding: &ast::Binding, name: &str) -> (bool, bool) {
+    let shadows = binds(&binding.manifest, name);
+    let recursive = shadows && binding.manifest.symbol().is_some() && is_closure(&binding.value);
+    let mentioned = !recursive && mentions(&binding.value, name);
+    (mentioned, shadows)
+}
+
+fn boundary_mentions(boundary: &ast::Boundary, name: &str) -> bool {
+    for binding in &boundary.bindings {
+        match binding_mentions(binding, name) {
+            (true, _) => return true,
+            (false, true) => return false,
+            (false, false) => (),
+        }
+    }
+    mentions(&boundary.value, name)
+}
+
+fn actions_mention(actions: &[ast::Action], name: &str) -> bool {
+    for action in actions {
+        let (mentioned, shadows) = match action {
+            ast::Action::Value(value) => (mentions(value, name), false),
+            ast::Action::Cast(cast) => (cast_mentions(cast, name), false),
+            ast::Action::Binding(binding) => binding_mentions(binding, name),
+            ast::Action::Channel(channel) => (
+                cast_mentions(&channel.cast, name),
+                binds(&channel.manifest, name),
+            ),
+            ast::Action::Repetition(repetition) => {
+                let counter = repetition.counter.as_slice();
+                let mentioned = mentions(&repetition.count, name)
+                    || (!declares(counter, name) && actions_mention(&repetition.actions, name));
+                (mentioned, false)
+            }
+        };
+        if mentioned {
+            return true;
+        }
+        if shadows {
+            return false;
+        }
+    }
+    false
+}
+
+fn cast_mentions(cast: &ast::Cast, name: &str) -> bool {
+    actions_mention(std::slice::from_ref(&cast.spell), name)
+        || cast
+            .components
+            .iter()
+            .any(|a| actions_mention(std::slice::from_ref(a), name))
+        || cast.recovery.as_ref().is_some_and(|recovery| {
+            let failure = recovery.failure.as_slice();
+            !declares(failure, name) && actions_mention(&recovery.actions, name)
+        })
+}
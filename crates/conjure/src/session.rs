@@ -0,0 +1,147 @@
+//! Running a program one action at a time, as entered in an interactive session.
+
+use std::fmt;
+
+use crate::{
+    ast::{self, Capability, Type},
+    diagnostic::Diagnostic,
+    interpret::{Host, Interpreter, Limits, RuntimeError, Value},
+    resolve::Resolver,
+    syntax,
+    typeck::Checker,
+};
+
+#[derive(Debug, Clone)]
+pub enum SessionError {
+    /// The entry did not parse or did not pass semantic analysis.
+    Rejected(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Self::Runtime(err) => write!(f, "error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// An action that was entered and checked.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub action: ast::Action,
+    /// The type of the action's product. Bindings and channels produce nil.
+    pub ty: Type,
+    /// The symbols bound by a binding or channel, with their types.
+    pub bound: Vec<(ast::Symbol, Type)>,
+}
+
+/// A program that grows one action at a time. Bindings persist across entries; binding a symbol
+/// again shadows the earlier binding.
+pub struct Session<'h> {
+    resolver: Resolver,
+    checker: Checker,
+    interpreter: Interpreter<'h>,
+}
+
+impl<'h> Session<'h> {
+    pub fn new(host: &'h mut dyn Host) -> Self {
+        Self {
+            resolver: Resolver::new(),
+            checker: Checker::default(),
+            interpreter: Interpreter::new(host),
+        }
+    }
+
+    /// Allows the spells cast in the session to use the given capabilities. A new session grants
+    /// none.
+    pub fn grant(&mut self, capabilities: impl IntoIterator<Item = Capability>) {
+        self.interpreter.grant(capabilities);
+    }
+
+    /// Bounds what the entries performed from now on may take, together with those performed so
+    /// far.
+    pub fn limit(&mut self, limits: Limits) {
+        self.interpreter.limit(limits);
+    }
+
+    /// Parses and checks an entry without performing it.
+    pub fn check(&self, source: &str) -> Result<Entry, SessionError> {
+        self.analyze(source).map(|(entry, _, _)| entry)
+    }
+
+    /// Parses, checks and performs an entry. Returns the entry with its product.
+    pub fn enter(&mut self, source: &str) -> Result<(Entry, Value), SessionError> {
+        let (entry, resolver, checker) = self.analyze(source)?;
+        let product = self
+            .interpreter
+            .perform(&entry.action)
+            .map_err(SessionError::Runtime)?;
+        self.resolver = resolver;
+        self.checker = checker;
+        Ok((entry, product))
+    }
+
+    /// The value currently bound to a symbol.
+    pub fn lookup(&self, symbol: &str) -> Option<&Value> {
+        self.interpreter.globals().lookup(symbol)
+    }
+
+    fn analyze(&self, source: &str) -> Result<(Entry, Resolver, Checker), SessionError> {
+        let action = syntax::parse_action(source).map_err(|d| SessionError::Rejected(vec![d]))?;
+
+        let mut resolver = self.resolver.clone();
+        resolver.push_scope();
+        resolver.action(&action);
+        let mut checker = self.checker.clone();
+        let ty = checker.action(&action);
+
+        let mut diagnostics = resolver.take_diagnostics();
+        diagnostics.extend(checker.take_diagnostics());
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(SessionError::Rejected(diagnostics));
+        }
+
+        let bound = match &action {
+            ast::Action::Binding(ast::Binding { manifest, .. })
+            | ast::Action::Channel(ast::Channel { manifest, .. }) => manifest
+                .symbols()
+                .into_iter()
+                .map(|symbol| {
+                    let ty = checker.types().symbols.get(&symbol.span).cloned();
+                    (symbol.clone(), ty.unwrap_or(Type::Inferred))
+                })
+                .collect(),
+            ast::Action::Value(_) | ast::Action::Cast(_) | ast::Action::Repetition(_) => Vec::new(),
+        };
+        Ok((Entry { action, ty, bound }, resolver, checker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpret::StdHost;
+
+    #[test]
+    fn named_types_of_different_entries_stay_apart() {
+        let mut host = StdHost;
+        let mut session = Session::new(&mut host);
+        session.enter("A = type natural;").unwrap();
+        session.enter("B = type phrase;").unwrap();
+        session.enter("x: A = 5;").unwrap();
+        assert!(session.check(r#"y: B = "five";"#).is_ok());
+        assert!(session.check("z: B = 5;").is_err());
+    }
+}
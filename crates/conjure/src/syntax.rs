@@ -0,0 +1,156 @@
+//! The textual form of Conjure programs.
+//!
+//! A source consists of bindings, each terminated by a semicolon:
+//!
+//! ```text
+//! // Greets the world.
+//! greeting: phrase = "Hello World!";
+//! * = spell() -> nil {
+//!     utter!(greeting);
+//! };
+//! ```
+//!
+//! Charms are written `charm(x: natural) -> natural { ... }` and invoked as `f(x)`; spells are
+//! written `spell(...) -> type { action; ... }` and cast as `s!(x)`. A bound charm or spell may
+//! refer to itself, and a spell may repeat actions with `repeat count as i { action; ... }`.
+//!
+//! A spell that may fail is declared with `fails`, as in `spell(x: phrase) -> natural fails`,
+//! and fails by casting `fail!("reason")`. Only such spells may cast spells that may fail, unless
+//! the cast is recovered: `recover parse!(x) as reason { utter!(reason); 0; }` performs the
+//! actions in braces if the cast fails, with `reason` bound to its phrase. Charms cannot fail.
+//!
+//! Spells reach the outside world through builtins that need capabilities: `utter` and `read`
+//! need `console`, `load` and `store` need `filesystem`, `now` needs `clock` and `random` needs
+//! `randomness`. A spell type lists the capabilities of the spell, as in
+//! `spell(phrase) -> nil uses [console]`. A spell value may declare them the same way, which limits
+//! what it may cast; otherwise they are inferred from its casts.
+//!
+//! Records name their values: `(name = "Merlin", age = 312)` has the type
+//! `(name: phrase, age: natural)`, and `wizard.name` projects a field out of it.
+//!
+//! Sequences hold any number of values of one type: `[1, 2, 3]` has the type `[natural]`. The
+//! builtins `length`, `index`, `map`, `filter` and `fold` work on sequences of any type.
+//!
+//! Binding a type names it: after `Shape = type #circle(amount) | #point;`, `Shape` may be used
+//! wherever a type is expected. A named type may refer to itself, as in
+//! `List = type #empty | #link((natural, List));`.
+//!
+//! Charms and spells may be generic over type parameters, named in brackets after the keyword:
+//! `charm[T](x: T) -> T { x }` works on values of any type. Types that are left out are inferred,
+//! so `charm(x) { x }` is just as generic.
+//!
+//! Anything that binds a value may take it apart with a pattern: `(a, (b, _)) = pair;` binds the
+//! parts of a conjunction, `_` ignores a part, and `x?` matches an optional value that is present.
+//! Patterns with `?` can fail to match, so they are only allowed in divinations.
+//!
+//! A divination chooses between values. Each arm is guarded by a truth, by the presence of an
+//! optional value, which is then bound for the arm, or by `_`, which always holds:
+//!
+//! ```text
+//! divine {
+//!     less(n, 10) => "small",
+//!     name = read_name(n) => name,
+//!     _ => "large",
+//! }
+//! ```
+//!
+//! Phrases may embed values in braces: `"{n} apples at {price:.2} each"`. An embedded value is
+//! written out as `describe` would, or as `format` would with the spec after the colon, which may
+//! ask for a sign (`+`), zero padding (`0`), a width and, for amounts, a precision (`.2`). Write
+//! `\{` and `\}` for literal braces.
+//!
+//! Tagged values carry a tag and maybe a payload: `#circle(2.0)` has the type `#circle(amount)`,
+//! which fits the union `#circle(amount) | #point`. A divination with a subject matches it against
+//! the pattern of each arm, and must cover every variant unless it ends with `_`:
+//!
+//! ```text
+//! divine shape {
+//!     #circle(r) => multiply(r, r),
+//!     #point => 0.0,
+//! }
+//! ```
+
+mod format;
+mod lexer;
+mod parser;
+mod print;
+
+use crate::{
+    ast::{Action, Conjuration},
+    diagnostic::Diagnostic,
+};
+
+pub use format::format;
+pub use parser::{format_spec, simple_type, MAX_FORMAT_SIZE};
+
+/// How deeply values, types, patterns, actions and phrases may nest in each other. Everything
+/// that works on a parsed program walks it recursively, so this keeps them all within the stack.
+pub const MAX_DEPTH: usize = 256;
+
+/// Parses a complete source into a [`Conjuration`].
+pub fn parse(source: &str) -> Result<Conjuration, Diagnostic> {
+    let mut parser = parser::Parser::new(lexer::tokenize(source)?, source.len());
+    parser.conjuration()
+}
+
+/// Parses a single action, such as a line entered interactively. The terminating semicolon is
+/// optional.
+pub fn parse_action(source: &str) -> Result<Action, Diagnostic> {
+    let mut parser = parser::Parser::new(lexer::tokenize(source)?, source.len());
+    let action = parser.action()?;
+    parser.finish_entry()?;
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(open: &str, inner: &str, close: &str, depth: usize) -> String {
+        format!("x = {}{inner}{};", open.repeat(depth), close.repeat(depth))
+    }
+
+    #[test]
+    fn errors_point_at_what_is_wrong() {
+        let cases = [
+            ("x = (1, 2;", "expected `)`, found `;`", 9),
+            ("x = 1 2;", "expected `;`, found number", 6),
+            ("x = \"abc;", "unterminated phrase", 4),
+            ("x = \"{1:q}\";", "malformed format spec `q`", 8),
+            (
+                "x = 18446744073709551616;",
+                "number `18446744073709551616` is out of range",
+                4,
+            ),
+        ];
+        for (source, message, start) in cases {
+            let error = parse(source).unwrap_err();
+            assert_eq!(
+                (error.message.as_str(), error.span.start),
+                (message, start),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn entries_need_no_semicolon() {
+        let conjuration = parse("// A comment.\na = 1;\nb: phrase = \"{a:03}\";").unwrap();
+        assert_eq!(conjuration.bindings.len(), 2);
+        assert!(parse_action("a = 1").is_ok());
+        assert!(parse_action("utter!(\"hi\");").is_ok());
+        assert!(parse_action("a = 1; b = 2;").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_reported() {
+        for (open, inner, close) in [("(", "1", ")"), ("\"{", "1", "}\""), ("[", "1", "]")] {
+            let source = nested(open, inner, close, 200_000);
+            let error = parse(&source).unwrap_err();
+            assert!(error.message.contains("levels deep"), "{}", error.message);
+        }
+        let chain = format!("x = f{};", "(1)".repeat(200_000));
+        assert!(parse(&chain).is_err());
+        assert!(parse(&nested("(", "1", ")", MAX_DEPTH - 10)).is_ok());
+    }
+}
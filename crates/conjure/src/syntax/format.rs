@@ -0,0 +1,275 @@
+use crate::diagnostic::Diagnostic;
+
+use super::lexer::{self, Token, TokenKind};
+
+const INDENT: &str = "    ";
+
+/// Formats a source in the canonical style: one binding or action per line, blocks indented by
+/// four spaces, and single spaces between tokens except around brackets and separators.
+///
+/// Formatting works on the token stream rather than the AST, so comments and single blank lines
+/// between bindings and actions are preserved. The source must parse.
+pub fn format(source: &str) -> Result<String, Diagnostic> {
+    super::parse(source)?;
+    let tokens = lexer::tokenize(source)?;
+    let mut formatter = Formatter {
+        source,
+        output: String::new(),
+        depth: 0,
+        line_start: true,
+        blocks: Vec::new(),
+        divinations: Vec::new(),
+        parens: 0,
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i]);
+        formatter.token(previous, token, tokens.get(i + 1));
+    }
+    let mut output = formatter.output.trim_end().to_string();
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    output: String,
+    depth: usize,
+    line_start: bool,
+    /// For every open brace, whether it holds the arms of a divination, and the number of
+    /// parentheses open outside of it.
+    blocks: Vec<(bool, usize)>,
+    /// For every `divine` keyword whose arms have not been opened yet, the number of parentheses
+    /// open outside of it. The arms open with the first brace outside of the subject.
+    divinations: Vec<usize>,
+    parens: usize,
+}
+
+impl Formatter<'_> {
+    fn newline(&mut self) {
+        if !self.line_start {
+            let trimmed = self.output.trim_end_matches(' ').len();
+            self.output.truncate(trimmed);
+            self.output.push('\n');
+            self.line_start = true;
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            for _ in 0..self.depth {
+                self.output.push_str(INDENT);
+            }
+            self.line_start = false;
+        }
+        self.output.push_str(text);
+    }
+
+    fn lines_between(&self, previous: &Token, token: &Token) -> usize {
+        self.source[previous.span.end..token.span.start]
+            .matches('\n')
+            .count()
+    }
+
+    /// Whether a comma at the current position separates the arms of a divination.
+    fn separates_arms(&self) -> bool {
+        self.blocks
+            .last()
+            .is_some_and(|&(arms, parens)| arms && parens == self.parens)
+    }
+
+    fn token(&mut self, previous: Option<&Token>, token: &Token, next: Option<&Token>) {
+        let after_line_break = previous.is_none_or(|p| {
+            matches!(
+                p.kind,
+                TokenKind::Semicolon | TokenKind::LeftBrace | TokenKind::Comment(_)
+            )
+        });
+        match token.kind {
+            TokenKind::LeftParen => self.parens += 1,
+            TokenKind::RightParen => self.parens -= 1,
+            TokenKind::Divine => self.divinations.push(self.parens),
+            TokenKind::LeftBrace => {
+                let arms = self.divinations.last() == Some(&self.parens);
+                if arms {
+                    self.divinations.pop();
+                }
+                self.blocks.push((arms, self.parens));
+            }
+            TokenKind::RightBrace => {
+                self.blocks.pop();
+            }
+            _ => (),
+        }
+        if let Some(previous) = previous {
+            let opens_block = previous.kind == TokenKind::LeftBrace;
+            if after_line_break
+                && !opens_block
+                && token.kind != TokenKind::RightBrace
+                && self.lines_between(previous, token) > 1
+            {
+                self.blank_line();
+            }
+        }
+
+        match &token.kind {
+            TokenKind::Comment(text) => {
+                let trailing = previous.is_some_and(|p| self.lines_between(p, token) == 0);
+                if trailing {
+                    self.write(" ");
+                } else {
+                    self.newline();
+                }
+                self.write(&format!("//{text}"));
+                self.newline();
+                return;
+            }
+            TokenKind::RightBrace => {
+                if previous.is_some_and(|p| p.kind != TokenKind::LeftBrace) {
+                    self.newline();
+                    self.depth -= 1;
+                }
+                self.write("}");
+                return;
+            }
+            _ => (),
+        }
+
+        if !self.line_start && previous.is_some_and(|p| space_between(&p.kind, &token.kind)) {
+            self.write(" ");
+        }
+        match &token.kind {
+            TokenKind::Identifier(name) => self.write(name),
+            TokenKind::Natural(_)
+            | TokenKind::Whole(_)
+            | TokenKind::Amount(_)
+            | TokenKind::Phrase(_)
+            | TokenKind::Interpolation(_) => {
+                self.write(&self.source[token.span.start..token.span.end])
+            }
+            kind => self.write(kind.text()),
+        }
+        let trailing_comment = next.is_some_and(|n| {
+            matches!(n.kind, TokenKind::Comment(_)) && self.lines_between(token, n) == 0
+        });
+        match token.kind {
+            TokenKind::Semicolon if !trailing_comment => self.newline(),
+            TokenKind::Comma if self.separates_arms() && !trailing_comment => self.newline(),
+            TokenKind::LeftBrace if next.is_some_and(|n| n.kind != TokenKind::RightBrace) => {
+                self.depth += 1;
+                if !trailing_comment {
+                    self.newline();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn space_between(previous: &TokenKind, token: &TokenKind) -> bool {
+    use TokenKind::*;
+
+    !matches!(
+        (previous, token),
+        (LeftParen | LeftBracket | Bang | Dot | Hash, _)
+            | (
+                _,
+                RightParen | RightBracket | Comma | Semicolon | Question | Colon | Bang | Dot
+            )
+            | (
+                Identifier(_) | Star | RightParen | RightBracket | RightBrace | Charm | Spell,
+                LeftParen
+            )
+            | (Charm | Spell, LeftBracket)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Fragment;
+
+    const MESSY: &str = r#"// Greets.
+greeting:phrase="Hello {name:+08.2} and \{braces\}";   // trailing
+
+
+Shape=type #dot|#square(natural);
+area=charm(s:Shape,)->natural{divine s{#dot=>0,#square(n)=>multiply(n,n),}};
+* = spell() -> nil uses [console] {
+  utter!(greeting); // say it
+    repeat 3 as i { utter!("{i:03}"); };
+  x = recover parse!("1") as reason { 0; };
+  r = (name = "Merlin", age = 312,);
+};
+"#;
+
+    const FORMATTED: &str = r#"// Greets.
+greeting: phrase = "Hello {name:+08.2} and \{braces\}"; // trailing
+
+Shape = type #dot | #square(natural);
+area = charm(s: Shape,) -> natural {
+    divine s {
+        #dot => 0,
+        #square(n) => multiply(n, n),
+    }
+};
+* = spell() -> nil uses [console] {
+    utter!(greeting); // say it
+    repeat 3 as i {
+        utter!("{i:03}");
+    };
+    x = recover parse!("1") as reason {
+        0;
+    };
+    r = (name = "Merlin", age = 312,);
+};
+"#;
+
+    /// The tokens of a source without their spans, with embedded values flattened.
+    fn kinds(source: &str) -> Vec<String> {
+        fn flatten(tokens: &[Token], kinds: &mut Vec<String>) {
+            for token in tokens {
+                match &token.kind {
+                    TokenKind::Interpolation(fragments) => {
+                        for fragment in fragments {
+                            match fragment {
+                                Fragment::Text(text) => kinds.push(format!("{text:?}")),
+                                Fragment::Embed { tokens, spec, .. } => {
+                                    flatten(tokens, kinds);
+                                    kinds.push(format!("{:?}", spec.as_ref().map(|(s, _)| s)));
+                                }
+                            }
+                        }
+                    }
+                    kind => kinds.push(format!("{kind:?}")),
+                }
+            }
+        }
+
+        let mut kinds = Vec::new();
+        flatten(&lexer::tokenize(source).unwrap(), &mut kinds);
+        kinds
+    }
+
+    #[test]
+    fn formatting_only_changes_layout() {
+        let formatted = format(MESSY).unwrap();
+        assert_eq!(formatted, FORMATTED);
+        assert_eq!(kinds(&formatted), kinds(MESSY));
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn sources_that_do_not_parse_are_not_formatted() {
+        assert!(format("x = (1, 2;").is_err());
+        assert_eq!(format("").unwrap(), "");
+    }
+}
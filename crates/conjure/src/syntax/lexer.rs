@@ -0,0 +1,394 @@
+use crate::{
+    ast::Span,
+    diagnostic::Diagnostic,
+    interpret::{STACK_RED_ZONE, STACK_SEGMENT_SIZE},
+};
+
+use super::MAX_DEPTH;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    Natural(u64),
+    Whole(i64),
+    Amount(f64),
+    Phrase(String),
+    /// A phrase with embedded values, which contains at least one embed.
+    Interpolation(Vec<Fragment>),
+    Comment(String),
+
+    Nil,
+    True,
+    False,
+    Charm,
+    Spell,
+    Type,
+    Divine,
+    Repeat,
+    As,
+    Fails,
+    Recover,
+    Uses,
+
+    Star,
+    Underscore,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Comma,
+    Semicolon,
+    Colon,
+    Equals,
+    Arrow,
+    FatArrow,
+    Question,
+    Bang,
+    Dot,
+    Hash,
+    Pipe,
+}
+
+impl TokenKind {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Identifier(name) => format!("symbol `{name}`"),
+            Self::Natural(_) | Self::Whole(_) | Self::Amount(_) => "number".to_string(),
+            Self::Phrase(_) | Self::Interpolation(_) => "phrase".to_string(),
+            Self::Comment(_) => "comment".to_string(),
+            other => format!("`{}`", other.text()),
+        }
+    }
+
+    /// The source text of punctuation and keywords.
+    pub fn text(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::True => "true",
+            Self::False => "false",
+            Self::Charm => "charm",
+            Self::Spell => "spell",
+            Self::Type => "type",
+            Self::Divine => "divine",
+            Self::Repeat => "repeat",
+            Self::As => "as",
+            Self::Fails => "fails",
+            Self::Recover => "recover",
+            Self::Uses => "uses",
+            Self::Star => "*",
+            Self::Underscore => "_",
+            Self::LeftParen => "(",
+            Self::RightParen => ")",
+            Self::LeftBracket => "[",
+            Self::RightBracket => "]",
+            Self::LeftBrace => "{",
+            Self::RightBrace => "}",
+            Self::Comma => ",",
+            Self::Semicolon => ";",
+            Self::Colon => ":",
+            Self::Equals => "=",
+            Self::Arrow => "->",
+            Self::FatArrow => "=>",
+            Self::Question => "?",
+            Self::Bang => "!",
+            Self::Dot => ".",
+            Self::Hash => "#",
+            Self::Pipe => "|",
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// A piece of an interpolated phrase.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fragment {
+    Text(String),
+    /// The tokens of an embedded value, and the text of its format spec, if it has one. The span
+    /// covers the braces.
+    Embed {
+        tokens: Vec<Token>,
+        spec: Option<(String, Span)>,
+        span: Span,
+    },
+}
+
+/// Splits a source into tokens. Comments are kept, so that the formatter can preserve them; the
+/// parser skips them.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, Diagnostic> {
+    let mut lexer = Lexer {
+        source,
+        position: 0,
+        tokens: Vec::new(),
+        depth: 0,
+    };
+    lexer.run()?;
+    Ok(lexer.tokens)
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+    tokens: Vec<Token>,
+    /// How many embedded values the current position is nested in.
+    depth: usize,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.position..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+    }
+
+    fn run(&mut self) -> Result<(), Diagnostic> {
+        while let Some(token) = self.token()? {
+            self.tokens.push(token);
+        }
+        Ok(())
+    }
+
+    /// Lexes the next token, skipping whitespace before it.
+    fn token(&mut self) -> Result<Option<Token>, Diagnostic> {
+        self.eat_while(char::is_whitespace);
+        let start = self.position;
+        let Some(c) = self.peek() else {
+            return Ok(None);
+        };
+        let kind = match c {
+            '/' if self.peek_second() == Some('/') => {
+                self.eat_while(|c| c != '\n');
+                let text = self.source[start + 2..self.position].trim_end();
+                TokenKind::Comment(text.to_string())
+            }
+            '"' => self.phrase()?,
+            '0'..='9' => self.number(start)?,
+            '+' | '-' if self.peek_second().is_some_and(|c| c.is_ascii_digit()) => {
+                self.bump();
+                self.number(start)?
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                keyword_or_identifier(&self.source[start..self.position])
+            }
+            _ => {
+                self.bump();
+                match c {
+                    '*' => TokenKind::Star,
+                    '(' => TokenKind::LeftParen,
+                    ')' => TokenKind::RightParen,
+                    '[' => TokenKind::LeftBracket,
+                    ']' => TokenKind::RightBracket,
+                    '{' => TokenKind::LeftBrace,
+                    '}' => TokenKind::RightBrace,
+                    ',' => TokenKind::Comma,
+                    ';' => TokenKind::Semicolon,
+                    ':' => TokenKind::Colon,
+                    '=' if self.peek() == Some('>') => {
+                        self.bump();
+                        TokenKind::FatArrow
+                    }
+                    '=' => TokenKind::Equals,
+                    '?' => TokenKind::Question,
+                    '!' => TokenKind::Bang,
+                    '.' => TokenKind::Dot,
+                    '#' => TokenKind::Hash,
+                    '|' => TokenKind::Pipe,
+                    '-' if self.peek() == Some('>') => {
+                        self.bump();
+                        TokenKind::Arrow
+                    }
+                    _ => {
+                        return Err(Diagnostic::error(
+                            format!("unexpected character `{c}`"),
+                            Span::new(start, self.position),
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Some(Token {
+            kind,
+            span: Span::new(start, self.position),
+        }))
+    }
+
+    fn phrase(&mut self) -> Result<TokenKind, Diagnostic> {
+        let start = self.position;
+        self.bump();
+        let mut text = String::new();
+        let mut fragments = Vec::new();
+        loop {
+            let escape_start = self.position;
+            match self.bump() {
+                Some('"') if fragments.is_empty() => return Ok(TokenKind::Phrase(text)),
+                Some('"') => {
+                    if !text.is_empty() {
+                        fragments.push(Fragment::Text(text));
+                    }
+                    return Ok(TokenKind::Interpolation(fragments));
+                }
+                Some('{') => {
+                    if !text.is_empty() {
+                        fragments.push(Fragment::Text(std::mem::take(&mut text)));
+                    }
+                    fragments.push(self.embed(escape_start)?);
+                }
+                Some('\\') => match self.bump() {
+                    Some('"') => text.push('"'),
+                    Some('{') => text.push('{'),
+                    Some('}') => text.push('}'),
+                    Some('\\') => text.push('\\'),
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    _ => {
+                        return Err(Diagnostic::error(
+                            "unknown escape sequence",
+                            Span::new(escape_start, self.position),
+                        ))
+                    }
+                },
+                Some(c) => text.push(c),
+                None => {
+                    return Err(Diagnostic::error(
+                        "unterminated phrase",
+                        Span::new(start, self.position),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Lexes the value embedded in a phrase after its opening brace, up to the closing brace or the
+    /// colon introducing its format spec.
+    fn embed(&mut self, start: usize) -> Result<Fragment, Diagnostic> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Diagnostic::error(
+                format!("phrases are embedded more than {MAX_DEPTH} levels deep"),
+                Span::new(start, self.position),
+            ));
+        }
+        self.depth += 1;
+        let fragment =
+            stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || self.embedded(start));
+        self.depth -= 1;
+        fragment
+    }
+
+    fn embedded(&mut self, start: usize) -> Result<Fragment, Diagnostic> {
+        let mut tokens = Vec::new();
+        let mut depth = 0usize;
+        let unterminated =
+            |end| Diagnostic::error("unterminated embedded value", Span::new(start, end));
+        loop {
+            let Some(token) = self.token()? else {
+                return Err(unterminated(self.position));
+            };
+            match token.kind {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace
+                    if depth > 0 =>
+                {
+                    depth -= 1
+                }
+                TokenKind::RightBrace => {
+                    return Ok(Fragment::Embed {
+                        tokens,
+                        spec: None,
+                        span: Span::new(start, self.position),
+                    })
+                }
+                TokenKind::Colon if depth == 0 => {
+                    let spec_start = self.position;
+                    self.eat_while(|c| c != '}' && c != '"');
+                    let spec_end = self.position;
+                    if self.bump() != Some('}') {
+                        return Err(unterminated(self.position));
+                    }
+                    let spec = self.source[spec_start..spec_end].to_string();
+                    return Ok(Fragment::Embed {
+                        tokens,
+                        spec: Some((spec, Span::new(spec_start, spec_end))),
+                        span: Span::new(start, self.position),
+                    });
+                }
+                _ => (),
+            }
+            tokens.push(token);
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Result<TokenKind, Diagnostic> {
+        self.eat_while(|c| c.is_ascii_digit());
+        let mut is_amount = false;
+        if self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            is_amount = true;
+            self.bump();
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            is_amount = true;
+            self.bump();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.bump();
+            }
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        let text = &self.source[start..self.position];
+        let span = Span::new(start, self.position);
+        let out_of_range = || Diagnostic::error(format!("number `{text}` is out of range"), span);
+        if is_amount {
+            text.parse()
+                .map(TokenKind::Amount)
+                .map_err(|_| Diagnostic::error(format!("malformed amount `{text}`"), span))
+        } else if text.starts_with(['+', '-']) {
+            text.parse()
+                .map(TokenKind::Whole)
+                .map_err(|_| out_of_range())
+        } else {
+            text.parse()
+                .map(TokenKind::Natural)
+                .map_err(|_| out_of_range())
+        }
+    }
+}
+
+fn keyword_or_identifier(text: &str) -> TokenKind {
+    match text {
+        "nil" => TokenKind::Nil,
+        "true" => TokenKind::True,
+        "false" => TokenKind::False,
+        "charm" => TokenKind::Charm,
+        "spell" => TokenKind::Spell,
+        "type" => TokenKind::Type,
+        "divine" => TokenKind::Divine,
+        "repeat" => TokenKind::Repeat,
+        "as" => TokenKind::As,
+        "fails" => TokenKind::Fails,
+        "recover" => TokenKind::Recover,
+        "uses" => TokenKind::Uses,
+        "_" => TokenKind::Underscore,
+        _ => TokenKind::Identifier(text.to_string()),
+    }
+}
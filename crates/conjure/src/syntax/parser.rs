@@ -0,0 +1,895 @@
+use crate::{
+    ast::{
+        Action, Arm, Binding, Boundary, Capabilities, Capability, Cast, Channel, Charm, CharmType,
+        Conjuration, Divination, Element, Embed, Field, FieldType, FormatSpec, Interpolation,
+        Invocation, Manifest, Omen, Pattern, Piece, Presence, Projection, Recovery, Repetition,
+        Sequence, SimpleType, Span, Spell, SpellType, Symbol, Type, Value, Variant, VariantType,
+    },
+    diagnostic::Diagnostic,
+    interpret::{STACK_RED_ZONE, STACK_SEGMENT_SIZE},
+};
+
+use super::{
+    lexer::{Fragment, Token, TokenKind},
+    MAX_DEPTH,
+};
+
+type Result<T> = std::result::Result<T, Diagnostic>;
+
+pub(super) struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end: usize,
+    /// The names of the type parameters in scope, which are parsed as type variables.
+    type_parameters: Vec<String>,
+    /// How deeply the construct being parsed is nested, up to [`MAX_DEPTH`].
+    depth: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>, source_len: usize) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Comment(_)))
+            .collect();
+        Self {
+            tokens,
+            position: 0,
+            end: source_len,
+            type_parameters: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Nests the construct being parsed `levels` deeper, unless that would nest it too deeply.
+    fn deepen(&mut self, levels: usize) -> Result<()> {
+        self.depth += levels;
+        if self.depth > MAX_DEPTH {
+            return Err(Diagnostic::error(
+                format!("this is nested more than {MAX_DEPTH} levels deep"),
+                self.span(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses a construct nested in the current one. Debug builds take a lot of stack for every
+    /// level, so the stack is grown as needed, as the interpreter does.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.deepen(1)?;
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || f(self));
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|t| &t.kind)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.position + n).map(|t| &t.kind)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.position)
+            .map_or(Span::new(self.end, self.end), |t| t.span)
+    }
+
+    fn previous_span(&self) -> Span {
+        self.position
+            .checked_sub(1)
+            .map_or(Span::default(), |i| self.tokens[i].span)
+    }
+
+    fn at(&self, kind: &TokenKind) -> bool {
+        self.peek() == Some(kind)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.at(kind);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> Result<Span> {
+        if self.eat(kind) {
+            Ok(self.previous_span())
+        } else {
+            Err(self.unexpected(&format!("`{}`", kind.text())))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let found = self
+            .peek()
+            .map_or("end of input".to_string(), TokenKind::describe);
+        Diagnostic::error(format!("expected {expected}, found {found}"), self.span())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    /// Consumes an optional semicolon, and requires the input to end after it.
+    pub fn finish_entry(&mut self) -> Result<()> {
+        self.eat(&TokenKind::Semicolon);
+        if !self.is_done() {
+            return Err(self.unexpected("end of input"));
+        }
+        Ok(())
+    }
+
+    /// Parses items separated by commas up to a closing parenthesis, which is consumed. A trailing
+    /// comma is permitted; the returned flag tells whether one was present.
+    fn comma_separated<T>(
+        &mut self,
+        item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<(Vec<T>, bool)> {
+        self.separated_until(&TokenKind::RightParen, item)
+    }
+
+    /// Parses items separated by commas up to `closing`, which is consumed.
+    fn separated_until<T>(
+        &mut self,
+        closing: &TokenKind,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<(Vec<T>, bool)> {
+        let mut items = Vec::new();
+        let mut trailing_comma = false;
+        while !self.eat(closing) {
+            items.push(item(self)?);
+            trailing_comma = self.eat(&TokenKind::Comma);
+            if !trailing_comma {
+                self.expect(closing)?;
+                break;
+            }
+        }
+        Ok((items, trailing_comma))
+    }
+
+    pub fn conjuration(&mut self) -> Result<Conjuration> {
+        let mut bindings = Vec::new();
+        while !self.is_done() {
+            bindings.push(self.binding()?);
+            self.expect(&TokenKind::Semicolon)?;
+        }
+        Ok(Conjuration { bindings })
+    }
+
+    /// Whether the upcoming tokens start a binding rather than a value.
+    pub fn at_binding(&self) -> bool {
+        let mut n = match self.peek() {
+            Some(TokenKind::Identifier(_) | TokenKind::Star | TokenKind::Underscore) => 1,
+            // A conjunction pattern looks like a conjunction value up to its closing parenthesis.
+            Some(TokenKind::LeftParen) => match self.group_len(0) {
+                Some(n) => n,
+                None => return false,
+            },
+            // So does a variant pattern look like a variant value.
+            Some(TokenKind::Hash) if matches!(self.peek_nth(1), Some(TokenKind::Identifier(_))) => {
+                match self.peek_nth(2) {
+                    Some(TokenKind::LeftParen) => match self.group_len(2) {
+                        Some(n) => 2 + n,
+                        None => return false,
+                    },
+                    _ => 2,
+                }
+            }
+            _ => return false,
+        };
+        while self.peek_nth(n) == Some(&TokenKind::Question) {
+            n += 1;
+        }
+        matches!(self.peek_nth(n), Some(TokenKind::Colon | TokenKind::Equals))
+    }
+
+    /// The number of tokens in the parenthesized group starting `offset` tokens ahead, if it is
+    /// closed.
+    fn group_len(&self, offset: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut n = 0;
+        loop {
+            match self.peek_nth(offset + n)? {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => depth -= 1,
+                _ => (),
+            }
+            n += 1;
+            if depth == 0 {
+                return Some(n);
+            }
+        }
+    }
+
+    pub fn binding(&mut self) -> Result<Binding> {
+        let manifest = self.manifest()?;
+        self.expect(&TokenKind::Equals)?;
+        let value = self.value()?;
+        Ok(Binding { manifest, value })
+    }
+
+    fn symbol(&mut self) -> Result<Symbol> {
+        let span = self.span();
+        let name = match self.peek() {
+            Some(TokenKind::Identifier(name)) => name.clone(),
+            Some(TokenKind::Star) => "*".to_string(),
+            _ => return Err(self.unexpected("a symbol")),
+        };
+        self.position += 1;
+        Ok(Symbol::new(name, span))
+    }
+
+    fn manifest(&mut self) -> Result<Manifest> {
+        self.nested(Self::nested_manifest)
+    }
+
+    fn nested_manifest(&mut self) -> Result<Manifest> {
+        let start = self.span();
+        let mut manifest = self.base_manifest()?;
+        let depth = self.depth;
+        while self.eat(&TokenKind::Question) {
+            self.deepen(1)?;
+            manifest = Manifest {
+                pattern: Pattern::Optional(Box::new(manifest)),
+                ty: Type::Inferred,
+                span: start.to(self.previous_span()),
+            };
+        }
+        if self.eat(&TokenKind::Colon) {
+            if manifest.ty != Type::Inferred {
+                return Err(Diagnostic::error(
+                    "the type of this pattern is already declared",
+                    self.previous_span(),
+                ));
+            }
+            manifest.ty = self.ty()?;
+            manifest.span = start.to(self.previous_span());
+        }
+        self.depth = depth;
+        Ok(manifest)
+    }
+
+    fn base_manifest(&mut self) -> Result<Manifest> {
+        let start = self.span();
+        let pattern = if self.eat(&TokenKind::Underscore) {
+            Pattern::Wildcard
+        } else if self.eat(&TokenKind::LeftParen) {
+            let (mut manifests, trailing_comma) = self.comma_separated(Self::manifest)?;
+            if manifests.len() == 1 && !trailing_comma {
+                let mut manifest = manifests.pop().unwrap();
+                manifest.span = start.to(self.previous_span());
+                return Ok(manifest);
+            }
+            Pattern::Conjunction(manifests)
+        } else if self.eat(&TokenKind::Hash) {
+            let tag = self.symbol()?;
+            let payload = if self.eat(&TokenKind::LeftParen) {
+                let payload_start = self.previous_span();
+                let (mut manifests, trailing_comma) = self.comma_separated(Self::manifest)?;
+                let payload = if manifests.len() == 1 && !trailing_comma {
+                    manifests.pop().unwrap()
+                } else {
+                    Manifest {
+                        pattern: Pattern::Conjunction(manifests),
+                        ty: Type::Inferred,
+                        span: payload_start.to(self.previous_span()),
+                    }
+                };
+                Some(Box::new(payload))
+            } else {
+                None
+            };
+            Pattern::Variant { tag, payload }
+        } else {
+            Pattern::Symbol(self.symbol()?)
+        };
+        Ok(Manifest {
+            pattern,
+            ty: Type::Inferred,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// Parses the type parameters of a charm or spell, if it has any.
+    fn parameters(&mut self) -> Result<Vec<Symbol>> {
+        if !self.eat(&TokenKind::LeftBracket) {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .separated_until(&TokenKind::RightBracket, Self::symbol)?
+            .0)
+    }
+
+    /// Parses the rest of a charm or spell with its type parameters in scope.
+    fn generic<T>(
+        &mut self,
+        parameters: &[Symbol],
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let outer = self.type_parameters.len();
+        self.type_parameters
+            .extend(parameters.iter().map(|p| p.name.clone()));
+        let result = f(self);
+        self.type_parameters.truncate(outer);
+        result
+    }
+
+    fn components(&mut self) -> Result<Vec<Manifest>> {
+        self.expect(&TokenKind::LeftParen)?;
+        Ok(self.comma_separated(Self::manifest)?.0)
+    }
+
+    fn product(&mut self) -> Result<Type> {
+        if self.eat(&TokenKind::Arrow) {
+            self.ty()
+        } else {
+            Ok(Type::Inferred)
+        }
+    }
+
+    pub fn ty(&mut self) -> Result<Type> {
+        self.nested(Self::nested_type)
+    }
+
+    fn nested_type(&mut self) -> Result<Type> {
+        let mut ty = self.base_type()?;
+        let depth = self.depth;
+        while self.eat(&TokenKind::Question) {
+            self.deepen(1)?;
+            ty = Type::Optional(Box::new(ty));
+        }
+        self.depth = depth;
+        Ok(ty)
+    }
+
+    fn base_type(&mut self) -> Result<Type> {
+        let span = self.span();
+        let Some(kind) = self.peek().cloned() else {
+            return Err(self.unexpected("a type"));
+        };
+        self.position += 1;
+        let ty = match kind {
+            TokenKind::Nil => Type::Nil,
+            TokenKind::Type => Type::Type,
+            TokenKind::Underscore => Type::Inferred,
+            TokenKind::Identifier(name) if self.type_parameters.contains(&name) => {
+                Type::Variable(Symbol::new(name, span))
+            }
+            TokenKind::Identifier(name) => match simple_type(&name) {
+                Some(simple) => Type::Simple(simple),
+                None => Type::Symbol(Symbol::new(name, span)),
+            },
+            TokenKind::LeftParen if self.at_field(&TokenKind::Colon) => {
+                Type::Record(self.comma_separated(Self::field_type)?.0)
+            }
+            TokenKind::LeftParen => {
+                let (mut types, trailing_comma) = self.comma_separated(Self::ty)?;
+                if types.len() == 1 && !trailing_comma {
+                    types.pop().unwrap()
+                } else {
+                    Type::Conjoined(types)
+                }
+            }
+            TokenKind::LeftBracket => {
+                let element = self.ty()?;
+                self.expect(&TokenKind::RightBracket)?;
+                Type::Sequence(Box::new(element))
+            }
+            TokenKind::Hash => {
+                self.position -= 1;
+                let mut variants = vec![self.variant_type()?];
+                while self.eat(&TokenKind::Pipe) {
+                    variants.push(self.variant_type()?);
+                }
+                Type::Union(variants)
+            }
+            TokenKind::Charm | TokenKind::Spell => {
+                self.expect(&TokenKind::LeftParen)?;
+                let components = self.comma_separated(Self::ty)?.0;
+                self.expect(&TokenKind::Arrow)?;
+                let product = Box::new(self.ty()?);
+                if kind == TokenKind::Charm {
+                    Type::Charm(CharmType {
+                        components,
+                        product,
+                    })
+                } else {
+                    let fails = self.eat(&TokenKind::Fails);
+                    Type::Spell(SpellType {
+                        components,
+                        product,
+                        fails,
+                        capabilities: self.uses()?.unwrap_or_default(),
+                    })
+                }
+            }
+            _ => {
+                self.position -= 1;
+                return Err(self.unexpected("a type"));
+            }
+        };
+        Ok(ty)
+    }
+
+    /// Parses the capabilities of a spell, as in `uses [console, clock]`, if it names any.
+    fn uses(&mut self) -> Result<Option<Capabilities>> {
+        if !self.eat(&TokenKind::Uses) {
+            return Ok(None);
+        }
+        self.expect(&TokenKind::LeftBracket)?;
+        let names = self
+            .separated_until(&TokenKind::RightBracket, Self::symbol)?
+            .0;
+        let mut capabilities = Capabilities::new();
+        for name in names {
+            let capability = Capability::from_name(&name).ok_or_else(|| {
+                Diagnostic::error(format!("unknown capability `{name}`"), name.span)
+            })?;
+            capabilities.insert(capability);
+        }
+        Ok(Some(capabilities))
+    }
+
+    fn variant_type(&mut self) -> Result<VariantType> {
+        self.expect(&TokenKind::Hash)?;
+        let tag = self.symbol()?;
+        let payload = if self.eat(&TokenKind::LeftParen) {
+            let (mut types, trailing_comma) = self.comma_separated(Self::ty)?;
+            Some(if types.len() == 1 && !trailing_comma {
+                types.pop().unwrap()
+            } else {
+                Type::Conjoined(types)
+            })
+        } else {
+            None
+        };
+        Ok(VariantType { tag, payload })
+    }
+
+    /// Whether the upcoming tokens start a record field, whose symbol is followed by `separator`.
+    fn at_field(&self, separator: &TokenKind) -> bool {
+        matches!(self.peek(), Some(TokenKind::Identifier(_))) && self.peek_nth(1) == Some(separator)
+    }
+
+    fn field_type(&mut self) -> Result<FieldType> {
+        let symbol = self.symbol()?;
+        self.expect(&TokenKind::Colon)?;
+        let ty = self.ty()?;
+        Ok(FieldType { symbol, ty })
+    }
+
+    fn field(&mut self) -> Result<Field> {
+        let symbol = self.symbol()?;
+        self.expect(&TokenKind::Equals)?;
+        let value = self.value()?;
+        Ok(Field { symbol, value })
+    }
+
+    pub fn value(&mut self) -> Result<Value> {
+        let value = self.nested(Self::invocations)?;
+        if self.at(&TokenKind::Bang) {
+            return Err(Diagnostic::error(
+                "spells can only be cast in actions; bind the product of the cast first",
+                self.span(),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Parses a value, followed by any number of invocations of it and projections from it.
+    fn invocations(&mut self) -> Result<Value> {
+        let mut value = self.primary_value()?;
+        let depth = self.depth;
+        loop {
+            if matches!(self.peek(), Some(TokenKind::LeftParen | TokenKind::Dot)) {
+                self.deepen(1)?;
+            }
+            if self.eat(&TokenKind::LeftParen) {
+                let components = self.comma_separated(Self::value)?.0;
+                value = Value::Invocation(Invocation {
+                    charm: Box::new(value),
+                    components,
+                });
+            } else if self.eat(&TokenKind::Dot) {
+                let field = self.symbol()?;
+                value = Value::Projection(Projection {
+                    record: Box::new(value),
+                    field,
+                });
+            } else {
+                self.depth = depth;
+                return Ok(value);
+            }
+        }
+    }
+
+    fn primary_value(&mut self) -> Result<Value> {
+        let span = self.span();
+        let Some(kind) = self.peek().cloned() else {
+            return Err(self.unexpected("a value"));
+        };
+        self.position += 1;
+        let value = match kind {
+            TokenKind::Nil => Value::Element(Element::Nil),
+            TokenKind::True => Value::Element(Element::Truth(true)),
+            TokenKind::False => Value::Element(Element::Truth(false)),
+            TokenKind::Natural(n) => Value::Element(Element::NaturalCount(n)),
+            TokenKind::Whole(n) => Value::Element(Element::WholeCount(n)),
+            TokenKind::Amount(x) => Value::Element(Element::Amount(x)),
+            TokenKind::Phrase(text) => Value::Element(Element::Phrase(text)),
+            TokenKind::Interpolation(fragments) => Value::Interpolation(Interpolation {
+                pieces: fragments
+                    .into_iter()
+                    .map(|fragment| self.piece(fragment))
+                    .collect::<Result<_>>()?,
+                span,
+            }),
+            TokenKind::Identifier(name) => Value::Symbol(Symbol::new(name, span)),
+            TokenKind::Star => Value::Symbol(Symbol::new("*", span)),
+            TokenKind::Type => Value::Type(self.ty()?),
+            TokenKind::LeftParen if self.at_field(&TokenKind::Equals) => {
+                Value::Record(self.comma_separated(Self::field)?.0)
+            }
+            TokenKind::LeftParen => {
+                let (mut values, trailing_comma) = self.comma_separated(Self::value)?;
+                if values.len() == 1 && !trailing_comma {
+                    values.pop().unwrap()
+                } else {
+                    Value::Conjunction(values)
+                }
+            }
+            TokenKind::LeftBracket => {
+                let elements = self
+                    .separated_until(&TokenKind::RightBracket, Self::value)?
+                    .0;
+                Value::Sequence(Sequence {
+                    elements,
+                    span: span.to(self.previous_span()),
+                })
+            }
+            TokenKind::Hash => {
+                let tag = self.symbol()?;
+                let payload = if self.eat(&TokenKind::LeftParen) {
+                    let (mut values, trailing_comma) = self.comma_separated(Self::value)?;
+                    Some(Box::new(if values.len() == 1 && !trailing_comma {
+                        values.pop().unwrap()
+                    } else {
+                        Value::Conjunction(values)
+                    }))
+                } else {
+                    None
+                };
+                Value::Variant(Variant { tag, payload })
+            }
+            TokenKind::LeftBrace => {
+                self.position -= 1;
+                Value::Boundary(self.boundary(Type::Inferred)?)
+            }
+            TokenKind::Charm => {
+                let parameters = self.parameters()?;
+                let (components, boundary) = self.generic(&parameters, |p| {
+                    let components = p.components()?;
+                    let product = p.product()?;
+                    Ok((components, p.boundary(product)?))
+                })?;
+                Value::Charm(Charm {
+                    parameters,
+                    components,
+                    boundary,
+                })
+            }
+            TokenKind::Spell => {
+                let parameters = self.parameters()?;
+                let (components, ty, fails, capabilities, (actions, span)) =
+                    self.generic(&parameters, |p| {
+                        let components = p.components()?;
+                        let ty = p.product()?;
+                        let fails = p.eat(&TokenKind::Fails);
+                        let capabilities = p.uses()?;
+                        Ok((components, ty, fails, capabilities, p.actions()?))
+                    })?;
+                Value::Spell(Spell {
+                    parameters,
+                    components,
+                    actions,
+                    ty,
+                    fails,
+                    capabilities,
+                    span,
+                })
+            }
+            TokenKind::Divine => Value::Divination(self.divination(span)?),
+            _ => {
+                self.position -= 1;
+                return Err(self.unexpected("a value"));
+            }
+        };
+        Ok(value)
+    }
+
+    fn boundary(&mut self, ty: Type) -> Result<Boundary> {
+        let start = self.expect(&TokenKind::LeftBrace)?;
+        let mut bindings = Vec::new();
+        while self.at_binding() {
+            bindings.push(self.binding()?);
+            self.expect(&TokenKind::Semicolon)?;
+        }
+        let value = Box::new(self.value()?);
+        let end = self.expect(&TokenKind::RightBrace)?;
+        Ok(Boundary {
+            bindings,
+            value,
+            ty,
+            span: start.to(end),
+        })
+    }
+
+    /// Parses the subject and arms of a divination, whose `divine` keyword has been consumed.
+    fn divination(&mut self, keyword: Span) -> Result<Divination> {
+        let subject = if self.at(&TokenKind::LeftBrace) {
+            None
+        } else {
+            Some(Box::new(self.value()?))
+        };
+        self.expect(&TokenKind::LeftBrace)?;
+        let mut arms = Vec::new();
+        while !self.eat(&TokenKind::RightBrace) {
+            arms.push(if subject.is_some() {
+                self.pattern_arm()?
+            } else {
+                self.arm()?
+            });
+            if !self.eat(&TokenKind::Comma) {
+                self.expect(&TokenKind::RightBrace)?;
+                break;
+            }
+        }
+        Ok(Divination {
+            subject,
+            arms,
+            span: keyword.to(self.previous_span()),
+        })
+    }
+
+    fn arm(&mut self) -> Result<Arm> {
+        let start = self.span();
+        let omen = if self.at_binding() {
+            let manifest = self.manifest()?;
+            self.expect(&TokenKind::Equals)?;
+            let value = self.value()?;
+            Omen::Presence(Presence { manifest, value })
+        } else if self.eat(&TokenKind::Underscore) {
+            Omen::Otherwise
+        } else {
+            Omen::Truth(self.value()?)
+        };
+        self.expect(&TokenKind::FatArrow)?;
+        let value = self.value()?;
+        Ok(Arm {
+            omen,
+            value,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    /// Parses an arm of a divination with a subject, which is matched against a pattern.
+    fn pattern_arm(&mut self) -> Result<Arm> {
+        let start = self.span();
+        let omen =
+            if self.at(&TokenKind::Underscore) && self.peek_nth(1) == Some(&TokenKind::FatArrow) {
+                self.position += 1;
+                Omen::Otherwise
+            } else {
+                Omen::Pattern(self.manifest()?)
+            };
+        self.expect(&TokenKind::FatArrow)?;
+        let value = self.value()?;
+        Ok(Arm {
+            omen,
+            value,
+            span: start.to(self.previous_span()),
+        })
+    }
+
+    fn actions(&mut self) -> Result<(Vec<Action>, Span)> {
+        let start = self.expect(&TokenKind::LeftBrace)?;
+        let mut actions = Vec::new();
+        while !self.eat(&TokenKind::RightBrace) {
+            actions.push(self.action()?);
+            self.expect(&TokenKind::Semicolon)?;
+        }
+        Ok((actions, start.to(self.previous_span())))
+    }
+
+    pub fn action(&mut self) -> Result<Action> {
+        self.nested(Self::nested_action)
+    }
+
+    fn nested_action(&mut self) -> Result<Action> {
+        if self.at(&TokenKind::Repeat) {
+            return self.repetition();
+        }
+        if !self.at_binding() {
+            return self.casts();
+        }
+        let manifest = self.manifest()?;
+        self.expect(&TokenKind::Equals)?;
+        Ok(match self.casts()? {
+            Action::Cast(cast) => Action::Channel(Channel { manifest, cast }),
+            Action::Value(value) => Action::Binding(Binding { manifest, value }),
+            Action::Binding(_) | Action::Channel(_) | Action::Repetition(_) => {
+                unreachable!("casts() never binds or repeats")
+            }
+        })
+    }
+
+    fn repetition(&mut self) -> Result<Action> {
+        let start = self.expect(&TokenKind::Repeat)?;
+        let count = self.value()?;
+        let counter = if self.eat(&TokenKind::As) {
+            Some(self.manifest()?)
+        } else {
+            None
+        };
+        let (actions, end) = self.actions()?;
+        Ok(Action::Repetition(Repetition {
+            count,
+            counter,
+            actions,
+            span: start.to(end),
+        }))
+    }
+
+    /// Parses a value, followed by any number of casts of it, or a recovered cast.
+    fn casts(&mut self) -> Result<Action> {
+        if self.at(&TokenKind::Recover) {
+            return self.recovery();
+        }
+        let mut action = Action::Value(self.invocations()?);
+        let depth = self.depth;
+        while self.eat(&TokenKind::Bang) {
+            self.deepen(1)?;
+            self.expect(&TokenKind::LeftParen)?;
+            let components = self.comma_separated(Self::cast_component)?.0;
+            action = Action::Cast(Cast {
+                spell: Box::new(action),
+                components,
+                recovery: None,
+            });
+        }
+        self.depth = depth;
+        if self.at(&TokenKind::LeftParen) {
+            return Err(Diagnostic::error(
+                "the product of a cast cannot be invoked directly; bind it first",
+                self.span(),
+            ));
+        }
+        if self.at(&TokenKind::Dot) {
+            return Err(Diagnostic::error(
+                "fields cannot be projected from the product of a cast directly; bind it first",
+                self.span(),
+            ));
+        }
+        Ok(action)
+    }
+
+    /// Parses a cast followed by the actions recovering from its failure, such as
+    /// `recover read!() as failure { ... }`.
+    fn recovery(&mut self) -> Result<Action> {
+        let start = self.expect(&TokenKind::Recover)?;
+        let cast_start = self.span();
+        let Action::Cast(mut cast) = self.casts()? else {
+            return Err(Diagnostic::error(
+                "only a cast can be recovered",
+                cast_start.to(self.previous_span()),
+            ));
+        };
+        if cast.recovery.is_some() {
+            return Err(Diagnostic::error(
+                "a recovered cast cannot be recovered again",
+                cast_start.to(self.previous_span()),
+            ));
+        }
+        let failure = if self.eat(&TokenKind::As) {
+            Some(self.manifest()?)
+        } else {
+            None
+        };
+        let (actions, end) = self.actions()?;
+        cast.recovery = Some(Recovery {
+            failure,
+            actions,
+            span: start.to(end),
+        });
+        Ok(Action::Cast(cast))
+    }
+
+    /// Parses a piece of an interpolated phrase. An embedded value is parsed on its own, as if it
+    /// were the whole input.
+    fn piece(&self, fragment: Fragment) -> Result<Piece> {
+        let (tokens, spec, span) = match fragment {
+            Fragment::Text(text) => return Ok(Piece::Text(text)),
+            Fragment::Embed { tokens, spec, span } => (tokens, spec, span),
+        };
+        let mut parser = Parser::new(tokens, span.end - 1);
+        parser.type_parameters = self.type_parameters.clone();
+        parser.depth = self.depth;
+        let value = parser.value()?;
+        if !parser.is_done() {
+            return Err(parser.unexpected("`}`"));
+        }
+        let spec = match spec {
+            Some((text, span)) => {
+                Some(format_spec(&text).map_err(|message| Diagnostic::error(message, span))?)
+            }
+            None => None,
+        };
+        Ok(Piece::Embed(Embed {
+            value: Box::new(value),
+            spec,
+        }))
+    }
+
+    fn cast_component(&mut self) -> Result<Action> {
+        if self.at_binding() {
+            return Err(Diagnostic::error(
+                "bindings cannot be cast components",
+                self.span(),
+            ));
+        }
+        self.casts()
+    }
+}
+
+pub fn simple_type(name: &str) -> Option<SimpleType> {
+    match name {
+        "truth" => Some(SimpleType::Truth),
+        "natural" => Some(SimpleType::NaturalCount),
+        "whole" => Some(SimpleType::WholeCount),
+        "amount" => Some(SimpleType::Amount),
+        "phrase" => Some(SimpleType::Phrase),
+        _ => None,
+    }
+}
+
+/// The largest width or precision a format spec may ask for.
+pub const MAX_FORMAT_SIZE: usize = u16::MAX as usize;
+
+/// Parses a format spec, such as `+08.2`: an optional `+`, an optional `0`, an optional width and
+/// an optional precision after a point. Widths and precisions may be at most [`MAX_FORMAT_SIZE`].
+pub fn format_spec(text: &str) -> std::result::Result<FormatSpec, String> {
+    let mut spec = FormatSpec::default();
+    let mut rest = text;
+    if let Some(after) = rest.strip_prefix('+') {
+        spec.sign = true;
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix('0') {
+        spec.zero = true;
+        rest = after;
+    }
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (rest, None),
+    };
+    let number = |digits: &str, what: &str| {
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("malformed format spec `{text}`"));
+        }
+        digits
+            .parse()
+            .ok()
+            .filter(|&n| n <= MAX_FORMAT_SIZE)
+            .ok_or_else(|| format!("the {what} in format spec `{text}` exceeds {MAX_FORMAT_SIZE}"))
+    };
+    if !width.is_empty() {
+        spec.width = Some(number(width, "width")?);
+    }
+    if let Some(precision) = precision {
+        spec.precision = Some(number(precision, "precision")?);
+    }
+    Ok(spec)
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use crate::ast::{Capabilities, Capability, FormatSpec, Manifest, Pattern, SimpleType, Type};
+
+impl fmt::Display for SimpleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Truth => "truth",
+            Self::NaturalCount => "natural",
+            Self::WholeCount => "whole",
+            Self::Amount => "amount",
+            Self::Phrase => "phrase",
+        })
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn write_capabilities(f: &mut fmt::Formatter<'_>, capabilities: &Capabilities) -> fmt::Result {
+    f.write_str("[")?;
+    for (i, capability) in capabilities.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{capability}")?;
+    }
+    f.write_str("]")
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, types: &[Type]) -> fmt::Result {
+    f.write_str("(")?;
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{ty}")?;
+    }
+    f.write_str(")")
+}
+
+/// Types display in the syntax they are written in.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inferred => f.write_str("_"),
+            Self::Nil => f.write_str("nil"),
+            Self::Optional(inner)
+                if matches!(**inner, Self::Charm(_) | Self::Spell(_) | Self::Union(_)) =>
+            {
+                write!(f, "({inner})?")
+            }
+            Self::Optional(inner) => write!(f, "{inner}?"),
+            Self::Symbol(symbol) | Self::Named(symbol, _) | Self::Variable(symbol) => {
+                write!(f, "{symbol}")
+            }
+            Self::Simple(simple) => write!(f, "{simple}"),
+            Self::Conjoined(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Self::Conjoined(types) => write_list(f, types),
+            Self::Record(fields) => {
+                f.write_str("(")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", field.symbol, field.ty)?;
+                }
+                f.write_str(")")
+            }
+            Self::Sequence(element) => write!(f, "[{element}]"),
+            Self::Union(variants) => {
+                for (i, variant) in variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "#{}", variant.tag)?;
+                    match &variant.payload {
+                        Some(Self::Conjoined(types)) if types.len() != 1 => write_list(f, types)?,
+                        Some(payload) => write!(f, "({payload})")?,
+                        None => (),
+                    }
+                }
+                Ok(())
+            }
+            Self::Charm(charm) => {
+                f.write_str("charm")?;
+                write_list(f, &charm.components)?;
+                write!(f, " -> {}", charm.product)
+            }
+            Self::Spell(spell) => {
+                f.write_str("spell")?;
+                write_list(f, &spell.components)?;
+                write!(f, " -> {}", spell.product)?;
+                if spell.fails {
+                    f.write_str(" fails")?;
+                }
+                if !spell.capabilities.is_empty() {
+                    f.write_str(" uses ")?;
+                    write_capabilities(f, &spell.capabilities)?;
+                }
+                Ok(())
+            }
+            Self::Type => f.write_str("type"),
+        }
+    }
+}
+
+fn write_manifests(f: &mut fmt::Formatter<'_>, manifests: &[Manifest]) -> fmt::Result {
+    f.write_str("(")?;
+    for (i, manifest) in manifests.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{manifest}")?;
+    }
+    f.write_str(")")
+}
+
+/// Patterns display in the syntax they are written in, including the types declared inside them.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Symbol(symbol) => write!(f, "{symbol}"),
+            Self::Wildcard => f.write_str("_"),
+            Self::Conjunction(manifests) if manifests.len() == 1 => {
+                write!(f, "({},)", manifests[0])
+            }
+            Self::Conjunction(manifests) => write_manifests(f, manifests),
+            Self::Variant { tag, payload } => {
+                write!(f, "#{tag}")?;
+                match payload.as_deref() {
+                    Some(Manifest {
+                        pattern: Self::Conjunction(manifests),
+                        ty: Type::Inferred,
+                        ..
+                    }) if manifests.len() != 1 => write_manifests(f, manifests),
+                    Some(payload) => write!(f, "({payload})"),
+                    None => Ok(()),
+                }
+            }
+            Self::Optional(inner) if inner.ty != Type::Inferred => write!(f, "({inner})?"),
+            Self::Optional(inner) => write!(f, "{}?", inner.pattern),
+        }
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        if self.ty != Type::Inferred {
+            write!(f, ": {}", self.ty)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format specs display as they are written after the colon, which [`super::format_spec`] parses
+/// back.
+impl fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sign {
+            f.write_str("+")?;
+        }
+        if self.zero {
+            f.write_str("0")?;
+        }
+        if let Some(width) = self.width {
+            write!(f, "{width}")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{precision}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1672 @@
+//! Type checking.
+//!
+//! Types flow from values to the symbols they are bound to. Types that are not declared, such as
+//! those of components, are inferred from how the values are used, Hindley–Milner style: a binding
+//! whose type leaves some parts open is generic, and every reference to it may fill those parts
+//! differently. Generic charms and spells may also name their type parameters. A recursive charm
+//! or spell sees itself with a single type throughout its own definition.
+//!
+//! [`Type::Inferred`] doubles as the type of anything whose type could not be determined; it is
+//! compatible with every type, so that one mistake is reported only once.
+//!
+//! A binding whose value is a type names it. Named types stand for their definitions, which may
+//! refer to themselves as long as a value constructor comes first: `#leaf | #node((Tree, Tree))` is
+//! a fine definition of `Tree`, but `Tree?` is not.
+//!
+//! Patterns are checked for exhaustiveness: a pattern that some value of its type would not match
+//! is only accepted in a presence omen or an arm of a divination with a subject, where the
+//! divination moves on to the next arm instead. The arms of such a divination must together match
+//! every value of the subject's type, unless they end with a `_` arm.
+
+mod unify;
+
+use std::collections::HashMap;
+
+use unify::{is_flexible, transform, variables, Substitution};
+
+use crate::{
+    ast::{
+        self, Capabilities, Capability, CharmType, FieldType, SimpleType, Span, SpellType, Symbol,
+        Type, VariantType,
+    },
+    builtins::Builtin,
+    diagnostic::Diagnostic,
+};
+
+/// The types determined by the checker.
+#[derive(Debug, Clone, Default)]
+pub struct Types {
+    /// The type of every symbol occurrence, keyed by its span.
+    pub symbols: HashMap<Span, Type>,
+    /// Every named type, indexed by the number that [`Type::Named`] refers to it by.
+    pub named: Vec<NamedType>,
+}
+
+impl Types {
+    /// The type a named type stands for, following aliases. Other types are returned as they are.
+    pub fn unfold<'a>(&'a self, ty: &'a Type) -> &'a Type {
+        let mut ty = ty;
+        while let Type::Named(_, id) = ty {
+            match self.named.get(*id) {
+                Some(named) => ty = &named.ty,
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// Like [`is_instance`], with the named types in `expected` standing for their definitions
+    /// here.
+    pub fn is_instance(&self, expected: &Type, actual: &Type) -> bool {
+        let mut checker = Checker::default();
+        for (id, named) in self.named.iter().enumerate() {
+            checker.substitution.define(id, named.ty.clone());
+        }
+        let expected = checker.instantiate(&variables(expected), expected);
+        checker.substitution.compatible(&expected, actual)
+    }
+}
+
+/// The definition of a named type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedType {
+    /// The symbol that names the type.
+    pub symbol: Symbol,
+    pub ty: Type,
+    /// Whether the definition refers to the type itself.
+    pub recursive: bool,
+}
+
+pub fn check(conjuration: &ast::Conjuration) -> (Types, Vec<Diagnostic>) {
+    let mut checker = Checker::default();
+    for binding in &conjuration.bindings {
+        checker.binding(binding);
+    }
+    let diagnostics = checker.take_diagnostics();
+    (checker.into_types(), diagnostics)
+}
+
+/// Whether a value of type `actual` may be used where `expected` is required.
+pub fn is_compatible(expected: &Type, actual: &Type) -> bool {
+    Substitution::default().compatible(expected, actual)
+}
+
+/// Like [`is_compatible`], but with the type variables in `expected` standing for whatever types
+/// make it fit, as they do for the components of a generic charm or spell.
+pub fn is_instance(expected: &Type, actual: &Type) -> bool {
+    Types::default().is_instance(expected, actual)
+}
+
+/// Fills the inferred parts of a declared type with the corresponding parts of the actual type.
+fn refine(declared: &Type, actual: &Type) -> Type {
+    match (declared, actual) {
+        (Type::Inferred, actual) => actual.clone(),
+        (Type::Conjoined(declared), Type::Conjoined(actual)) if declared.len() == actual.len() => {
+            Type::Conjoined(
+                declared
+                    .iter()
+                    .zip(actual)
+                    .map(|(d, a)| refine(d, a))
+                    .collect(),
+            )
+        }
+        (Type::Record(declared), Type::Record(actual)) => Type::Record(
+            declared
+                .iter()
+                .map(|d| FieldType {
+                    symbol: d.symbol.clone(),
+                    ty: field_type(actual, &d.symbol).map_or(d.ty.clone(), |a| refine(&d.ty, a)),
+                })
+                .collect(),
+        ),
+        (Type::Sequence(declared), Type::Sequence(actual)) => {
+            Type::Sequence(Box::new(refine(declared, actual)))
+        }
+        (Type::Union(declared), Type::Union(actual)) => Type::Union(
+            declared
+                .iter()
+                .map(|d| VariantType {
+                    tag: d.tag.clone(),
+                    payload: match (&d.payload, variant_type(actual, &d.tag)) {
+                        (
+                            Some(declared),
+                            Some(VariantType {
+                                payload: Some(actual),
+                                ..
+                            }),
+                        ) => Some(refine(declared, actual)),
+                        (payload, _) => payload.clone(),
+                    },
+                })
+                .collect(),
+        ),
+        (Type::Optional(declared), Type::Optional(actual)) => {
+            Type::Optional(Box::new(refine(declared, actual)))
+        }
+        (Type::Optional(declared), actual) if !matches!(actual, Type::Nil) => {
+            Type::Optional(Box::new(refine(declared, actual)))
+        }
+        (declared, _) => declared.clone(),
+    }
+}
+
+/// The type of the field named `symbol`, if there is one.
+fn field_type<'a>(fields: &'a [FieldType], symbol: &Symbol) -> Option<&'a Type> {
+    fields.iter().find(|f| f.symbol == *symbol).map(|f| &f.ty)
+}
+
+/// The variant tagged `tag`, if there is one.
+fn variant_type<'a>(variants: &'a [VariantType], tag: &Symbol) -> Option<&'a VariantType> {
+    variants.iter().find(|v| v.tag == *tag)
+}
+
+/// The narrowest type of which both `a` and `b` are instances, if any. Nil and any other type
+/// unite into an optional, and two unions into a union of the variants of both.
+pub fn unite(a: &Type, b: &Type) -> Option<Type> {
+    Substitution::default().unite(a, b)
+}
+
+/// A type whose variables may stand for different types at every reference to it.
+#[derive(Debug, Clone)]
+struct Scheme {
+    variables: Vec<String>,
+    ty: Type,
+    /// If the symbol names a type, the symbol and the number of the type, which named types refer
+    /// to it by.
+    definition: Option<(Symbol, usize)>,
+}
+
+/// The name of the `i`th variable a binding is made generic over.
+fn variable_name(i: usize) -> String {
+    let letter = char::from(b'A' + (i % 26) as u8);
+    match i / 26 {
+        0 => letter.to_string(),
+        n => format!("{letter}{n}"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Callee {
+    Charm,
+    Spell,
+}
+
+/// Checks types incrementally, for hosts that receive a program piece by piece.
+#[derive(Debug, Clone, Default)]
+pub struct Checker {
+    scopes: Vec<HashMap<String, Scheme>>,
+    types: Types,
+    substitution: Substitution,
+    diagnostics: Vec<Diagnostic>,
+    /// The span of the innermost symbol being bound, invoked or cast, for errors concerning nodes
+    /// without a span of their own.
+    span: Span,
+    /// Whether the spell being checked cannot fail, so that casts that may fail must be recovered.
+    infallible: bool,
+    /// The capabilities used by the casts in the spell being checked so far.
+    used: Capabilities,
+    /// The capabilities the spell being checked declares, if it declares them.
+    declared: Option<Capabilities>,
+}
+
+impl Checker {
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// The types determined so far, with everything inferred about them filled in.
+    pub fn types(&self) -> Types {
+        Types {
+            symbols: self
+                .types
+                .symbols
+                .iter()
+                .map(|(span, ty)| (*span, self.display(ty)))
+                .collect(),
+            named: self.types.named.clone(),
+        }
+    }
+
+    pub fn into_types(self) -> Types {
+        self.types()
+    }
+
+    /// A type as far as it is known, for showing it to the user. Parts that are still unknown show
+    /// as inferred.
+    fn display(&self, ty: &Type) -> Type {
+        transform(&self.substitution.resolve(ty), &mut |t| match t {
+            Type::Variable(symbol) if is_flexible(symbol.as_str()) => Some(Type::Inferred),
+            _ => None,
+        })
+    }
+
+    fn error(&mut self, message: String) {
+        self.diagnostics.push(Diagnostic::error(message, self.span));
+    }
+
+    fn compatible(&mut self, expected: &Type, actual: &Type) -> bool {
+        self.substitution.compatible(expected, actual)
+    }
+
+    fn expect(&mut self, expected: &Type, actual: &Type, context: impl FnOnce() -> String) {
+        if !self.compatible(expected, actual) {
+            let context = context();
+            let (expected, actual) = (self.display(expected), self.display(actual));
+            self.error(format!("{context}: expected {expected}, found {actual}"));
+        }
+    }
+
+    fn unite(&mut self, a: &Type, b: &Type) -> Option<Type> {
+        self.substitution.unite(a, b)
+    }
+
+    /// A declared type with every inferred part replaced by a variable of its own, to be solved
+    /// by inference.
+    fn open(&mut self, ty: &Type) -> Type {
+        let ty = self.named(ty);
+        transform(&ty, &mut |t| match t {
+            Type::Inferred => Some(self.substitution.fresh()),
+            _ => None,
+        })
+    }
+
+    /// A declared type with its named types referring to the symbols that name them, which stay
+    /// the same wherever the type goes.
+    fn named(&self, ty: &Type) -> Type {
+        transform(ty, &mut |t| match t {
+            Type::Symbol(symbol) => {
+                let (symbol, id) = self.scheme(symbol)?.definition.clone()?;
+                Some(Type::Named(symbol, id))
+            }
+            _ => None,
+        })
+    }
+
+    fn scheme(&self, symbol: &Symbol) -> Option<&Scheme> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(symbol.as_str()))
+    }
+
+    /// A type with the given variables replaced by fresh ones.
+    fn instantiate(&mut self, names: &[String], ty: &Type) -> Type {
+        if names.is_empty() {
+            return ty.clone();
+        }
+        let fresh: HashMap<&str, Type> = names
+            .iter()
+            .map(|name| (name.as_str(), self.substitution.fresh()))
+            .collect();
+        transform(ty, &mut |t| match t {
+            Type::Variable(symbol) => fresh.get(symbol.as_str()).cloned(),
+            _ => None,
+        })
+    }
+
+    /// Declares a charm or spell provided by the host, in the current scope. The type variables in
+    /// its type may stand for any type wherever it is used.
+    pub fn declare_native(&mut self, name: &str, ty: &Type) {
+        self.validate_type(ty);
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+        let scheme = Scheme {
+            variables: variables(ty),
+            ty: ty.clone(),
+            definition: None,
+        };
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), scheme);
+    }
+
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn declare(&mut self, symbol: &Symbol, ty: Type) {
+        self.types.symbols.insert(symbol.span, ty.clone());
+        if self.scopes.is_empty() {
+            self.scopes.push(HashMap::new());
+        }
+        let scheme = Scheme {
+            variables: Vec::new(),
+            ty,
+            definition: None,
+        };
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(symbol.name.clone(), scheme);
+    }
+
+    /// The type of a generic charm or spell outside of its definition, where its type parameters
+    /// may stand for any type.
+    fn instantiate_parameters(&mut self, parameters: &[Symbol], ty: &Type) -> Type {
+        let names: Vec<_> = parameters.iter().map(|p| p.name.clone()).collect();
+        self.instantiate(&names, &self.substitution.resolve(ty))
+    }
+
+    /// Makes the symbols bound in the innermost scope generic over the variables in their types
+    /// that nothing else in scope depends on. The variables are given readable names.
+    fn generalize(&mut self, symbols: &[&Symbol]) {
+        let mut bound = Vec::new();
+        for (depth, scope) in self.scopes.iter().enumerate() {
+            for (name, scheme) in scope {
+                let generalized =
+                    depth + 1 == self.scopes.len() && symbols.iter().any(|s| s.as_str() == name);
+                if !generalized {
+                    let ty = self.substitution.resolve(&scheme.ty);
+                    let free = variables(&ty)
+                        .into_iter()
+                        .filter(|v| !scheme.variables.contains(v));
+                    bound.extend(free);
+                }
+            }
+        }
+        for symbol in symbols {
+            let Some(scheme) = self.scopes.last().and_then(|s| s.get(symbol.as_str())) else {
+                continue;
+            };
+            let ty = self.substitution.resolve(&scheme.ty);
+            let present = variables(&ty);
+            let mut names = Vec::new();
+            for variable in &present {
+                if bound.contains(variable) {
+                    continue;
+                }
+                let name = if is_flexible(variable) {
+                    let name = (0..)
+                        .map(variable_name)
+                        .find(|n| !bound.contains(n) && !present.contains(n))
+                        .unwrap();
+                    let rigid = Type::Variable(Symbol::new(name.clone(), Span::default()));
+                    self.substitution.assign(variable, &rigid);
+                    name
+                } else {
+                    variable.clone()
+                };
+                bound.push(name.clone());
+                names.push(name);
+            }
+            let ty = self.substitution.resolve(&ty);
+            let scheme = self
+                .scopes
+                .last_mut()
+                .unwrap()
+                .get_mut(symbol.as_str())
+                .unwrap();
+            scheme.variables = names;
+            scheme.ty = ty;
+        }
+    }
+
+    /// Whether `symbol` refers to a builtin rather than a binding or component.
+    fn builtin(&self, symbol: &Symbol) -> Option<Builtin> {
+        let shadowed = self
+            .scopes
+            .iter()
+            .any(|scope| scope.contains_key(symbol.as_str()));
+        if shadowed {
+            None
+        } else {
+            Builtin::from_name(symbol)
+        }
+    }
+
+    fn lookup(&mut self, symbol: &Symbol) -> Type {
+        let ty = match self.builtin(symbol) {
+            Some(builtin) => {
+                let mut signatures = builtin.signatures();
+                if signatures.len() == 1 {
+                    let signature = signatures.pop().unwrap();
+                    self.instantiate(&variables(&signature), &signature)
+                } else {
+                    Type::Inferred
+                }
+            }
+            None => match self.scheme(symbol).cloned() {
+                Some(scheme) => self.instantiate(&scheme.variables, &scheme.ty),
+                None => Type::Inferred,
+            },
+        };
+        self.types.symbols.insert(symbol.span, ty.clone());
+        ty
+    }
+
+    fn validate_type(&mut self, ty: &Type) {
+        match ty {
+            Type::Inferred
+            | Type::Nil
+            | Type::Simple(_)
+            | Type::Type
+            | Type::Variable(_)
+            | Type::Named(..) => (),
+            Type::Optional(inner) | Type::Sequence(inner) => self.validate_type(inner),
+            // Unbound symbols are reported by the resolver.
+            Type::Symbol(symbol) => match self.scheme(symbol) {
+                Some(Scheme {
+                    definition: Some(_),
+                    ..
+                }) => (),
+                None if self.builtin(symbol).is_none() => (),
+                _ => self.diagnostics.push(Diagnostic::error(
+                    format!("`{symbol}` is not a type; name one by binding it to `type ...`"),
+                    symbol.span,
+                )),
+            },
+            Type::Conjoined(types) => types.iter().for_each(|t| self.validate_type(t)),
+            Type::Record(fields) => {
+                self.distinct_fields(fields.iter().map(|f| &f.symbol));
+                fields.iter().for_each(|f| self.validate_type(&f.ty));
+            }
+            Type::Union(variants) => {
+                let mut seen: Vec<&Symbol> = Vec::new();
+                for variant in variants {
+                    if seen.contains(&&variant.tag) {
+                        self.diagnostics.push(Diagnostic::error(
+                            format!("variant `#{}` is named more than once", variant.tag),
+                            variant.tag.span,
+                        ));
+                    }
+                    seen.push(&variant.tag);
+                    if let Some(payload) = &variant.payload {
+                        self.validate_type(payload);
+                    }
+                }
+            }
+            Type::Charm(CharmType {
+                components,
+                product,
+            })
+            | Type::Spell(SpellType {
+                components,
+                product,
+                ..
+            }) => {
+                components.iter().for_each(|t| self.validate_type(t));
+                self.validate_type(product);
+            }
+        }
+    }
+
+    fn distinct_fields<'a>(&mut self, symbols: impl Iterator<Item = &'a Symbol>) {
+        let mut seen: Vec<&Symbol> = Vec::new();
+        for symbol in symbols {
+            if seen.contains(&symbol) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("field `{symbol}` is named more than once"),
+                    symbol.span,
+                ));
+            }
+            seen.push(symbol);
+        }
+    }
+
+    pub fn binding(&mut self, binding: &ast::Binding) {
+        let manifest = &binding.manifest;
+        if let (Some(symbol), ast::Value::Type(ty)) = (manifest.symbol(), &binding.value) {
+            self.bind_exhaustive(manifest, &Type::Type);
+            self.define(symbol, ty);
+            return;
+        }
+        let recursive = match (manifest.symbol(), signature(&binding.value)) {
+            (Some(symbol), Some(signature)) => {
+                let signature = self.open(&refine(&manifest.ty, &signature));
+                self.declare(symbol, signature.clone());
+                Some(signature)
+            }
+            _ => None,
+        };
+        let actual = self.value(&binding.value);
+        if let Some(signature) = recursive {
+            // Mismatches with the declared type are reported when binding the manifest.
+            self.compatible(&signature, &actual);
+        }
+        self.bind_exhaustive(manifest, &actual);
+        self.generalize(&manifest.symbols());
+    }
+
+    /// Makes `symbol`, which is bound already, name the type `ty`.
+    fn define(&mut self, symbol: &Symbol, ty: &Type) {
+        let scheme = self
+            .scopes
+            .last_mut()
+            .unwrap()
+            .get_mut(symbol.as_str())
+            .unwrap();
+        // Named types are numbered in the order they are defined, so that types of the same name,
+        // or defined at the same span in different sources, stay apart.
+        let id = self.types.named.len();
+        scheme.definition = Some((symbol.clone(), id));
+        self.validate_type(ty);
+        let mut definition = self.named(ty);
+        if !self.substitution.is_contractive(id, &definition) {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "`{symbol}` cannot be defined as itself; make it a variant, sequence, record, \
+                     conjunction, charm or spell of itself instead"
+                ),
+                symbol.span,
+            ));
+            definition = Type::Inferred;
+        }
+        let mut recursive = false;
+        transform(&definition, &mut |t| {
+            recursive |= matches!(t, Type::Named(_, n) if *n == id);
+            None
+        });
+        self.types.named.push(NamedType {
+            symbol: symbol.clone(),
+            ty: definition.clone(),
+            recursive,
+        });
+        self.substitution.define(id, definition);
+    }
+
+    fn components(&mut self, components: &[ast::Manifest]) -> Vec<Type> {
+        components
+            .iter()
+            .map(|manifest| {
+                let ty = self.open(&declared_type(manifest));
+                self.bind_exhaustive(manifest, &ty);
+                ty
+            })
+            .collect()
+    }
+
+    /// Whether a type is a variable that inference has not solved yet.
+    fn is_unsolved(&self, ty: &Type) -> bool {
+        matches!(self.substitution.shallow(ty), Type::Variable(v) if is_flexible(v.as_str()))
+    }
+
+    /// Binds the symbols of a manifest to the parts of a value of type `actual`. Returns whether
+    /// the pattern is refutable, that is, whether some value of that type would not match it.
+    fn bind(&mut self, manifest: &ast::Manifest, actual: &Type) -> bool {
+        self.validate_type(&manifest.ty);
+        self.span = manifest.span;
+        let declared = self.open(&manifest.ty);
+        self.expect(&declared, actual, || {
+            format!("mismatched type for `{}`", manifest.pattern)
+        });
+        let mut ty = self.substitution.shallow(&declared);
+        if !matches!(manifest.pattern, ast::Pattern::Symbol(_)) {
+            ty = self.substitution.unfold(&ty);
+        }
+        match &manifest.pattern {
+            ast::Pattern::Symbol(symbol) => {
+                self.declare(symbol, ty);
+                false
+            }
+            ast::Pattern::Wildcard => false,
+            ast::Pattern::Conjunction(manifests) => {
+                let parts = match ty {
+                    Type::Conjoined(types) if types.len() == manifests.len() => types,
+                    Type::Inferred => vec![Type::Inferred; manifests.len()],
+                    ty if self.is_unsolved(&ty) => {
+                        let parts: Vec<_> = manifests
+                            .iter()
+                            .map(|_| self.substitution.fresh())
+                            .collect();
+                        self.compatible(&ty, &Type::Conjoined(parts.clone()));
+                        parts
+                    }
+                    other => {
+                        let other = self.display(&other);
+                        self.error(format!(
+                            "cannot take {other} apart into {} values",
+                            manifests.len()
+                        ));
+                        vec![Type::Inferred; manifests.len()]
+                    }
+                };
+                let mut refutable = false;
+                for (manifest, part) in manifests.iter().zip(&parts) {
+                    refutable |= self.bind(manifest, part);
+                }
+                refutable
+            }
+            ast::Pattern::Optional(inner) => {
+                let content = match ty {
+                    Type::Optional(content) => *content,
+                    Type::Inferred => Type::Inferred,
+                    ty if self.is_unsolved(&ty) => {
+                        let content = self.substitution.fresh();
+                        self.compatible(&ty, &Type::Optional(Box::new(content.clone())));
+                        content
+                    }
+                    other => {
+                        let other = self.display(&other);
+                        self.error(format!(
+                            "`{}` can only match an optional value, found {other}",
+                            manifest.pattern
+                        ));
+                        Type::Inferred
+                    }
+                };
+                self.bind(inner, &content);
+                true
+            }
+            ast::Pattern::Variant { tag, payload } => {
+                let (content, refutable) = match &ty {
+                    Type::Union(variants) => match variant_type(variants, tag) {
+                        Some(variant) => (variant.payload.clone(), variants.len() > 1),
+                        None => {
+                            let ty = self.display(&ty);
+                            self.error(format!("`#{tag}` is not a variant of {ty}"));
+                            (Some(Type::Inferred), true)
+                        }
+                    },
+                    Type::Inferred => (Some(Type::Inferred), true),
+                    ty if self.is_unsolved(ty) => {
+                        let payload = payload.as_ref().map(|_| self.substitution.fresh());
+                        let variant = Type::Union(vec![VariantType {
+                            tag: tag.clone(),
+                            payload: payload.clone(),
+                        }]);
+                        self.compatible(ty, &variant);
+                        (payload, false)
+                    }
+                    other => {
+                        let other = self.display(other);
+                        self.error(format!(
+                            "`{}` can only match a tagged value, found {other}",
+                            manifest.pattern
+                        ));
+                        (Some(Type::Inferred), true)
+                    }
+                };
+                let Some(payload) = payload else {
+                    return refutable;
+                };
+                let content = content.unwrap_or_else(|| {
+                    self.error(format!("`#{tag}` carries no payload"));
+                    Type::Inferred
+                });
+                self.bind(payload, &content) || refutable
+            }
+        }
+    }
+
+    /// Binds a manifest where every value must match it.
+    fn bind_exhaustive(&mut self, manifest: &ast::Manifest, actual: &Type) {
+        if self.bind(manifest, actual) {
+            let actual = self.display(actual);
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "refutable pattern `{}` does not match every value of {actual}; divine it \
+                     instead",
+                    manifest.pattern
+                ),
+                manifest.span,
+            ));
+        }
+    }
+
+    pub fn value(&mut self, value: &ast::Value) -> Type {
+        match value {
+            ast::Value::Element(element) => element_type(element),
+            ast::Value::Symbol(symbol) => self.lookup(symbol),
+            ast::Value::Boundary(boundary) => self.scoped(|c| c.boundary(boundary)),
+            ast::Value::Conjunction(values) => {
+                Type::Conjoined(values.iter().map(|v| self.value(v)).collect())
+            }
+            ast::Value::Record(fields) => {
+                self.distinct_fields(fields.iter().map(|f| &f.symbol));
+                let types = fields
+                    .iter()
+                    .map(|field| {
+                        let ty = self.value(&field.value);
+                        self.types.symbols.insert(field.symbol.span, ty.clone());
+                        FieldType {
+                            symbol: field.symbol.clone(),
+                            ty,
+                        }
+                    })
+                    .collect();
+                Type::Record(types)
+            }
+            ast::Value::Projection(projection) => {
+                let record = self.value(&projection.record);
+                let record = self.substitution.unfold(&record);
+                let field = &projection.field;
+                let ty = match &record {
+                    Type::Record(fields) => field_type(fields, field).cloned(),
+                    // Records are not inferred from the fields projected out of them.
+                    Type::Inferred => Some(Type::Inferred),
+                    record if self.is_unsolved(record) => Some(Type::Inferred),
+                    _ => None,
+                };
+                let ty = ty.unwrap_or_else(|| {
+                    let record = self.display(&record);
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("{record} has no field `{field}`"),
+                        field.span,
+                    ));
+                    Type::Inferred
+                });
+                self.types.symbols.insert(field.span, ty.clone());
+                ty
+            }
+            ast::Value::Sequence(sequence) => {
+                let mut element = self.substitution.fresh();
+                for (i, value) in sequence.elements.iter().enumerate() {
+                    let actual = self.value(value);
+                    element = self.unite(&element, &actual).unwrap_or_else(|| {
+                        let (expected, actual) = (self.display(&element), self.display(&actual));
+                        self.diagnostics.push(Diagnostic::error(
+                            format!(
+                                "mismatched element {} of the sequence: expected {expected}, \
+                                 found {actual}",
+                                i + 1
+                            ),
+                            sequence.span,
+                        ));
+                        element.clone()
+                    });
+                }
+                Type::Sequence(Box::new(element))
+            }
+            ast::Value::Variant(variant) => {
+                let payload = variant.payload.as_ref().map(|v| self.value(v));
+                let ty = Type::Union(vec![VariantType {
+                    tag: variant.tag.clone(),
+                    payload,
+                }]);
+                self.types.symbols.insert(variant.tag.span, ty.clone());
+                ty
+            }
+            ast::Value::Charm(charm) => {
+                let ty = self.scoped(|c| {
+                    let components = c.components(&charm.components);
+                    let product = c.scoped(|c| c.boundary(&charm.boundary));
+                    Type::Charm(CharmType {
+                        components,
+                        product: Box::new(product),
+                    })
+                });
+                self.instantiate_parameters(&charm.parameters, &ty)
+            }
+            ast::Value::Invocation(invocation) => {
+                let components: Vec<_> = invocation
+                    .components
+                    .iter()
+                    .map(|v| self.value(v))
+                    .collect();
+                let callee = match &*invocation.charm {
+                    ast::Value::Symbol(symbol) => Some(symbol),
+                    _ => None,
+                };
+                self.call(Callee::Charm, callee, components, |c| {
+                    c.value(&invocation.charm)
+                })
+            }
+            ast::Value::Spell(spell) => {
+                let infallible = std::mem::replace(&mut self.infallible, !spell.fails);
+                let used = std::mem::take(&mut self.used);
+                let declared = std::mem::replace(&mut self.declared, spell.capabilities.clone());
+                let ty = self.scoped(|c| {
+                    let components = c.components(&spell.components);
+                    let product = c.scoped(|c| c.actions(&spell.actions, &spell.ty));
+                    Type::Spell(SpellType {
+                        components,
+                        product: Box::new(product),
+                        fails: spell.fails,
+                        // Casts beyond the declared capabilities are reported where they occur.
+                        capabilities: spell.capabilities.clone().unwrap_or_else(|| c.used.clone()),
+                    })
+                });
+                self.infallible = infallible;
+                self.used = used;
+                self.declared = declared;
+                self.instantiate_parameters(&spell.parameters, &ty)
+            }
+            ast::Value::Type(ty) => {
+                self.validate_type(ty);
+                Type::Type
+            }
+            ast::Value::Divination(divination) => self.divination(divination),
+            ast::Value::Interpolation(interpolation) => self.interpolation(interpolation),
+        }
+    }
+
+    fn divination(&mut self, divination: &ast::Divination) -> Type {
+        // Without a subject, only `_` arms count towards exhaustiveness, which the subject's type
+        // being unknown achieves.
+        let subject = match &divination.subject {
+            Some(subject) => self.value(subject),
+            None => Type::Inferred,
+        };
+        if self.is_unsolved(&subject) {
+            // A subject of unknown type is taken to be of the type the patterns of the arms match.
+            let mut matched: Option<Type> = None;
+            for arm in &divination.arms {
+                if let ast::Omen::Pattern(manifest) = &arm.omen {
+                    let ty = self.open(&declared_type(manifest));
+                    if self.is_unsolved(&ty) {
+                        continue;
+                    }
+                    matched = match matched {
+                        None => Some(ty),
+                        Some(matched) => Some(self.unite(&matched, &ty).unwrap_or(matched)),
+                    };
+                }
+            }
+            if let Some(matched) = matched {
+                self.compatible(&subject, &matched);
+            }
+        }
+        let resolved = self.substitution.resolve(&subject);
+        let types = std::slice::from_ref(&resolved);
+        let mut product: Option<Type> = None;
+        let mut rows: Vec<Vec<Shape>> = Vec::new();
+        for arm in &divination.arms {
+            let shape = match &arm.omen {
+                ast::Omen::Pattern(manifest) => Shape::of(manifest),
+                _ => Shape::Any,
+            };
+            if !is_useful(&rows, &[shape], types, &self.substitution) {
+                self.diagnostics
+                    .push(Diagnostic::warning("unreachable arm", arm.span));
+            }
+            let actual = match &arm.omen {
+                ast::Omen::Truth(value) => {
+                    let omen = self.value(value);
+                    if !self.compatible(&Type::Simple(SimpleType::Truth), &omen) {
+                        let omen = self.display(&omen);
+                        self.diagnostics.push(Diagnostic::error(
+                            format!("mismatched omen: expected truth, found {omen}"),
+                            arm.span,
+                        ));
+                    }
+                    self.value(&arm.value)
+                }
+                ast::Omen::Presence(presence) => {
+                    let manifest = &presence.manifest;
+                    let value = self.value(&presence.value);
+                    let (content, optional) = match self.substitution.unfold(&value) {
+                        Type::Optional(inner) => (*inner, true),
+                        Type::Inferred => (Type::Inferred, true),
+                        ty if self.is_unsolved(&ty) => {
+                            let content = self.substitution.fresh();
+                            self.compatible(&ty, &Type::Optional(Box::new(content.clone())));
+                            (content, true)
+                        }
+                        other => (other, false),
+                    };
+                    self.scoped(|c| {
+                        if !c.bind(manifest, &content) && !optional {
+                            let content = c.display(&content);
+                            c.diagnostics.push(Diagnostic::error(
+                                format!(
+                                    "`{}` can only be divined from an optional value or with a \
+                                     refutable pattern, found {content}",
+                                    manifest.pattern
+                                ),
+                                manifest.span,
+                            ));
+                        }
+                        c.value(&arm.value)
+                    })
+                }
+                ast::Omen::Pattern(manifest) => {
+                    if divination.subject.is_none() {
+                        self.diagnostics.push(Diagnostic::error(
+                            "patterns can only be divined from a subject",
+                            manifest.span,
+                        ));
+                    }
+                    rows.push(vec![Shape::of(manifest)]);
+                    self.scoped(|c| {
+                        c.bind(manifest, &subject);
+                        c.value(&arm.value)
+                    })
+                }
+                ast::Omen::Otherwise => {
+                    rows.push(vec![Shape::Any]);
+                    self.value(&arm.value)
+                }
+            };
+            product = match product {
+                None => Some(actual),
+                Some(product) => Some(self.unite(&product, &actual).unwrap_or_else(|| {
+                    let (product, actual) = (self.display(&product), self.display(&actual));
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("mismatched arm: expected {product}, found {actual}"),
+                        arm.span,
+                    ));
+                    Type::Inferred
+                })),
+            };
+        }
+        if is_useful(&rows, &[Shape::Any], types, &self.substitution) {
+            let subject = self.display(&subject);
+            let message = match &divination.subject {
+                Some(_) => format!(
+                    "divination is not exhaustive; match every value of {subject} or end it with \
+                     a `_` arm"
+                ),
+                None => "divination is not exhaustive; end it with a `_` arm".to_string(),
+            };
+            self.diagnostics
+                .push(Diagnostic::error(message, divination.span));
+        }
+        product.unwrap_or(Type::Inferred)
+    }
+
+    fn boundary(&mut self, boundary: &ast::Boundary) -> Type {
+        self.validate_type(&boundary.ty);
+        boundary.bindings.iter().for_each(|b| self.binding(b));
+        let actual = self.value(&boundary.value);
+        let declared = self.open(&boundary.ty);
+        self.expect(&declared, &actual, || "mismatched product".to_string());
+        declared
+    }
+
+    /// Checks the actions of a spell, returning its product type.
+    fn actions(&mut self, actions: &[ast::Action], declared: &Type) -> Type {
+        self.validate_type(declared);
+        let mut product = Type::Nil;
+        for action in actions {
+            product = self.check_action(action);
+        }
+        if *declared == Type::Nil {
+            return Type::Nil;
+        }
+        let declared = self.open(declared);
+        self.expect(&declared, &product, || {
+            "mismatched spell product".to_string()
+        });
+        declared
+    }
+
+    /// Checks an action, returning the type of its product. The product may have any type in
+    /// the parts that nothing determines, which are named as type variables.
+    pub fn action(&mut self, action: &ast::Action) -> Type {
+        let ty = self.check_action(action);
+        let ty = self.substitution.resolve(&ty);
+        let present = variables(&ty);
+        let mut names = (0..).map(variable_name).filter(|n| !present.contains(n));
+        let mut named: HashMap<String, Type> = HashMap::new();
+        transform(&ty, &mut |t| match t {
+            Type::Variable(symbol) if is_flexible(symbol.as_str()) => Some(
+                named
+                    .entry(symbol.name.clone())
+                    .or_insert_with(|| {
+                        let name = names.next().unwrap();
+                        Type::Variable(Symbol::new(name, Span::default()))
+                    })
+                    .clone(),
+            ),
+            _ => None,
+        })
+    }
+
+    fn check_action(&mut self, action: &ast::Action) -> Type {
+        match action {
+            ast::Action::Value(value) => self.value(value),
+            ast::Action::Cast(cast) => self.cast(cast),
+            ast::Action::Binding(binding) => {
+                self.binding(binding);
+                Type::Nil
+            }
+            ast::Action::Channel(channel) => {
+                let actual = self.cast(&channel.cast);
+                self.bind_exhaustive(&channel.manifest, &actual);
+                Type::Nil
+            }
+            ast::Action::Repetition(repetition) => {
+                let natural = Type::Simple(SimpleType::NaturalCount);
+                let count = self.value(&repetition.count);
+                if !self.compatible(&natural, &count) {
+                    let count = self.display(&count);
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("mismatched repetition count: expected natural, found {count}"),
+                        repetition.span,
+                    ));
+                }
+                self.scoped(|c| {
+                    if let Some(counter) = &repetition.counter {
+                        c.bind_exhaustive(counter, &natural);
+                    }
+                    repetition.actions.iter().for_each(|a| {
+                        c.check_action(a);
+                    });
+                });
+                Type::Nil
+            }
+        }
+    }
+
+    fn cast(&mut self, cast: &ast::Cast) -> Type {
+        // Within a recovered cast, failures are caught by the recovery.
+        let infallible = self.infallible && cast.recovery.is_none();
+        let infallible = std::mem::replace(&mut self.infallible, infallible);
+        let components: Vec<_> = cast
+            .components
+            .iter()
+            .map(|a| self.check_action(a))
+            .collect();
+        let callee = match &*cast.spell {
+            ast::Action::Value(ast::Value::Symbol(symbol)) => Some(symbol),
+            _ => None,
+        };
+        let product = self.call(Callee::Spell, callee, components, |c| {
+            c.check_action(&cast.spell)
+        });
+        self.infallible = infallible;
+        match &cast.recovery {
+            Some(recovery) => self.recovery(recovery, product),
+            None => product,
+        }
+    }
+
+    /// Checks the recovery of a cast whose product has type `product`. The product of the recovery
+    /// must agree with it, since either may be the product of the recovered cast.
+    fn recovery(&mut self, recovery: &ast::Recovery, product: Type) -> Type {
+        let recovered = self.scoped(|c| {
+            if let Some(failure) = &recovery.failure {
+                c.bind_exhaustive(failure, &Type::Simple(SimpleType::Phrase));
+            }
+            c.actions(&recovery.actions, &Type::Inferred)
+        });
+        match self.substitution.unite(&product, &recovered) {
+            Some(ty) => ty,
+            None => {
+                let (product, recovered) = (self.display(&product), self.display(&recovered));
+                self.diagnostics.push(Diagnostic::error(
+                    format!("mismatched recovery product: the cast has {product}, but the recovery has {recovered}"),
+                    recovery.span,
+                ));
+                Type::Inferred
+            }
+        }
+    }
+
+    /// The first overload of `builtin` that accepts the components, instantiated, and its product.
+    fn overload(
+        &mut self,
+        builtin: Builtin,
+        kind: Callee,
+        components: &[Type],
+    ) -> Option<(Type, Type)> {
+        for signature in builtin.signatures() {
+            let signature = self.instantiate(&variables(&signature), &signature);
+            let Some((expected, product)) = signature_parts(&signature, kind) else {
+                continue;
+            };
+            let saved = self.substitution.clone();
+            if self.substitution.all_compatible(expected, components) {
+                let product = product.clone();
+                return Some((signature, product));
+            }
+            self.substitution = saved;
+        }
+        None
+    }
+
+    /// Checks the values embedded in a phrase as the components of the builtins they stand for.
+    fn interpolation(&mut self, interpolation: &ast::Interpolation) -> Type {
+        let phrase = Type::Simple(SimpleType::Phrase);
+        for piece in &interpolation.pieces {
+            let ast::Piece::Embed(embed) = piece else {
+                continue;
+            };
+            let ty = self.value(&embed.value);
+            self.span = interpolation.span;
+            let precision = embed.spec.is_some_and(|spec| spec.precision.is_some());
+            if precision && !self.compatible(&Type::Simple(SimpleType::Amount), &ty) {
+                let ty = self.display(&ty);
+                self.error(format!(
+                    "{ty} cannot be written with a precision; only amounts can"
+                ));
+                continue;
+            }
+            let (builtin, components) = match embed.spec {
+                Some(_) => (Builtin::Format, vec![ty, phrase.clone()]),
+                None => (Builtin::Describe, vec![ty]),
+            };
+            if self.overload(builtin, Callee::Charm, &components).is_none() {
+                let ty = self.display(&components[0]);
+                self.error(match builtin {
+                    Builtin::Format => format!("cannot format {ty}; only counts and amounts can"),
+                    _ => format!("cannot embed {ty} in a phrase"),
+                });
+            }
+        }
+        phrase
+    }
+
+    /// Checks that the spell being checked may have the effects of casting a spell of type
+    /// `callee`, and notes the capabilities it uses.
+    fn effects(&mut self, name: &str, callee: &Type) {
+        let Type::Spell(spell) = callee else {
+            return;
+        };
+        if spell.fails && self.infallible {
+            self.error(format!(
+                "{name} may fail; recover the cast, or declare the spell casting it with `fails`"
+            ));
+        }
+        if let Some(declared) = &self.declared {
+            let undeclared: Vec<_> = spell.capabilities.difference(declared).collect();
+            if let Some(capability) = undeclared.first() {
+                self.error(format!(
+                    "{name} uses the {capability} capability, which the spell casting it does not \
+                     declare"
+                ));
+            }
+        }
+        self.used.extend(spell.capabilities.iter().copied());
+    }
+
+    /// Checks an invocation or cast, returning the type of its product.
+    ///
+    /// `symbol` is the callee if it is a plain symbol, which allows overloaded builtins to be
+    /// resolved; `callee_type` checks the callee otherwise.
+    fn call(
+        &mut self,
+        kind: Callee,
+        symbol: Option<&Symbol>,
+        components: Vec<Type>,
+        callee_type: impl FnOnce(&mut Self) -> Type,
+    ) -> Type {
+        if let Some(symbol) = symbol {
+            self.span = symbol.span;
+        }
+        let verb = match kind {
+            Callee::Charm => "invoke",
+            Callee::Spell => "cast",
+        };
+        let name = symbol.map_or("this".to_string(), |s| format!("`{s}`"));
+
+        // Overloaded builtins take the first signature that accepts the components. Others are
+        // checked like any charm or spell.
+        let overloads = symbol
+            .and_then(|s| self.builtin(s))
+            .filter(|b| b.signatures().len() > 1);
+        if let Some(builtin) = overloads {
+            if let Some((signature, product)) = self.overload(builtin, kind, &components) {
+                if kind == Callee::Spell {
+                    self.effects(&name, &signature);
+                }
+                self.types.symbols.insert(symbol.unwrap().span, signature);
+                return product;
+            }
+            if builtin.is_spell() != (kind == Callee::Spell) {
+                self.error(wrong_callee(&name, kind));
+            } else {
+                let components = self.display(&Type::Conjoined(components));
+                self.error(format!(
+                    "no variant of {name} accepts the components {components}"
+                ));
+            }
+            return Type::Inferred;
+        }
+
+        let callee = callee_type(self);
+        if let Some(symbol) = symbol {
+            self.span = symbol.span;
+        }
+        let callee = self.substitution.unfold(&callee);
+        if self.is_unsolved(&callee) {
+            let product = self.substitution.fresh();
+            let signature = match kind {
+                Callee::Charm => Type::Charm(CharmType {
+                    components,
+                    product: Box::new(product.clone()),
+                }),
+                // Nothing tells what the spell does, so it may do anything the spell casting it
+                // may do.
+                Callee::Spell => Type::Spell(SpellType {
+                    components,
+                    product: Box::new(product.clone()),
+                    fails: !self.infallible,
+                    capabilities: self
+                        .declared
+                        .clone()
+                        .unwrap_or_else(|| Capability::ALL.into()),
+                }),
+            };
+            if !self.compatible(&callee, &signature) {
+                self.error(format!(
+                    "cannot {verb} {name} with these components, as its type would contain itself"
+                ));
+            }
+            self.effects(&name, &signature);
+            return product;
+        }
+        if kind == Callee::Spell {
+            self.effects(&name, &callee);
+        }
+        match (kind, &callee) {
+            (_, Type::Inferred) => Type::Inferred,
+            (
+                Callee::Charm,
+                Type::Charm(CharmType {
+                    components: expected,
+                    product,
+                }),
+            )
+            | (
+                Callee::Spell,
+                Type::Spell(SpellType {
+                    components: expected,
+                    product,
+                    ..
+                }),
+            ) => {
+                if expected.len() != components.len() {
+                    self.error(format!(
+                        "{name} takes {} components, but {} were given",
+                        expected.len(),
+                        components.len()
+                    ));
+                } else {
+                    for (i, (expected, actual)) in expected.iter().zip(&components).enumerate() {
+                        self.expect(expected, actual, || {
+                            format!("mismatched component {} of {name}", i + 1)
+                        });
+                    }
+                }
+                (**product).clone()
+            }
+            (_, Type::Charm(_) | Type::Spell(_)) => {
+                self.error(wrong_callee(&name, kind));
+                Type::Inferred
+            }
+            (_, other) => {
+                let other = self.display(other);
+                self.error(format!("cannot {verb} {name}, which has type {other}"));
+                Type::Inferred
+            }
+        }
+    }
+}
+
+/// The type of a charm or spell as far as it is declared, which is all that a recursive reference
+/// to it can rely on.
+fn signature(value: &ast::Value) -> Option<Type> {
+    let components = |manifests: &[ast::Manifest]| manifests.iter().map(declared_type).collect();
+    match value {
+        ast::Value::Charm(charm) => Some(Type::Charm(CharmType {
+            components: components(&charm.components),
+            product: Box::new(charm.boundary.ty.clone()),
+        })),
+        ast::Value::Spell(spell) => Some(Type::Spell(SpellType {
+            components: components(&spell.components),
+            product: Box::new(spell.ty.clone()),
+            fails: spell.fails,
+            capabilities: spell.capabilities.clone().unwrap_or_default(),
+        })),
+        _ => None,
+    }
+}
+
+/// The type of the values a manifest matches, as far as it or its pattern declares it.
+fn declared_type(manifest: &ast::Manifest) -> Type {
+    let from_pattern = match &manifest.pattern {
+        ast::Pattern::Symbol(_) | ast::Pattern::Wildcard => Type::Inferred,
+        ast::Pattern::Conjunction(manifests) => {
+            Type::Conjoined(manifests.iter().map(declared_type).collect())
+        }
+        ast::Pattern::Optional(inner) => Type::Optional(Box::new(declared_type(inner))),
+        ast::Pattern::Variant { tag, payload } => Type::Union(vec![VariantType {
+            tag: tag.clone(),
+            payload: payload.as_deref().map(declared_type),
+        }]),
+    };
+    refine(&manifest.ty, &from_pattern)
+}
+
+/// The structure of a pattern, as far as exhaustiveness is concerned.
+#[derive(Debug, Clone)]
+enum Shape {
+    /// Matches every value.
+    Any,
+    /// Matches the values built by the named constructor whose parts match the shapes.
+    Constructor(String, Vec<Shape>),
+}
+
+impl Shape {
+    fn of(manifest: &ast::Manifest) -> Self {
+        match &manifest.pattern {
+            ast::Pattern::Symbol(_) | ast::Pattern::Wildcard => Self::Any,
+            ast::Pattern::Conjunction(manifests) => {
+                Self::Constructor("()".to_string(), manifests.iter().map(Self::of).collect())
+            }
+            ast::Pattern::Optional(inner) => {
+                Self::Constructor("?".to_string(), vec![Self::of(inner)])
+            }
+            ast::Pattern::Variant { tag, payload } => Self::Constructor(
+                format!("#{tag}"),
+                payload.iter().map(|m| Self::of(m)).collect(),
+            ),
+        }
+    }
+}
+
+/// The constructors that together build every value of a type, with the types of their parts.
+/// Values of other types can only be matched as a whole. Optional values are built like a union
+/// of a present variant and nil.
+fn constructors(ty: &Type) -> Option<Vec<(String, Vec<Type>)>> {
+    match ty {
+        Type::Union(variants) => Some(
+            variants
+                .iter()
+                .map(|v| (format!("#{}", v.tag), v.payload.iter().cloned().collect()))
+                .collect(),
+        ),
+        Type::Optional(inner) => Some(vec![
+            ("?".to_string(), vec![(**inner).clone()]),
+            ("nil".to_string(), Vec::new()),
+        ]),
+        Type::Conjoined(types) => Some(vec![("()".to_string(), types.clone())]),
+        _ => None,
+    }
+}
+
+/// Whether some values of the given types match `row` but none of `rows`. Named types are unfolded
+/// by `substitution`.
+fn is_useful(
+    rows: &[Vec<Shape>],
+    row: &[Shape],
+    types: &[Type],
+    substitution: &Substitution,
+) -> bool {
+    if rows.is_empty() {
+        return true;
+    }
+    let Some((head, tail)) = row.split_first() else {
+        return false;
+    };
+    let (ty, types) = types.split_first().expect("a type for every column");
+    let Some(constructors) = constructors(&substitution.unfold(ty)) else {
+        // Where the type is unknown, mistakes have been reported already, so every pattern is
+        // taken to match.
+        let rows: Vec<_> = rows.iter().map(|r| r[1..].to_vec()).collect();
+        return is_useful(&rows, tail, types, substitution);
+    };
+    let specialized = |name: &str, parts: &[Type]| {
+        let rows: Vec<_> = rows
+            .iter()
+            .filter_map(|r| specialize(r, name, parts.len()))
+            .collect();
+        let types: Vec<_> = parts.iter().chain(types).cloned().collect();
+        (rows, types)
+    };
+    match head {
+        Shape::Constructor(name, _) => {
+            let parts = constructors
+                .iter()
+                .find(|(n, _)| n == name)
+                .map_or(Vec::new(), |(_, parts)| parts.clone());
+            let Some(row) = specialize(row, name, parts.len()) else {
+                return false;
+            };
+            let (rows, types) = specialized(name, &parts);
+            is_useful(&rows, &row, &types, substitution)
+        }
+        Shape::Any => {
+            let complete = constructors.iter().all(|(name, _)| {
+                rows.iter()
+                    .any(|r| matches!(&r[0], Shape::Constructor(n, _) if n == name))
+            });
+            if !complete {
+                // Some constructor is matched only by the rows that match everything.
+                let rows: Vec<_> = rows
+                    .iter()
+                    .filter(|r| matches!(r[0], Shape::Any))
+                    .map(|r| r[1..].to_vec())
+                    .collect();
+                return is_useful(&rows, tail, types, substitution);
+            }
+            constructors.iter().any(|(name, parts)| {
+                let row: Vec<_> = std::iter::repeat_n(Shape::Any, parts.len())
+                    .chain(tail.iter().cloned())
+                    .collect();
+                let (rows, types) = specialized(name, parts);
+                is_useful(&rows, &row, &types, substitution)
+            })
+        }
+    }
+}
+
+/// The row for the parts of values built by the named constructor, if its first shape can match
+/// them.
+fn specialize(row: &[Shape], name: &str, arity: usize) -> Option<Vec<Shape>> {
+    let (head, tail) = row.split_first()?;
+    let parts = match head {
+        Shape::Any => vec![Shape::Any; arity],
+        Shape::Constructor(n, parts) if n == name => {
+            let mut parts = parts.clone();
+            parts.resize(arity, Shape::Any);
+            parts
+        }
+        Shape::Constructor(..) => return None,
+    };
+    Some(parts.into_iter().chain(tail.iter().cloned()).collect())
+}
+
+fn signature_parts(signature: &Type, kind: Callee) -> Option<(&[Type], &Type)> {
+    match (kind, signature) {
+        (Callee::Charm, Type::Charm(charm)) => Some((&charm.components, &charm.product)),
+        (Callee::Spell, Type::Spell(spell)) => Some((&spell.components, &spell.product)),
+        _ => None,
+    }
+}
+
+fn wrong_callee(name: &str, kind: Callee) -> String {
+    match kind {
+        Callee::Charm => format!("{name} is a spell; cast it with `!` instead"),
+        Callee::Spell => format!("{name} is a charm; invoke it without `!` instead"),
+    }
+}
+
+pub fn element_type(element: &ast::Element) -> Type {
+    match element {
+        ast::Element::Nil => Type::Nil,
+        ast::Element::Truth(_) => Type::Simple(SimpleType::Truth),
+        ast::Element::NaturalCount(_) => Type::Simple(SimpleType::NaturalCount),
+        ast::Element::WholeCount(_) => Type::Simple(SimpleType::WholeCount),
+        ast::Element::Amount(_) => Type::Simple(SimpleType::Amount),
+        ast::Element::Phrase(_) => Type::Simple(SimpleType::Phrase),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax;
+
+    fn check_source(source: &str) -> (Types, Vec<Diagnostic>) {
+        check(&syntax::parse(source).unwrap())
+    }
+
+    /// The type of the binding of `name` in `source`.
+    fn type_of(source: &str, name: &str) -> String {
+        let (types, diagnostics) = check_source(source);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let start = source.find(&format!("{name} =")).unwrap();
+        let ty = types
+            .symbols
+            .iter()
+            .find(|(span, _)| span.start == start)
+            .unwrap()
+            .1;
+        ty.to_string()
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        check_source(source)
+            .1
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn inferred_charms_are_generalised() {
+        let source = "id = charm(x) { x };\na = id(1);\nb = id(\"one\");";
+        assert_eq!(type_of(source, "id"), "charm(A) -> A");
+        assert_eq!(type_of(source, "a"), "natural");
+        assert_eq!(type_of(source, "b"), "phrase");
+        let source = "k = charm(x) { charm(y) { x } };\nc = k(1)(\"one\");";
+        assert_eq!(type_of(source, "k"), "charm(A) -> charm(B) -> A");
+        assert_eq!(type_of(source, "c"), "natural");
+    }
+
+    #[test]
+    fn type_parameters_are_instantiated_at_each_use() {
+        let source = "pair = charm[T](x: T) -> (T, T) { (x, x) };\n\
+                      a = pair(2.0);\n\
+                      b = pair((1, \"one\"));";
+        assert_eq!(type_of(source, "a"), "(amount, amount)");
+        assert_eq!(
+            type_of(source, "b"),
+            "((natural, phrase), (natural, phrase))"
+        );
+    }
+
+    #[test]
+    fn refutable_bindings_name_the_uncovered_type() {
+        let found = errors(
+            "Shape = type #dot | #square(natural);\n\
+             t: Shape = #dot;\n\
+             #square(n) = t;",
+        );
+        assert_eq!(
+            found,
+            ["refutable pattern `#square(n)` does not match every value of Shape; divine it instead"]
+        );
+        let found = errors("m: natural? = 1;\nn? = m;");
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].contains("every value of natural?"), "{}", found[0]);
+    }
+
+    #[test]
+    fn undeclared_capabilities_are_errors() {
+        let found = errors("quiet = spell() -> nil uses [] { utter!(\"hi\"); };");
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].contains("console capability"), "{}", found[0]);
+        assert!(errors("loud = spell() -> nil uses [console] { utter!(\"hi\"); };").is_empty());
+        let source = "loud = spell() -> nil { utter!(\"hi\"); t = now!(); };";
+        assert_eq!(
+            type_of(source, "loud"),
+            "spell() -> nil uses [console, clock]"
+        );
+    }
+
+    #[test]
+    fn capabilities_propagate_through_spell_components() {
+        let source = "
+            twice = spell(s: spell() -> nil uses [console]) -> nil { s!(); s!(); };
+            hello = spell() -> nil { utter!(\"hi\"); };
+        ";
+        assert_eq!(
+            type_of(source, "twice"),
+            "spell(spell() -> nil uses [console]) -> nil uses [console]"
+        );
+        let found = errors(&format!(
+            "{source}pure = spell() -> nil uses [] {{ twice!(hello); }};"
+        ));
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert!(found[0].contains("console capability"), "{}", found[0]);
+        // A spell component that uses a capability does not fit where none is allowed.
+        let found = errors(&format!(
+            "{source}never = spell(s: spell() -> nil) -> nil {{ s!(); }};\n\
+             * = spell() -> nil {{ never!(hello); }};"
+        ));
+        assert_eq!(found.len(), 1, "{found:?}");
+    }
+
+    const SHAPE: &str = "Shape = type #dot | #square(natural) | #disc(amount);\n";
+
+    fn not_exhaustive(source: &str) -> bool {
+        let found = errors(source);
+        assert!(found.len() <= 1, "{found:?}");
+        found
+            .iter()
+            .any(|message| message.starts_with("divination is not exhaustive"))
+    }
+
+    #[test]
+    fn divinations_must_be_exhaustive() {
+        assert!(not_exhaustive(
+            "f = charm(p: (natural?, truth)) -> natural { divine p { (n?, _) => n } };"
+        ));
+        assert!(!not_exhaustive(
+            "f = charm(p: (natural?, truth)) -> natural { divine p { (n?, _) => n, _ => 0 } };"
+        ));
+        // `_?` covers a present value whatever it holds, but not an absent one.
+        assert!(not_exhaustive(
+            "g = charm(p: natural??) -> natural { divine p { n?? => n, _? => 1 } };"
+        ));
+        assert!(!not_exhaustive(
+            "g = charm(p: natural??) -> natural { divine p { n?? => n, _? => 1, _ => 2 } };"
+        ));
+        assert!(not_exhaustive(&format!(
+            "{SHAPE}h = charm(s: Shape) -> natural {{ divine s {{ #dot => 0, #square(n) => n }} }};"
+        )));
+        assert!(!not_exhaustive(&format!(
+            "{SHAPE}h = charm(s: Shape) -> natural {{\n\
+                 divine s {{ #dot => 0, #square(n) => n, #disc(_) => 1 }}\n\
+             }};"
+        )));
+    }
+
+    #[test]
+    fn unreachable_arms_are_warned_about() {
+        let unreachable = |source: &str| {
+            let (_, diagnostics) = check_source(source);
+            assert!(diagnostics.iter().all(|d| !d.is_error()), "{diagnostics:?}");
+            let warnings = diagnostics
+                .iter()
+                .filter(|d| d.message == "unreachable arm");
+            warnings
+                .map(|d| &source[d.span.start..d.span.end])
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        assert_eq!(
+            unreachable(&format!(
+                "{SHAPE}k = charm(s: Shape) -> natural {{ divine s {{ #dot => 0, #dot => 1, _ => 2 }} }};"
+            )),
+            "#dot => 1"
+        );
+        assert_eq!(
+            unreachable(
+                "g = charm(p: natural??) -> natural { divine p { n? => 0, n?? => 1, _ => 2 } };"
+            ),
+            "n?? => 1"
+        );
+        assert_eq!(
+            unreachable("g = charm(p: natural?) -> natural { divine p { n? => n, _ => 1 } };"),
+            ""
+        );
+    }
+
+    #[test]
+    fn components_are_not_generalised() {
+        let errors = errors("f = charm(g) { (g(1), g(\"one\")) };");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(
+            errors[0].contains("expected natural, found phrase"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn instances_are_checked_against_declarations() {
+        let found = errors("id = charm(x) { x };\nx: phrase = id(1);");
+        assert_eq!(found.len(), 1, "{found:?}");
+        let found = errors("pair = charm[T](x: T) -> (T, T) { (x, 1) };");
+        assert_eq!(found.len(), 1, "{found:?}");
+    }
+}
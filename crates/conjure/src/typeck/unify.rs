@@ -0,0 +1,318 @@
+//! Type variables, and the substitution that solves them.
+//!
+//! Type variables come in two kinds. Rigid variables are the type parameters of generic charms and
+//! spells; inside their definition they stand for an unknown type and only match themselves.
+//! Flexible variables are introduced by inference for types that are not declared, and take on
+//! whatever type they are first required to match.
+//!
+//! Named types stand for their definitions, which are unfolded where their structure matters. A
+//! named type may be recursive, so two of them are compared on the assumption that they match,
+//! which holds unless something else contradicts it.
+
+use std::collections::HashMap;
+
+use crate::ast::{CharmType, FieldType, SpellType, Symbol, Type, VariantType};
+
+use super::{field_type, variant_type};
+
+/// Whether a type variable was introduced by inference rather than declared. Inferred variables
+/// are named so that they cannot be written in source.
+pub fn is_flexible(name: &str) -> bool {
+    name.starts_with('\'')
+}
+
+/// Rebuilds a type bottom-up, replacing every part for which `f` returns a type.
+pub fn transform(ty: &Type, f: &mut dyn FnMut(&Type) -> Option<Type>) -> Type {
+    if let Some(replacement) = f(ty) {
+        return replacement;
+    }
+    fn all(types: &[Type], f: &mut dyn FnMut(&Type) -> Option<Type>) -> Vec<Type> {
+        types.iter().map(|t| transform(t, f)).collect()
+    }
+    match ty {
+        Type::Inferred
+        | Type::Nil
+        | Type::Symbol(_)
+        | Type::Named(..)
+        | Type::Variable(_)
+        | Type::Simple(_)
+        | Type::Type => ty.clone(),
+        Type::Optional(inner) => Type::Optional(Box::new(transform(inner, f))),
+        Type::Sequence(element) => Type::Sequence(Box::new(transform(element, f))),
+        Type::Conjoined(types) => Type::Conjoined(all(types, f)),
+        Type::Record(fields) => Type::Record(
+            fields
+                .iter()
+                .map(|field| FieldType {
+                    symbol: field.symbol.clone(),
+                    ty: transform(&field.ty, f),
+                })
+                .collect(),
+        ),
+        Type::Union(variants) => Type::Union(
+            variants
+                .iter()
+                .map(|variant| VariantType {
+                    tag: variant.tag.clone(),
+                    payload: variant.payload.as_ref().map(|p| transform(p, f)),
+                })
+                .collect(),
+        ),
+        Type::Charm(charm) => Type::Charm(CharmType {
+            components: all(&charm.components, f),
+            product: Box::new(transform(&charm.product, f)),
+        }),
+        Type::Spell(spell) => Type::Spell(SpellType {
+            components: all(&spell.components, f),
+            product: Box::new(transform(&spell.product, f)),
+            fails: spell.fails,
+            capabilities: spell.capabilities.clone(),
+        }),
+    }
+}
+
+/// The names of the type variables occurring in a type, each once, in order of occurrence.
+pub fn variables(ty: &Type) -> Vec<String> {
+    let mut names = Vec::new();
+    transform(ty, &mut |t| {
+        if let Type::Variable(symbol) = t {
+            if !names.contains(&symbol.name) {
+                names.push(symbol.name.clone());
+            }
+        }
+        None
+    });
+    names
+}
+
+/// The types inferred for flexible type variables so far, and the definitions of named types.
+#[derive(Debug, Clone, Default)]
+pub struct Substitution {
+    types: HashMap<String, Type>,
+    next: usize,
+    /// The type every named type stands for, keyed by its number.
+    definitions: HashMap<usize, Type>,
+    /// The pairs of named types that are being compared, and so are assumed to be compatible.
+    assumptions: Vec<(usize, usize)>,
+}
+
+impl Substitution {
+    /// A flexible variable that has not been used before.
+    pub fn fresh(&mut self) -> Type {
+        self.next += 1;
+        Type::Variable(Symbol::new(format!("'{}", self.next), Default::default()))
+    }
+
+    /// Replaces a type that is a solved variable with its solution, until it is not.
+    pub fn shallow(&self, ty: &Type) -> Type {
+        let mut ty = ty;
+        while let Type::Variable(symbol) = ty {
+            match self.types.get(&symbol.name) {
+                Some(solution) => ty = solution,
+                None => break,
+            }
+        }
+        ty.clone()
+    }
+
+    /// Like [`Self::shallow`], but also replaces a named type with its definition, until the type
+    /// is neither a solved variable nor a named type.
+    pub fn unfold(&self, ty: &Type) -> Type {
+        let mut ty = self.shallow(ty);
+        // Definitions are contractive, so this ends.
+        while let Type::Named(_, id) = &ty {
+            let definition = self.definitions.get(id);
+            ty = self.shallow(definition.unwrap_or(&Type::Inferred));
+        }
+        ty
+    }
+
+    /// Records the type the named type numbered `id` stands for.
+    pub fn define(&mut self, id: usize, ty: Type) {
+        self.definitions.insert(id, ty);
+    }
+
+    /// Whether a definition of the named type numbered `id` contains a value constructor before
+    /// every reference to itself, so that unfolding it makes progress.
+    pub fn is_contractive(&self, id: usize, definition: &Type) -> bool {
+        let mut ty = definition.clone();
+        loop {
+            ty = match self.shallow(&ty) {
+                Type::Named(_, n) if n == id => return false,
+                Type::Named(_, n) => match self.definitions.get(&n) {
+                    Some(definition) => definition.clone(),
+                    None => return true,
+                },
+                Type::Optional(inner) => *inner,
+                _ => return true,
+            }
+        }
+    }
+
+    /// Replaces every solved variable in a type with its solution.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        transform(ty, &mut |t| match t {
+            Type::Variable(symbol) => Some(match self.types.get(&symbol.name) {
+                Some(solution) => self.resolve(solution),
+                None => t.clone(),
+            }),
+            _ => None,
+        })
+    }
+
+    /// Solves a flexible variable, unless the solution would contain the variable itself.
+    pub fn assign(&mut self, name: &str, ty: &Type) -> bool {
+        let ty = self.resolve(ty);
+        if let Type::Variable(symbol) = &ty {
+            if symbol.name == name {
+                return true;
+            }
+        }
+        if variables(&ty).iter().any(|v| v == name) {
+            return false;
+        }
+        self.types.insert(name.to_string(), ty);
+        true
+    }
+
+    /// Whether a value of type `actual` may be used where `expected` is required, solving
+    /// flexible variables as needed.
+    pub fn compatible(&mut self, expected: &Type, actual: &Type) -> bool {
+        let expected = self.shallow(expected);
+        let actual = self.shallow(actual);
+        match (&expected, &actual) {
+            (Type::Inferred, _) | (_, Type::Inferred) => true,
+            (Type::Variable(e), Type::Variable(a)) if e == a => true,
+            (Type::Variable(v), other) | (other, Type::Variable(v)) if is_flexible(v) => {
+                self.assign(&v.name, other)
+            }
+            (Type::Named(_, e), Type::Named(_, a)) if e == a => true,
+            (Type::Named(_, e), Type::Named(_, a)) => {
+                if self.assumptions.contains(&(*e, *a)) {
+                    return true;
+                }
+                self.assumptions.push((*e, *a));
+                let compatible = self.compatible(&self.unfold(&expected), &self.unfold(&actual));
+                self.assumptions.pop();
+                compatible
+            }
+            (Type::Named(..), actual) => self.compatible(&self.unfold(&expected), actual),
+            (expected, Type::Named(..)) => self.compatible(expected, &self.unfold(&actual)),
+            (Type::Optional(_), Type::Nil) => true,
+            (Type::Optional(expected), Type::Optional(actual)) => self.compatible(expected, actual),
+            (Type::Optional(expected), actual) => self.compatible(expected, actual),
+            (Type::Nil, Type::Nil) | (Type::Type, Type::Type) => true,
+            (Type::Simple(expected), Type::Simple(actual)) => expected == actual,
+            (Type::Conjoined(expected), Type::Conjoined(actual)) => {
+                self.all_compatible(expected, actual)
+            }
+            (Type::Record(expected), Type::Record(actual)) => {
+                expected.len() == actual.len()
+                    && expected.iter().all(|e| {
+                        field_type(actual, &e.symbol).is_some_and(|a| self.compatible(&e.ty, a))
+                    })
+            }
+            (Type::Sequence(expected), Type::Sequence(actual)) => self.compatible(expected, actual),
+            (Type::Union(expected), Type::Union(actual)) => actual.iter().all(|a| {
+                variant_type(expected, &a.tag).is_some_and(|e| match (&e.payload, &a.payload) {
+                    (Some(expected), Some(actual)) => self.compatible(expected, actual),
+                    (expected, actual) => expected.is_none() && actual.is_none(),
+                })
+            }),
+            (Type::Charm(expected), Type::Charm(actual)) => {
+                self.all_compatible(&actual.components, &expected.components)
+                    && self.compatible(&expected.product, &actual.product)
+            }
+            (Type::Spell(expected), Type::Spell(actual)) => {
+                (expected.fails || !actual.fails)
+                    && expected.capabilities.is_superset(&actual.capabilities)
+                    && self.all_compatible(&actual.components, &expected.components)
+                    && self.compatible(&expected.product, &actual.product)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn all_compatible(&mut self, expected: &[Type], actual: &[Type]) -> bool {
+        expected.len() == actual.len()
+            && expected
+                .iter()
+                .zip(actual)
+                .all(|(e, a)| self.compatible(e, a))
+    }
+
+    /// The narrowest type of which both `a` and `b` are instances, if any, solving flexible
+    /// variables as needed.
+    pub fn unite(&mut self, a: &Type, b: &Type) -> Option<Type> {
+        let a = self.shallow(a);
+        let b = self.shallow(b);
+        match (&a, &b) {
+            (Type::Inferred, other) | (other, Type::Inferred) => Some(other.clone()),
+            (Type::Variable(x), Type::Variable(y)) if x == y => Some(a.clone()),
+            (Type::Variable(v), other) | (other, Type::Variable(v)) if is_flexible(v) => {
+                self.assign(&v.name, other).then(|| other.clone())
+            }
+            (Type::Nil, Type::Nil) => Some(Type::Nil),
+            (Type::Nil, Type::Optional(inner)) | (Type::Optional(inner), Type::Nil) => {
+                Some(Type::Optional(inner.clone()))
+            }
+            (Type::Nil, other) | (other, Type::Nil) => {
+                Some(Type::Optional(Box::new(other.clone())))
+            }
+            (Type::Named(_, x), Type::Named(_, y)) if x == y => Some(a.clone()),
+            (Type::Named(..), other) | (other, Type::Named(..)) => {
+                let named = if matches!(a, Type::Named(..)) { &a } else { &b };
+                if self.compatible(named, other) {
+                    Some(named.clone())
+                } else if self.compatible(other, named) {
+                    Some(other.clone())
+                } else {
+                    self.unite(&self.unfold(named), other)
+                }
+            }
+            (Type::Optional(a), Type::Optional(b)) => {
+                self.unite(a, b).map(|t| Type::Optional(Box::new(t)))
+            }
+            (Type::Optional(a), b) | (b, Type::Optional(a)) => {
+                self.unite(a, b).map(|t| Type::Optional(Box::new(t)))
+            }
+            (Type::Sequence(a), Type::Sequence(b)) => {
+                self.unite(a, b).map(|t| Type::Sequence(Box::new(t)))
+            }
+            (Type::Union(a), Type::Union(b)) => {
+                let mut variants = a.clone();
+                for variant in b {
+                    match variants.iter().position(|v| v.tag == variant.tag) {
+                        Some(i) => {
+                            let payload = match (variants[i].payload.clone(), &variant.payload) {
+                                (Some(a), Some(b)) => Some(self.unite(&a, b)?),
+                                (None, None) => None,
+                                _ => return None,
+                            };
+                            variants[i].payload = payload;
+                        }
+                        None => variants.push(variant.clone()),
+                    }
+                }
+                Some(Type::Union(variants))
+            }
+            // A spell that cannot fail is also one that may, and a spell using some capabilities
+            // is also one using more.
+            (Type::Spell(x), Type::Spell(y))
+                if x.fails != y.fails || x.capabilities != y.capabilities =>
+            {
+                let widen = |spell: &SpellType| {
+                    Type::Spell(SpellType {
+                        fails: x.fails || y.fails,
+                        capabilities: x.capabilities.union(&y.capabilities).copied().collect(),
+                        ..spell.clone()
+                    })
+                };
+                let (x, y) = (widen(x), widen(y));
+                (self.compatible(&x, &y) && self.compatible(&y, &x)).then_some(x)
+            }
+            (a, b) if self.compatible(a, b) && self.compatible(b, a) => Some(a.clone()),
+            _ => None,
+        }
+    }
+}